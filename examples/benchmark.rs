@@ -25,6 +25,7 @@ use ili9486::io::shim::OutputOnlyIoPin;
 use ili9486::io::stm32f1xx::gpioa::GPIOA;
 use ili9486::io::stm32f1xx::gpiob::GPIOB;
 use ili9486::io::stm32f1xx::gpioc::GPIOC;
+use ili9486::stats::Counting;
 use ili9486::{Command, Commands, ILI9486};
 
 extern crate panic_semihosting;
@@ -115,9 +116,17 @@ fn main() -> ! {
     let pb9 = OutputOnlyIoPin::new(gpiob.pb9.into_push_pull_output(&mut gpiob.crh));
     let pc7 = OutputOnlyIoPin::new(gpioc.pc7.into_push_pull_output(&mut gpioc.crl));
 
-    let parallel_gpio =
-        GPIO8ParallelInterface::new(pa5, pa6, pa7, pa8, pa9, pa10, pc7, pb10, pb6, pb8, pb7, pb9)
-            .unwrap();
+    // count the bus traffic of the benchmarks
+    let parallel_gpio = Counting::new(
+        GPIO8ParallelInterface::new(
+            (pa5, pa6, pa7, pa8, pa9, pa10, pc7, pb10),
+            pb6,
+            pb8,
+            pb7,
+            pb9,
+        )
+        .unwrap(),
+    );
     let mut lcd_driver = ILI9486::new(&mut delay, PixelFormat::Rgb565, parallel_gpio, pb5).unwrap();
 
     // reset
@@ -130,7 +139,7 @@ fn main() -> ! {
 
     // MADCTL settings
     lcd_driver
-        .write_command(Command::MemoryAccessControl, &[0b10001000])
+        .set_memory_access_control(0b10001000)
         .unwrap();
 
     lcd_driver.clear_screen().unwrap();
@@ -155,6 +164,7 @@ fn main() -> ! {
     let screen_fill_rect = elapsed_millis() - start;
 
     // benchmark screen fill (pixel by pixel)
+    let before = lcd_driver.interface().stats();
     start = elapsed_millis();
     lcd_driver.column_address_set(0, 319);
     lcd_driver.page_address_set(0, 479);
@@ -164,6 +174,7 @@ fn main() -> ! {
         }
     }
     let screen_fill_pixel = elapsed_millis() - start;
+    let screen_fill_pixel_stats = lcd_driver.interface().stats() - before;
 
     let t = Text::new("Hello Rust (and ILI9486 display)!", Point::new(64, 175))
         .into_styled(TextStyle::new(Font6x8, Rgb888::GREEN));
@@ -198,6 +209,19 @@ fn main() -> ! {
     .draw(&mut lcd_driver)
     .unwrap();
 
+    Text::new("redundant window writes: ", Point::new(64, 350))
+        .into_styled(TextStyle::new(Font6x8, Rgb888::BLUE))
+        .draw(&mut lcd_driver)
+        .unwrap();
+
+    Text::new(
+        (screen_fill_pixel_stats.redundant_window_writes).numtoa_str(10, &mut buf),
+        Point::new(64, 380),
+    )
+    .into_styled(TextStyle::new(Font6x8, Rgb888::BLUE))
+    .draw(&mut lcd_driver)
+    .unwrap();
+
     let tga = Tga::from_slice(include_bytes!("../test/rust-rle-bw-topleft.tga")).unwrap();
 
     let image: Image<Tga, Rgb888> = Image::new(
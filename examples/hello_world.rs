@@ -118,9 +118,14 @@ fn main() -> ! {
         &gpioc_crl_ref,
     );
 
-    let parallel_gpio =
-        GPIO8ParallelInterface::new(pa5, pa6, pa7, pa8, pa9, pa10, pc7, pb10, pb6, pb8, pb7, pb9)
-            .unwrap();
+    let parallel_gpio = GPIO8ParallelInterface::new(
+        (pa5, pa6, pa7, pa8, pa9, pa10, pc7, pb10),
+        pb6,
+        pb8,
+        pb7,
+        pb9,
+    )
+    .unwrap();
     let mut lcd_driver = ILI9486::new(&mut delay, PixelFormat::Rgb565, parallel_gpio, pb5).unwrap();
 
     // reset
@@ -133,7 +138,7 @@ fn main() -> ! {
 
     // MADCTL settings
     lcd_driver
-        .write_command(Command::MemoryAccessControl, &[0b10001000])
+        .set_memory_access_control(0b10001000)
         .unwrap();
 
     lcd_driver.clear_screen().unwrap();
@@ -18,13 +18,12 @@ use embedded_graphics::{
 
 use ili9486::color::PixelFormat;
 use ili9486::io::shim::OutputOnlyIoPin;
+use ili9486::spi::SpiInterface;
 use ili9486::io::stm32f1xx::gpioa::GPIOA;
 use ili9486::io::stm32f1xx::gpiob::GPIOB;
 use ili9486::io::stm32f1xx::gpioc::GPIOC;
 use ili9486::{Command, Commands, ILI9486};
 
-use display_interface_spi::SPIInterface;
-
 extern crate panic_semihosting;
 
 use core::cell::RefCell;
@@ -73,7 +72,7 @@ fn main() -> ! {
     };
     let spi = Spi::spi2(dp.SPI2, pins, spi_mode, 8.mhz(), clocks, &mut rcc.apb1);
 
-    let display_spi = SPIInterface::new(spi, dc, cs);
+    let display_spi = SpiInterface::new(spi, dc, cs).unwrap();
 
     let mut lcd_driver = ILI9486::new(
         &mut delay,
@@ -93,7 +92,7 @@ fn main() -> ! {
 
     // MADCTL settings
     lcd_driver
-        .write_command(Command::MemoryAccessControl, &[0b10001000])
+        .set_memory_access_control(0b10001000)
         .unwrap();
 
     lcd_driver.clear_screen().unwrap();
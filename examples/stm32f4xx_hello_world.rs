@@ -79,7 +79,7 @@ fn main() -> ! {
     let pc7 = GPIOC::PC7::<PullDown, PushPull>(gpioc.pc7.into_pull_down_input());
 
     let parallel_gpio =
-        GPIO8ParallelInterface::new(pa5, pa6, pa7, pa8, pa9, pa10, pc7, pb10, pb6, pb8, pb7, pb9)
+        GPIO8ParallelInterface::new((pa5, pa6, pa7, pa8, pa9, pa10, pc7, pb10), pb6, pb8, pb7, pb9)
             .unwrap();
     let mut lcd_driver = ILI9486::new(&mut delay, PixelFormat::Rgb565, parallel_gpio, pb5).unwrap();
 
@@ -93,7 +93,7 @@ fn main() -> ! {
 
     // MADCTL settings
     lcd_driver
-        .write_command(Command::MemoryAccessControl, &[0b10001000])
+        .set_memory_access_control(0b10001000)
         .unwrap();
 
     lcd_driver.clear_screen().unwrap();
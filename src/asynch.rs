@@ -0,0 +1,647 @@
+//! Async driver, requires the `async` feature.
+//!
+//! [AsyncILI9486](AsyncILI9486) is the counterpart of [ILI9486](crate::ILI9486) for async
+//! executors, e.g. embassy, on top of [AsyncWriteInterface](AsyncWriteInterface) and
+//! [AsyncReadInterface](AsyncReadInterface). Commands are encoded with the same
+//! [DcsCommand](crate::dcs::DcsCommand)s and pixels with the same
+//! [PixelWriter](crate::color::PixelWriter) and [PixelReader](crate::color::PixelReader). Pixels
+//! are written 32 at a time from a buffer with a single `await`, so other tasks run during fills,
+//! and an area is read back in a single read transaction.
+//!
+//! [Blocking](Blocking) turns blocking interfaces and delays into async ones and
+//! [block_on](block_on) runs a future without an executor, so the driver can be tested on the
+//! host, e.g. against the emulator:
+//!
+//! ```ignore
+//! let mut lcd = block_on(AsyncILI9486::new(
+//!     &mut Blocking(NoDelay),
+//!     PixelFormat::Rgb565,
+//!     Blocking(Emulator::new()),
+//!     OutputOnlyIoPin::new(NoPin),
+//! ))?;
+//! block_on(lcd.fill_rect(0, 0, 10, 10, (31, 0, 0)))?;
+//! assert_eq!(lcd.writer().0.pixel(0, 0), (63, 0, 0));
+//! ```
+
+use crate::buffer::{BufferReader, BufferWriter};
+use crate::color::{PixelFormat, PixelReader, PixelWriter, RGBPixel, ReadbackWord};
+use crate::dcs::{self, DcsCommand, DcsRead, MAX_PARAMETERS, MAX_RESPONSE_LEN};
+use crate::{health, Command, IoPin};
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use display_interface::v2::*;
+use display_interface::DisplayError;
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::OutputPin;
+
+/// Number of pixels encoded or decoded per transfer.
+const CHUNK_PIXELS: usize = 32;
+
+/// Buffer size for `CHUNK_PIXELS` pixels in the largest encoding, 3 words per pixel.
+const CHUNK_WORDS: usize = 3 * CHUNK_PIXELS;
+
+/// Writes words to the controller.
+#[allow(async_fn_in_trait)]
+pub trait AsyncWriteInterface<T> {
+    /// Writes `buf` as commands or data.
+    async fn write(&mut self, mode: WriteMode, buf: &[T]) -> Result<(), DisplayError>;
+}
+
+/// Reads words from the controller.
+#[allow(async_fn_in_trait)]
+pub trait AsyncReadInterface<T> {
+    /// Reads words in a single transaction and passes them to `f` until it returns `false`.
+    async fn read_stream(&mut self, f: &mut dyn FnMut(T) -> bool) -> Result<(), DisplayError>;
+
+    /// Reads `buf.len()` words in a single transaction.
+    async fn read(&mut self, buf: &mut [T]) -> Result<(), DisplayError> {
+        let len = buf.len();
+        if len == 0 {
+            return Ok(());
+        }
+        let mut index = 0;
+        self.read_stream(&mut |word| {
+            buf[index] = word;
+            index += 1;
+            index < len
+        })
+        .await
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait AsyncDelayUs {
+    async fn delay_us(&mut self, us: u32);
+}
+
+/// Runs a blocking interface or delay in an async driver, the futures complete on the first poll.
+pub struct Blocking<I>(pub I);
+
+impl<I, T> AsyncWriteInterface<T> for Blocking<I>
+where
+    I: WriteInterface<T>,
+{
+    async fn write(&mut self, mode: WriteMode, buf: &[T]) -> Result<(), DisplayError> {
+        self.0.write(mode, buf)
+    }
+}
+
+impl<I, T> AsyncReadInterface<T> for Blocking<I>
+where
+    I: ReadInterface<T>,
+{
+    async fn read_stream(&mut self, f: &mut dyn FnMut(T) -> bool) -> Result<(), DisplayError> {
+        self.0.read_stream(f)
+    }
+
+    async fn read(&mut self, buf: &mut [T]) -> Result<(), DisplayError> {
+        self.0.read(buf)
+    }
+}
+
+impl<D> AsyncDelayUs for Blocking<D>
+where
+    D: DelayUs<u32>,
+{
+    async fn delay_us(&mut self, us: u32) {
+        self.0.delay_us(us)
+    }
+}
+
+fn noop_raw_waker() -> RawWaker {
+    fn clone(_data: *const ()) -> RawWaker {
+        noop_raw_waker()
+    }
+    fn noop(_data: *const ()) {}
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    RawWaker::new(core::ptr::null(), &VTABLE)
+}
+
+/// Polls `future` until it completes, without an executor.
+///
+/// The waker does nothing and the future is polled again right away, which is fine for tests
+/// and for interfaces like [Blocking](Blocking) that never wait on an interrupt.
+pub fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    let mut future = pin!(future);
+    // the vtable functions ignore the data pointer, so the waker is always valid
+    #[allow(unsafe_code)]
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut context = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+/// An async driver for the ILI9486 LCD controller.
+pub struct AsyncILI9486<RW, T>
+where
+    RW: AsyncWriteInterface<T> + AsyncReadInterface<T>,
+{
+    rw_interface: RW,
+    color_mode: PixelFormat,
+    madctl: u8,
+    /// Whether the interface can read from the controller
+    can_read: bool,
+    _marker: PhantomData<T>,
+}
+
+impl<RW, T> AsyncILI9486<RW, T>
+where
+    RW: AsyncWriteInterface<T> + AsyncReadInterface<T>,
+    T: From<u8> + Into<u32> + Copy + Default + ReadbackWord,
+    for<'b> BufferWriter<'b, T>: PixelWriter<T>,
+    for<'b> BufferReader<'b, T>: PixelReader<T>,
+{
+    /// Resets the controller with `rst` and sets the pixel format, like
+    /// [ILI9486::new](crate::ILI9486::new).
+    pub async fn new<D, RST>(
+        delay: &mut D,
+        color_mode: PixelFormat,
+        rw_interface: RW,
+        mut rst: RST,
+    ) -> Result<AsyncILI9486<RW, T>, DisplayError>
+    where
+        D: AsyncDelayUs,
+        RST: IoPin,
+    {
+        let rst_output = rst.into_output();
+        wrap_output_err!(rst_output.set_low())?;
+        delay.delay_us(20).await;
+        wrap_output_err!(rst_output.set_high())?;
+        delay.delay_us(120_000).await;
+
+        let mut driver = AsyncILI9486 {
+            rw_interface,
+            color_mode,
+            madctl: 0,
+            can_read: true,
+            _marker: PhantomData,
+        };
+        driver.send(&dcs::InterfacePixelFormat(color_mode)).await?;
+
+        Ok(driver)
+    }
+
+    /// Runs the power-on sequence, like [ILI9486::init](crate::ILI9486::init).
+    pub async fn init<D>(&mut self, delay: &mut D) -> Result<(), DisplayError>
+    where
+        D: AsyncDelayUs,
+    {
+        self.send(&dcs::SoftReset).await?;
+        delay.delay_us(120_000).await;
+        self.send(&dcs::SleepOut).await?;
+        delay.delay_us(120_000).await;
+
+        self.send(&dcs::InterfacePixelFormat(self.color_mode))
+            .await?;
+        self.send(&dcs::MemoryAccessControl(self.madctl)).await?;
+
+        self.send(&dcs::NormalDisplayMode).await?;
+        self.send(&dcs::DisplayOn).await
+    }
+
+    /// Marks the interface as write-only, like
+    /// [ILI9486::write_only](crate::ILI9486::write_only).
+    pub fn write_only(mut self) -> Self {
+        self.can_read = false;
+        self
+    }
+
+    /// Whether the interface can read from the controller.
+    pub fn can_read(&self) -> bool {
+        self.can_read
+    }
+
+    /// The pixel format used for drawing.
+    pub fn color_mode(&self) -> PixelFormat {
+        self.color_mode
+    }
+
+    pub fn writer(&mut self) -> &mut RW {
+        &mut self.rw_interface
+    }
+
+    /// Sends a command and its parameters.
+    pub async fn send<C>(&mut self, command: &C) -> Result<(), DisplayError>
+    where
+        C: DcsCommand,
+    {
+        self.rw_interface
+            .write(WriteMode::Command, &[(C::COMMAND as u8).into()])
+            .await?;
+
+        let parameters = command.encode();
+        let mut data = [T::default(); MAX_PARAMETERS];
+        for chunk in parameters.as_ref().chunks(MAX_PARAMETERS) {
+            for (word, byte) in data.iter_mut().zip(chunk) {
+                *word = (*byte).into();
+            }
+            self.rw_interface
+                .write(WriteMode::Data, &data[..chunk.len()])
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Sends a read command and decodes the response.
+    pub async fn query<C>(&mut self, command: &C) -> Result<C::Response, DisplayError>
+    where
+        C: DcsRead,
+    {
+        self.send(command).await?;
+
+        // the response follows the dummy reads
+        let dummy = C::COMMAND.dummy_reads();
+        let mut data = [T::default(); MAX_RESPONSE_LEN + 1];
+        self.rw_interface
+            .read(&mut data[..dummy + C::RESPONSE_LEN])
+            .await?;
+
+        let mut response = [0u8; MAX_RESPONSE_LEN];
+        for (byte, word) in response.iter_mut().zip(&data[dummy..]) {
+            *byte = (*word).into() as u8;
+        }
+        Ok(C::decode(&response[..C::RESPONSE_LEN]))
+    }
+
+    pub async fn set_memory_access_control(&mut self, madctl: u8) -> Result<(), DisplayError> {
+        self.send(&dcs::MemoryAccessControl(madctl)).await?;
+        self.madctl = madctl;
+        Ok(())
+    }
+
+    /// Programs the address window of an area and sends `command`, `MemoryWrite` or
+    /// `MemoryRead`.
+    async fn start_area<C>(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        command: &C,
+    ) -> Result<(), DisplayError>
+    where
+        C: DcsCommand,
+    {
+        let (columns, pages) = dcs::address_window(x, y, width, height);
+        self.send(&columns).await?;
+        self.send(&pages).await?;
+        self.send(command).await
+    }
+
+    pub async fn draw_pixel(
+        &mut self,
+        x: u16,
+        y: u16,
+        color: RGBPixel,
+    ) -> Result<(), DisplayError> {
+        self.fill_rect(x, y, 1, 1, color).await
+    }
+
+    /// Fills an area with `color`, in the components of the color mode.
+    pub async fn fill_rect(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: RGBPixel,
+    ) -> Result<(), DisplayError> {
+        let mut remaining = width as usize * height as usize;
+        if remaining == 0 {
+            return Ok(());
+        }
+        self.start_area(x, y, width, height, &dcs::MemoryWrite)
+            .await?;
+
+        // every full chunk encodes the same
+        let mut words = [T::default(); CHUNK_WORDS];
+        let mut writer = BufferWriter::new(&mut words);
+        writer.write_repeated_pixel_data(&self.color_mode, &color, remaining.min(CHUNK_PIXELS))?;
+        let chunk = writer.len();
+        while remaining >= CHUNK_PIXELS {
+            self.rw_interface
+                .write(WriteMode::Data, &words[..chunk])
+                .await?;
+            remaining -= CHUNK_PIXELS;
+        }
+
+        if remaining > 0 {
+            let mut writer = BufferWriter::new(&mut words);
+            writer.write_repeated_pixel_data(&self.color_mode, &color, remaining)?;
+            let tail = writer.release();
+            self.rw_interface.write(WriteMode::Data, tail).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the pixels of an area, row by row, in the components of the color mode.
+    pub async fn write_pixels(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        pixels: &mut dyn Iterator<Item = RGBPixel>,
+    ) -> Result<(), DisplayError> {
+        let area = width as usize * height as usize;
+        if area == 0 {
+            return Ok(());
+        }
+        self.start_area(x, y, width, height, &dcs::MemoryWrite)
+            .await?;
+
+        // the copy of a lone last pixel of a packing format would land after the area, the
+        // pixel is drawn on its own instead
+        let lone = area % 2 == 1 && BufferWriter::new(&mut []).packs_pixels(&self.color_mode);
+        let mut pixels = pixels.take(area);
+        let mut words = [T::default(); CHUNK_WORDS];
+        let mut remaining = area - lone as usize;
+        while remaining > 0 {
+            // chunks hold an even number of pixels, so pairs are never split
+            let mut writer = BufferWriter::new(&mut words);
+            let chunk = remaining.min(CHUNK_PIXELS);
+            writer.write_pixels(&self.color_mode, &mut pixels.by_ref().take(chunk))?;
+            let chunk_words = writer.release();
+            if chunk_words.is_empty() {
+                return Ok(());
+            }
+            self.rw_interface
+                .write(WriteMode::Data, chunk_words)
+                .await?;
+            remaining -= chunk;
+        }
+
+        match pixels.next() {
+            Some(last) if lone => self.draw_pixel(x + width - 1, y + height - 1, last).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Fills the screen with black, in the orientation set with
+    /// [set_memory_access_control](AsyncILI9486::set_memory_access_control).
+    pub async fn clear_screen(&mut self) -> Result<(), DisplayError> {
+        let (columns, pages) = dcs::MemoryAccessControl(self.madctl).dimensions();
+        self.fill_rect(0, 0, columns, pages, (0, 0, 0)).await
+    }
+
+    /// Reads the pixels of an area, row by row, like
+    /// [ILI9486::read_pixels](crate::ILI9486::read_pixels).
+    pub async fn read_pixels(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        pixels: &mut [RGBPixel],
+    ) -> Result<(), DisplayError> {
+        let len = pixels.len().min(width as usize * height as usize);
+        if len == 0 {
+            return Ok(());
+        }
+        let pixels = &mut pixels[..len];
+        self.start_area(x, y, width, height, &dcs::MemoryRead)
+            .await?;
+
+        // the area is read in one transaction and decoded a group of words at a time
+        let dummy = Command::MemoryRead.dummy_reads();
+        let total = dummy + T::readback_words(len);
+        let mut group = [T::default(); 3];
+        let mut index = 0;
+        let mut decoded = 0;
+        let mut result = Ok(());
+        self.rw_interface
+            .read_stream(&mut |word| {
+                if index >= dummy {
+                    let offset = (index - dummy) % T::GROUP_WORDS;
+                    group[offset] = word;
+                    if offset + 1 == T::GROUP_WORDS {
+                        let count = T::GROUP_PIXELS.min(len - decoded);
+                        result = BufferReader::new(&group[..T::GROUP_WORDS])
+                            .read_pixel_data(0, &mut pixels[decoded..decoded + count]);
+                        decoded += count;
+                    }
+                }
+                index += 1;
+                index < total && result.is_ok()
+            })
+            .await?;
+        result?;
+
+        let max = PixelFormat::Rgb666.max_components();
+        for pixel in pixels.iter_mut() {
+            *pixel = self.color_mode.convert(pixel, &max);
+        }
+        Ok(())
+    }
+}
+
+impl<RW, T> AsyncILI9486<RW, T>
+where
+    RW: AsyncWriteInterface<T> + AsyncReadInterface<T>,
+    T: From<u8> + Into<u32> + Copy + Default + ReadbackWord,
+    for<'b> BufferWriter<'b, T>: PixelWriter<T>,
+    for<'b> BufferReader<'b, T>: PixelReader<T>,
+{
+    /// Switches the pixel format, like [ILI9486::set_color_mode](crate::ILI9486::set_color_mode).
+    pub async fn set_color_mode(&mut self, color_mode: PixelFormat) -> Result<(), DisplayError> {
+        if !BufferWriter::new(&mut []).supports_pixel_format(&color_mode) {
+            return Err(DisplayError::DataFormatNotImplemented);
+        }
+
+        self.send(&dcs::InterfacePixelFormat(color_mode)).await?;
+        // write-only interfaces use the format as sent
+        if self.can_read {
+            let response = self.query(&dcs::ReadDisplayPixelFormat).await;
+            if let Err(error) = health::verify_pixel_format(response, &color_mode) {
+                // pixels keep being encoded in the current color mode
+                let previous = self.color_mode;
+                self.send(&dcs::InterfacePixelFormat(previous)).await?;
+                return Err(error);
+            }
+        }
+
+        self.color_mode = color_mode;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::emulator::{Emulator, NoDelay, NoPin};
+    use crate::io::shim::OutputOnlyIoPin;
+    use std::vec::Vec;
+
+    /// An emulator that counts the read transactions.
+    struct Transactions {
+        emulator: Emulator,
+        reads: usize,
+    }
+
+    impl<T> AsyncWriteInterface<T> for Transactions
+    where
+        Emulator: WriteInterface<T>,
+    {
+        async fn write(&mut self, mode: WriteMode, buf: &[T]) -> Result<(), DisplayError> {
+            self.emulator.write(mode, buf)
+        }
+    }
+
+    impl<T> AsyncReadInterface<T> for Transactions
+    where
+        Emulator: ReadInterface<T>,
+    {
+        async fn read_stream(&mut self, f: &mut dyn FnMut(T) -> bool) -> Result<(), DisplayError> {
+            self.reads += 1;
+            self.emulator.read_stream(f)
+        }
+    }
+
+    fn driver<RW, T>(color_mode: PixelFormat, rw_interface: RW) -> AsyncILI9486<RW, T>
+    where
+        RW: AsyncWriteInterface<T> + AsyncReadInterface<T>,
+        T: From<u8> + Into<u32> + Copy + Default + ReadbackWord,
+        for<'b> BufferWriter<'b, T>: PixelWriter<T>,
+        for<'b> BufferReader<'b, T>: PixelReader<T>,
+    {
+        let mut lcd = block_on(AsyncILI9486::new(
+            &mut Blocking(NoDelay),
+            color_mode,
+            rw_interface,
+            OutputOnlyIoPin::new(NoPin),
+        ))
+        .unwrap();
+        block_on(lcd.init(&mut Blocking(NoDelay))).unwrap();
+        lcd
+    }
+
+    /// Distinct pixels that fit the components of both color modes.
+    fn pattern(len: usize) -> Vec<RGBPixel> {
+        (0..len)
+            .map(|i| (i as u8 % 32, (i * 3) as u8 % 64, (i * 7) as u8 % 32))
+            .collect()
+    }
+
+    #[test]
+    fn fills_rects() {
+        let mut lcd: AsyncILI9486<_, u8> = driver(PixelFormat::Rgb565, Blocking(Emulator::new()));
+        // more than a chunk, with a partial last one
+        block_on(lcd.fill_rect(10, 20, 40, 2, (31, 0, 0))).unwrap();
+
+        let emulator = &lcd.writer().0;
+        assert_eq!(emulator.column_window(), (10, 49));
+        assert_eq!(emulator.page_window(), (20, 21));
+        assert_eq!(emulator.pixel(10, 20), (0x3f, 0, 0));
+        assert_eq!(emulator.pixel(49, 21), (0x3f, 0, 0));
+        assert_eq!(emulator.pixel(50, 21), (0, 0, 0));
+        assert_eq!(emulator.pixel(10, 22), (0, 0, 0));
+
+        block_on(lcd.clear_screen()).unwrap();
+        assert!(lcd
+            .writer()
+            .0
+            .gram()
+            .iter()
+            .all(|pixel| *pixel == (0, 0, 0)));
+
+        // rows and columns exchanged
+        block_on(lcd.set_memory_access_control(0x20)).unwrap();
+        block_on(lcd.fill_rect(400, 300, 10, 10, (31, 0, 0))).unwrap();
+        block_on(lcd.clear_screen()).unwrap();
+        let emulator = &lcd.writer().0;
+        assert_eq!(emulator.column_window(), (0, 479));
+        assert_eq!(emulator.page_window(), (0, 319));
+        assert!(emulator.gram().iter().all(|pixel| *pixel == (0, 0, 0)));
+    }
+
+    #[test]
+    fn writes_pixels() {
+        // 16-bit RGB666 packs two pixels in three words, the area has a lone last pixel
+        let mut lcd: AsyncILI9486<_, u16> = driver(PixelFormat::Rgb666, Blocking(Emulator::new()));
+        let drawn = pattern(35);
+        block_on(lcd.write_pixels(3, 4, 5, 7, &mut drawn.iter().copied())).unwrap();
+
+        let emulator = &lcd.writer().0;
+        for (i, pixel) in drawn.iter().enumerate() {
+            let (x, y) = (3 + i % 5, 4 + i / 5);
+            assert_eq!(emulator.pixel(x, y), *pixel, "({}, {})", x, y);
+        }
+        assert_eq!(emulator.pixel(3, 11), (0, 0, 0));
+    }
+
+    fn readback<T>(color_mode: PixelFormat)
+    where
+        Emulator: WriteInterface<T> + ReadInterface<T>,
+        T: From<u8> + Into<u32> + Copy + Default + ReadbackWord,
+        for<'b> BufferWriter<'b, T>: PixelWriter<T>,
+        for<'b> BufferReader<'b, T>: PixelReader<T>,
+    {
+        let interface = Transactions {
+            emulator: Emulator::new(),
+            reads: 0,
+        };
+        let mut lcd: AsyncILI9486<_, T> = driver(color_mode, interface);
+        let drawn = pattern(35);
+        block_on(lcd.write_pixels(100, 200, 5, 7, &mut drawn.iter().copied())).unwrap();
+
+        let reads = lcd.writer().reads;
+        let mut pixels = [(0, 0, 0); 36];
+        block_on(lcd.read_pixels(100, 200, 5, 7, &mut pixels)).unwrap();
+        assert_eq!(pixels[..35], drawn[..], "{:?}", color_mode);
+        // only the area is read
+        assert_eq!(pixels[35], (0, 0, 0));
+        assert_eq!(lcd.writer().reads - reads, 1);
+    }
+
+    #[test]
+    fn reads_pixels_in_one_transaction() {
+        for color_mode in [PixelFormat::Rgb565, PixelFormat::Rgb666].iter() {
+            readback::<u8>(*color_mode);
+            readback::<u16>(*color_mode);
+        }
+    }
+
+    #[test]
+    fn sets_color_mode() {
+        let mut lcd: AsyncILI9486<_, u8> = driver(PixelFormat::Rgb565, Blocking(Emulator::new()));
+        block_on(lcd.set_color_mode(PixelFormat::Rgb666)).unwrap();
+        assert_eq!(lcd.color_mode(), PixelFormat::Rgb666);
+        assert_eq!(lcd.writer().0.pixel_format(), Some(PixelFormat::Rgb666));
+    }
+
+    /// An emulator whose reads all return 0.
+    struct ZeroReads(Emulator);
+
+    impl AsyncWriteInterface<u8> for ZeroReads {
+        async fn write(&mut self, mode: WriteMode, buf: &[u8]) -> Result<(), DisplayError> {
+            self.0.write(mode, buf)
+        }
+    }
+
+    impl AsyncReadInterface<u8> for ZeroReads {
+        async fn read_stream(&mut self, f: &mut dyn FnMut(u8) -> bool) -> Result<(), DisplayError> {
+            while f(0) {}
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn color_mode_restored_on_mismatch() {
+        let mut lcd: AsyncILI9486<_, u8> = driver(PixelFormat::Rgb565, ZeroReads(Emulator::new()));
+        assert!(matches!(
+            block_on(lcd.set_color_mode(PixelFormat::Rgb666)),
+            Err(DisplayError::InvalidFormatError)
+        ));
+        assert_eq!(lcd.color_mode(), PixelFormat::Rgb565);
+        assert_eq!(lcd.writer().0.pixel_format(), Some(PixelFormat::Rgb565));
+    }
+}
@@ -0,0 +1,110 @@
+//! Interfaces over word buffers.
+//!
+//! [BufferWriter](BufferWriter) collects the words written to it, so pixels can be encoded
+//! with the same [PixelWriter](crate::color::PixelWriter) the bus uses before they are sent,
+//! e.g. by DMA. [BufferReader](BufferReader) returns the words of a buffer, so words read from
+//! the bus can be decoded with the [PixelReader](crate::color::PixelReader).
+
+use display_interface::v2::*;
+use display_interface::DisplayError;
+
+/// Collects written words in a buffer.
+///
+/// Writing past the end of the buffer returns `BusWriteError`, reads return
+/// `DataFormatNotImplemented`.
+pub struct BufferWriter<'a, W> {
+    buffer: &'a mut [W],
+    len: usize,
+}
+
+impl<'a, W> BufferWriter<'a, W> {
+    pub fn new(buffer: &'a mut [W]) -> BufferWriter<'a, W> {
+        BufferWriter { buffer, len: 0 }
+    }
+
+    /// Number of words written.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no word was written.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the written part of the buffer.
+    pub fn release(self) -> &'a mut [W] {
+        let buffer = self.buffer;
+        &mut buffer[..self.len]
+    }
+}
+
+impl<'a, W> WriteInterface<W> for BufferWriter<'a, W>
+where
+    W: Copy,
+{
+    fn write_stream<'b>(
+        &mut self,
+        _mode: WriteMode,
+        func: &mut dyn FnMut() -> Option<&'b W>,
+    ) -> Result<(), DisplayError> {
+        while let Some(word) = func() {
+            let slot = self
+                .buffer
+                .get_mut(self.len)
+                .ok_or(DisplayError::BusWriteError)?;
+            *slot = *word;
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, W> ReadInterface<W> for BufferWriter<'a, W> {
+    fn read_stream(&mut self, _f: &mut dyn FnMut(W) -> bool) -> Result<(), DisplayError> {
+        Err(DisplayError::DataFormatNotImplemented)
+    }
+}
+
+/// Returns the words of a buffer as if they were read from the bus.
+///
+/// Reading past the end of the buffer returns `BusReadError`, writes return
+/// `DataFormatNotImplemented`.
+pub struct BufferReader<'a, W> {
+    words: &'a [W],
+    position: usize,
+}
+
+impl<'a, W> BufferReader<'a, W> {
+    pub fn new(words: &'a [W]) -> BufferReader<'a, W> {
+        BufferReader { words, position: 0 }
+    }
+}
+
+impl<'a, W> ReadInterface<W> for BufferReader<'a, W>
+where
+    W: Copy,
+{
+    fn read_stream(&mut self, f: &mut dyn FnMut(W) -> bool) -> Result<(), DisplayError> {
+        loop {
+            let word = *self
+                .words
+                .get(self.position)
+                .ok_or(DisplayError::BusReadError)?;
+            self.position += 1;
+            if !f(word) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<'a, W> WriteInterface<W> for BufferReader<'a, W> {
+    fn write_stream<'b>(
+        &mut self,
+        _mode: WriteMode,
+        _func: &mut dyn FnMut() -> Option<&'b W>,
+    ) -> Result<(), DisplayError> {
+        Err(DisplayError::DataFormatNotImplemented)
+    }
+}
@@ -1,16 +1,119 @@
-use crate::gpio::U18;
+use crate::gpio::{U18, U9};
 use display_interface::v2::*;
 use display_interface::DisplayError;
 
+/// The format of pixel data sent to the controller.
+///
+/// Pixels are passed to the [PixelWriter](PixelWriter) with the components in the bit depth of
+/// the format. Colors drawn through embedded-graphics are converted with rounding, the color
+/// types that map losslessly are:
+///
+/// |Format|embedded-graphics color|
+/// |------|-----------------------|
+/// |`Rgb565`|`Rgb565`|
+/// |`Rgb666`, `Rgb666TwoTransfer`|`Rgb666` (embedded-graphics 0.7 and later)|
+/// |`Rgb111`|-|
+///
+/// Supported interfaces:
+///
+/// |Format|8-bit, SPI|9-bit|16-bit|18-bit|
+/// |------|----------|-----|------|------|
+/// |`Rgb565`|2 transfers|-|1 transfer|1 transfer|
+/// |`Rgb666`|3 transfers|2 transfers|2 pixels in 3 transfers|1 transfer|
+/// |`Rgb666TwoTransfer`|3 transfers|2 transfers|2 transfers|1 transfer|
+/// |`Rgb111`|2 pixels in 1 transfer|-|-|-|
+///
+/// The other combinations return `DataFormatNotImplemented`. The formats that pack two pixels
+/// into shared transfers write a lone pixel twice, see
+/// [write_pixel_data](PixelWriter::write_pixel_data).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PixelFormat {
+    /// 16 bits per pixel, 5 bits red, 6 bits green, 5 bits blue
     Rgb565,
+    /// 18 bits per pixel, 6 bits per component
     Rgb666,
+    /// 18 bits per pixel, with one pixel in two transfers on a 16-bit bus instead of two pixels
+    /// in three. The controller can't be switched to this mode with a command, it has to be set
+    /// up for it by the board, `InterfacePixelFormat` is the same as for `Rgb666`. Same as
+    /// `Rgb666` on the other interfaces.
+    Rgb666TwoTransfer,
+    /// 3 bits per pixel, 1 bit per component
+    Rgb111,
+}
+
+impl PixelFormat {
+    /// The maximum red, green and blue values.
+    pub fn max_components(&self) -> RGBPixel {
+        match self {
+            PixelFormat::Rgb565 => (31, 63, 31),
+            PixelFormat::Rgb666 | PixelFormat::Rgb666TwoTransfer => (63, 63, 63),
+            PixelFormat::Rgb111 => (1, 1, 1),
+        }
+    }
+
+    /// Converts a pixel whose components range up to `max` to this format.
+    pub fn convert(&self, pixel: &RGBPixel, max: &RGBPixel) -> RGBPixel {
+        let to = self.max_components();
+        (
+            scale_component(pixel.0, max.0, to.0),
+            scale_component(pixel.1, max.1, to.1),
+            scale_component(pixel.2, max.2, to.2),
+        )
+    }
+
+    /// The format with the DBI bits of an `InterfacePixelFormat` parameter, 18 bits per pixel is
+    /// reported as `Rgb666` as the transfer mode is not part of the parameter.
+    pub(crate) fn from_dbi_bits(colmod: u8) -> Option<PixelFormat> {
+        match colmod & 0b111 {
+            0b101 => Some(PixelFormat::Rgb565),
+            0b110 => Some(PixelFormat::Rgb666),
+            0b001 => Some(PixelFormat::Rgb111),
+            _ => None,
+        }
+    }
+
+    /// The DBI bits of the `InterfacePixelFormat` parameter, as reported by `ReadDisplayPixelFormat`.
+    pub(crate) fn dbi_bits(&self) -> u8 {
+        match self {
+            PixelFormat::Rgb565 => 0b101,
+            PixelFormat::Rgb666 | PixelFormat::Rgb666TwoTransfer => 0b110,
+            PixelFormat::Rgb111 => 0b001,
+        }
+    }
 }
 
 /// (R, G, B)
 pub type RGBPixel = (u8, u8, u8);
 
+/// Scales `value` from `0..=from_max` to `0..=to_max`, rounding to the nearest value.
+pub fn scale_component(value: u8, from_max: u8, to_max: u8) -> u8 {
+    if from_max == to_max {
+        return value;
+    }
+    if from_max == 0 {
+        return 0;
+    }
+    let value = value.min(from_max) as u16;
+    ((value * to_max as u16 + from_max as u16 / 2) / from_max as u16) as u8
+}
+
 pub trait PixelWriter<T> {
+    /// Whether pixels in `pixel_format` can be written on this interface.
+    fn supports_pixel_format(&self, _pixel_format: &PixelFormat) -> bool {
+        true
+    }
+
+    /// Whether `pixel_format` packs two pixels into shared transfers on this interface, e.g.
+    /// `Rgb666` on a 16-bit bus.
+    fn packs_pixels(&self, _pixel_format: &PixelFormat) -> bool {
+        false
+    }
+
+    /// Writes one or two pixels.
+    ///
+    /// The controller only stores the pixels of complete transfers, so formats that
+    /// [pack](PixelWriter::packs_pixels) two pixels send a lone `pixel_a` twice. The copy is
+    /// written to the next position, e.g. a 1x1 window wraps it onto the pixel itself.
     fn write_pixel_data(
         &mut self,
         pixel_format: &PixelFormat,
@@ -29,179 +132,774 @@ pub trait PixelWriter<T> {
         }
         Ok(())
     }
-}
-
-fn encode_rgb565_8bit(pixel: &RGBPixel) -> (u8, u8) {
-    (
-        ((pixel.0 & 0b11111) << 3) | ((pixel.1 >> 2) & 0b111),
-        ((pixel.1 << 5) & 0b11100000) | (pixel.2 & 0b11111),
-    )
-}
 
-impl<T> PixelWriter<u8> for T
-where
-    T: ReadWriteInterface<u8>,
-{
-    fn write_repeated_pixel_data(
+    /// Writes all pixels of `pixels`, pairing them for the formats that pack two pixels.
+    ///
+    /// A lone last pixel of a packing format is written twice like with
+    /// [write_pixel_data](PixelWriter::write_pixel_data), the driver draws it in a 1x1 window so
+    /// the copy doesn't land after the area.
+    fn write_pixels(
         &mut self,
         pixel_format: &PixelFormat,
-        pixel: &RGBPixel,
-        num: usize,
+        pixels: &mut dyn Iterator<Item = RGBPixel>,
     ) -> Result<(), DisplayError> {
-        match pixel_format {
-            PixelFormat::Rgb565 => {
-                let (hi, lo) = encode_rgb565_8bit(pixel);
-                let mut index = num * 2;
-                self.write_stream(WriteMode::Data, &mut || {
-                    if index == 0 {
-                        None
-                    } else {
-                        index -= 1;
-                        if index % 2 == 0 {
-                            Some(&lo)
-                        } else {
-                            Some(&hi)
-                        }
-                    }
-                })
-            }
-            PixelFormat::Rgb666 => {
-                for _ in 0..num {
-                    self.write_pixel_data(pixel_format, pixel, None)?;
-                }
-                Ok(())
+        while let Some(pixel_a) = pixels.next() {
+            let pixel_b = pixels.next();
+            self.write_pixel_data(pixel_format, &pixel_a, pixel_b.as_ref())?;
+            if pixel_b.is_none() {
+                break;
             }
         }
+        Ok(())
     }
-    fn write_pixel_data(
+
+    /// The value of a command or parameter word on this interface, `None` if it isn't known,
+    /// e.g. for custom word types. [write_command](crate::ILI9486::write_command) only caches
+    /// the settings it sends when the values are known.
+    fn word_value(&self, _word: &T) -> Option<u32> {
+        None
+    }
+}
+
+/// Decodes pixels read back from the frame memory.
+///
+/// The controller returns pixels as RGB666 whatever the pixel format, so the components are at
+/// most 63. The words per pixel depend on the bus:
+///
+/// |Bus   |Readback                                                |
+/// |------|--------------------------------------------------------|
+/// |8-bit |3 words per pixel, components in bits 7..2              |
+/// |9-bit |2 words per pixel, red and the upper half of green first|
+/// |16-bit|3 words per 2 pixels, components in bits 15..10 and 7..2|
+/// |18-bit|1 word per pixel                                        |
+pub trait PixelReader<T> {
+    /// Discards `dummy` words and then reads `pixels.len()` pixels in a single transaction.
+    fn read_pixel_data(
         &mut self,
-        pixel_format: &PixelFormat,
-        pixel_a: &RGBPixel,
-        pixel_b: Option<&RGBPixel>,
-    ) -> Result<(), DisplayError> {
-        match pixel_format {
-            PixelFormat::Rgb565 => {
-                let (hi, lo) = encode_rgb565_8bit(pixel_a);
-                self.write(WriteMode::Data, &[hi, lo])?;
-                match pixel_b {
-                    Some(pixel) => {
-                        let (hi, lo) = encode_rgb565_8bit(pixel);
-                        self.write(WriteMode::Data, &[hi, lo])?;
-                    }
-                    None => {}
-                };
-                Ok(())
-            }
-            PixelFormat::Rgb666 => {
-                self.write(
-                    WriteMode::Data,
-                    &[pixel_a.0 << 2, pixel_a.1 << 2, pixel_a.2 << 2],
-                )?;
-
-                match pixel_b {
-                    Some(pixel) => {
-                        self.write(WriteMode::Data, &[pixel.0 << 2, pixel.1 << 2, pixel.2 << 2])?;
-                    }
-                    None => {}
-                }
-                Ok(())
-            }
+        dummy: usize,
+        pixels: &mut [RGBPixel],
+    ) -> Result<(), DisplayError>;
+}
+
+/// A bus word pixels are read back in, see [PixelReader](PixelReader).
+pub trait ReadbackWord {
+    /// Number of words a group of pixels is read back in, at most 3.
+    const GROUP_WORDS: usize;
+    /// Number of pixels in a group.
+    const GROUP_PIXELS: usize;
+
+    /// Number of words the controller returns for `pixels` pixels, excluding the dummy reads.
+    fn readback_words(pixels: usize) -> usize {
+        pixels.div_ceil(Self::GROUP_PIXELS) * Self::GROUP_WORDS
+    }
+}
+
+/// Number of words [write_pixels](PixelWriter::write_pixels) encodes before writing them.
+const ENCODED_CHUNK: usize = 64;
+
+/// The words encoding one or two pixels.
+struct Encoded<W> {
+    words: [W; 6],
+    len: usize,
+}
+
+impl<W: Copy + Default> Encoded<W> {
+    fn new(words: &[W]) -> Encoded<W> {
+        let mut encoded = Encoded {
+            words: [W::default(); 6],
+            len: 0,
+        };
+        encoded.extend(words);
+        encoded
+    }
+
+    fn extend(&mut self, words: &[W]) {
+        self.words[self.len..self.len + words.len()].copy_from_slice(words);
+        self.len += words.len();
+    }
+
+    fn as_slice(&self) -> &[W] {
+        &self.words[..self.len]
+    }
+}
+
+/// Encodes `pixel_a` and `pixel_b` for a bus, formats that pack two pixels use both and send a
+/// lone `pixel_a` twice, the others encode each pixel on its own.
+fn encode_pair<W, F>(
+    encode: F,
+    pixel_format: &PixelFormat,
+    pixel_a: &RGBPixel,
+    pixel_b: Option<&RGBPixel>,
+) -> Result<Encoded<W>, DisplayError>
+where
+    W: Copy + Default,
+    F: Fn(&PixelFormat, &RGBPixel, Option<&RGBPixel>) -> Option<Encoded<W>>,
+{
+    match (encode(pixel_format, pixel_a, pixel_b), pixel_b) {
+        (Some(encoded), _) => Ok(encoded),
+        (None, None) => encode(pixel_format, pixel_a, Some(pixel_a))
+            .ok_or(DisplayError::DataFormatNotImplemented),
+        (None, Some(pixel_b)) => {
+            let mut encoded = encode(pixel_format, pixel_a, None)
+                .ok_or(DisplayError::DataFormatNotImplemented)?;
+            let b = encode(pixel_format, pixel_b, None)
+                .ok_or(DisplayError::DataFormatNotImplemented)?;
+            encoded.extend(b.as_slice());
+            Ok(encoded)
         }
     }
 }
 
-fn encode_rgb565_16bit(pixel: &RGBPixel) -> u16 {
-    (((pixel.0 & 0b11111) as u16) << 10)
-        | (((pixel.1 & 0b111111) as u16) << 4)
-        | (pixel.2 & 0b11111) as u16
+/// Whether `encode` only encodes `pixel_format` in pairs.
+fn packs<W, F>(encode: F, pixel_format: &PixelFormat) -> bool
+where
+    F: Fn(&PixelFormat, &RGBPixel, Option<&RGBPixel>) -> Option<Encoded<W>>,
+{
+    let black = (0, 0, 0);
+    encode(pixel_format, &black, None).is_none()
+        && encode(pixel_format, &black, Some(&black)).is_some()
 }
 
-fn encode_rgb666_16bit(pixel_a: &RGBPixel, pixel_b: &RGBPixel) -> (u16, u16, u16) {
-    let one = (((pixel_a.0 & 0b111111) as u16) << 10) | (((pixel_a.1 & 0b111111) as u16) << 2);
-    let two = (((pixel_a.2 & 0b111111) as u16) << 10) | (((pixel_b.0 & 0b111111) as u16) << 2);
-    let three = (((pixel_b.1 & 0b111111) as u16) << 10) | (((pixel_b.2 & 0b111111) as u16) << 2);
-    (one, two, three)
+/// Writes `group` `repeat` times followed by `tail` in a single stream.
+fn write_repeated<I, W>(
+    interface: &mut I,
+    group: &[W],
+    repeat: usize,
+    tail: &[W],
+) -> Result<(), DisplayError>
+where
+    I: WriteInterface<W> + ?Sized,
+{
+    let mut group_index = 0;
+    let mut remaining = repeat;
+    let mut tail = tail.iter();
+    interface.write_stream(WriteMode::Data, &mut || {
+        if remaining == 0 || group.is_empty() {
+            return tail.next();
+        }
+        let word = &group[group_index];
+        group_index += 1;
+        if group_index == group.len() {
+            group_index = 0;
+            remaining -= 1;
+        }
+        Some(word)
+    })
 }
 
-impl<T> PixelWriter<u16> for T
+/// Encodes `pixels` in pairs into a buffer on the stack and writes it whenever it is full.
+///
+/// A word handed to `write_stream` has to stay valid for the whole stream, so the words can't be
+/// encoded inside the stream. Every 64 words are written in one stream instead.
+fn write_encoded<I, W, F>(
+    interface: &mut I,
+    encode: F,
+    pixels: &mut dyn Iterator<Item = RGBPixel>,
+) -> Result<(), DisplayError>
 where
-    T: ReadWriteInterface<u16>,
+    I: WriteInterface<W> + ?Sized,
+    W: Copy + Default,
+    F: Fn(&RGBPixel, Option<&RGBPixel>) -> Result<Encoded<W>, DisplayError>,
 {
-    fn write_pixel_data(
-        &mut self,
-        pixel_format: &PixelFormat,
-        pixel_a: &RGBPixel,
-        pixel_b: Option<&RGBPixel>,
-    ) -> Result<(), DisplayError> {
-        match pixel_format {
-            PixelFormat::Rgb565 => self.write(WriteMode::Data, &[encode_rgb565_16bit(pixel_a)]),
-            PixelFormat::Rgb666 => match pixel_b {
-                Some(b) => {
-                    let (one, two, three) = encode_rgb666_16bit(pixel_a, b);
-                    self.write(WriteMode::Data, &[one, two, three])
-                }
+    let mut words = [W::default(); ENCODED_CHUNK];
+    let mut done = false;
+
+    while !done {
+        let mut len = 0;
+        // a pair takes at most 6 words
+        while len + 6 <= ENCODED_CHUNK {
+            let pixel_a = match pixels.next() {
+                Some(pixel) => pixel,
                 None => {
-                    let (one, two, _three) = encode_rgb666_16bit(pixel_a, &(0, 0, 0));
-                    self.write(WriteMode::Data, &[one, two])
+                    done = true;
+                    break;
                 }
-            },
+            };
+            let pixel_b = pixels.next();
+            let encoded = encode(&pixel_a, pixel_b.as_ref())?;
+            words[len..len + encoded.len].copy_from_slice(encoded.as_slice());
+            len += encoded.len;
+            if pixel_b.is_none() {
+                done = true;
+                break;
+            }
+        }
+        if len > 0 {
+            interface.write(WriteMode::Data, &words[..len])?;
         }
     }
+    Ok(())
+}
 
-    fn write_repeated_pixel_data(
-        &mut self,
-        pixel_format: &PixelFormat,
-        pixel: &RGBPixel,
-        num: usize,
-    ) -> Result<(), DisplayError> {
-        match pixel_format {
-            PixelFormat::Rgb565 => {
-                let data = encode_rgb565_16bit(pixel);
-                let mut index = num;
-                self.write_stream(WriteMode::Data, &mut || {
-                    if index == 0 {
-                        None
-                    } else {
-                        index -= 1;
-                        Some(&data)
-                    }
-                })
-            }
-            PixelFormat::Rgb666 => {
-                for _ in 0..num {
-                    self.write_pixel_data(pixel_format, pixel, None)?;
-                }
-                Ok(())
-            }
+fn r5(pixel: &RGBPixel) -> u8 {
+    pixel.0 & 0b11111
+}
+
+fn g6(pixel: &RGBPixel) -> u8 {
+    pixel.1 & 0b111111
+}
+
+fn b5(pixel: &RGBPixel) -> u8 {
+    pixel.2 & 0b11111
+}
+
+fn rgb6(pixel: &RGBPixel) -> (u8, u8, u8) {
+    (pixel.0 & 0b111111, pixel.1 & 0b111111, pixel.2 & 0b111111)
+}
+
+fn rgb1(pixel: &RGBPixel) -> u8 {
+    ((pixel.0 & 1) << 2) | ((pixel.1 & 1) << 1) | (pixel.2 & 1)
+}
+
+/// 8-bit bus and SPI.
+///
+/// * RGB565: `R4..R0 G5..G3`, `G2..G0 B4..B0`
+/// * RGB666: `R5..R0 x x`, `G5..G0 x x`, `B5..B0 x x`
+/// * RGB111: `x x R G B R G B`, two pixels
+fn encode_8bit(
+    pixel_format: &PixelFormat,
+    pixel_a: &RGBPixel,
+    pixel_b: Option<&RGBPixel>,
+) -> Option<Encoded<u8>> {
+    match (pixel_format, pixel_b) {
+        (PixelFormat::Rgb565, None) => Some(Encoded::new(&[
+            (r5(pixel_a) << 3) | (g6(pixel_a) >> 3),
+            (g6(pixel_a) << 5) | b5(pixel_a),
+        ])),
+        (PixelFormat::Rgb666, None) | (PixelFormat::Rgb666TwoTransfer, None) => {
+            let (r, g, b) = rgb6(pixel_a);
+            Some(Encoded::new(&[r << 2, g << 2, b << 2]))
+        }
+        (PixelFormat::Rgb111, Some(pixel_b)) => {
+            Some(Encoded::new(&[(rgb1(pixel_a) << 3) | rgb1(pixel_b)]))
         }
+        _ => None,
     }
 }
 
-fn encode_rgb666_18bit(pixel: &RGBPixel) -> u32 {
-    (((pixel.0 & 0b111111) as u32) << 12)
-        | (((pixel.1 & 0b111111) as u32) << 6)
-        | ((pixel.2 & 0b111111) as u32)
+/// 9-bit bus, RGB666 only: `R5..R0 G5..G3`, `G2..G0 B5..B0`
+fn encode_9bit(
+    pixel_format: &PixelFormat,
+    pixel_a: &RGBPixel,
+    pixel_b: Option<&RGBPixel>,
+) -> Option<Encoded<U9>> {
+    match (pixel_format, pixel_b) {
+        (PixelFormat::Rgb666, None) | (PixelFormat::Rgb666TwoTransfer, None) => {
+            let (r, g, b) = rgb6(pixel_a);
+            Some(Encoded::new(&[
+                U9(((r as u16) << 3) | (g as u16 >> 3)),
+                U9((((g & 0b111) as u16) << 6) | b as u16),
+            ]))
+        }
+        _ => None,
+    }
+}
+
+/// 16-bit bus.
+///
+/// * RGB565: `R4..R0 G5..G0 B4..B0`
+/// * RGB666, two pixels in three transfers: `R5..R0 x x G5..G0 x x`, `B5..B0 x x R5..R0 x x`,
+///   `G5..G0 x x B5..B0 x x`
+/// * RGB666, one pixel in two transfers: `R5 R4`, `R3..R0 G5..G0 B5..B0`
+fn encode_16bit(
+    pixel_format: &PixelFormat,
+    pixel_a: &RGBPixel,
+    pixel_b: Option<&RGBPixel>,
+) -> Option<Encoded<u16>> {
+    let lanes = |upper: u8, lower: u8| ((upper as u16) << 10) | ((lower as u16) << 2);
+    match (pixel_format, pixel_b) {
+        (PixelFormat::Rgb565, None) => Some(Encoded::new(&[((r5(pixel_a) as u16) << 11)
+            | ((g6(pixel_a) as u16) << 5)
+            | b5(pixel_a) as u16])),
+        (PixelFormat::Rgb666, Some(pixel_b)) => {
+            let (a, b) = (rgb6(pixel_a), rgb6(pixel_b));
+            Some(Encoded::new(&[
+                lanes(a.0, a.1),
+                lanes(a.2, b.0),
+                lanes(b.1, b.2),
+            ]))
+        }
+        (PixelFormat::Rgb666TwoTransfer, None) => {
+            let (r, g, b) = rgb6(pixel_a);
+            Some(Encoded::new(&[
+                (r >> 4) as u16,
+                (((r & 0b1111) as u16) << 12) | ((g as u16) << 6) | b as u16,
+            ]))
+        }
+        _ => None,
+    }
+}
+
+/// 18-bit bus.
+///
+/// * RGB565: `R4..R0 x G5..G0 B4..B0 x`
+/// * RGB666: `R5..R0 G5..G0 B5..B0`
+fn encode_18bit(
+    pixel_format: &PixelFormat,
+    pixel_a: &RGBPixel,
+    pixel_b: Option<&RGBPixel>,
+) -> Option<Encoded<U18>> {
+    match (pixel_format, pixel_b) {
+        (PixelFormat::Rgb565, None) => Some(Encoded::new(&[((r5(pixel_a) as u32) << 13)
+            | ((g6(pixel_a) as u32) << 6)
+            | ((b5(pixel_a) as u32) << 1)])),
+        (PixelFormat::Rgb666, None) | (PixelFormat::Rgb666TwoTransfer, None) => {
+            let (r, g, b) = rgb6(pixel_a);
+            Some(Encoded::new(&[((r as u32) << 12)
+                | ((g as u32) << 6)
+                | b as u32]))
+        }
+        _ => None,
+    }
+}
+
+macro_rules! pixel_writer {
+    ($word:ty, $encode:ident) => {
+        impl<T> PixelWriter<$word> for T
+        where
+            T: ReadWriteInterface<$word>,
+        {
+            fn supports_pixel_format(&self, pixel_format: &PixelFormat) -> bool {
+                encode_pair($encode, pixel_format, &(0, 0, 0), Some(&(0, 0, 0))).is_ok()
+            }
+
+            fn packs_pixels(&self, pixel_format: &PixelFormat) -> bool {
+                packs($encode, pixel_format)
+            }
+
+            fn word_value(&self, word: &$word) -> Option<u32> {
+                Some((*word).into())
+            }
+
+            fn write_pixel_data(
+                &mut self,
+                pixel_format: &PixelFormat,
+                pixel_a: &RGBPixel,
+                pixel_b: Option<&RGBPixel>,
+            ) -> Result<(), DisplayError> {
+                let encoded = encode_pair($encode, pixel_format, pixel_a, pixel_b)?;
+                self.write(WriteMode::Data, encoded.as_slice())
+            }
+
+            fn write_repeated_pixel_data(
+                &mut self,
+                pixel_format: &PixelFormat,
+                pixel: &RGBPixel,
+                num: usize,
+            ) -> Result<(), DisplayError> {
+                // pairs of pixels, so formats packing two pixels repeat whole transfers, an odd
+                // pixel of those formats is written twice
+                let pair = encode_pair($encode, pixel_format, pixel, Some(pixel))?;
+                let tail = if num % 2 == 1 {
+                    encode_pair($encode, pixel_format, pixel, None)?
+                } else {
+                    Encoded::new(&[])
+                };
+                write_repeated(self, pair.as_slice(), num / 2, tail.as_slice())
+            }
+
+            fn write_pixels(
+                &mut self,
+                pixel_format: &PixelFormat,
+                pixels: &mut dyn Iterator<Item = RGBPixel>,
+            ) -> Result<(), DisplayError> {
+                write_encoded(
+                    self,
+                    |pixel_a: &RGBPixel, pixel_b: Option<&RGBPixel>| {
+                        encode_pair($encode, pixel_format, pixel_a, pixel_b)
+                    },
+                    pixels,
+                )
+            }
+        }
+    };
 }
 
-impl<T> PixelWriter<U18> for T
+pixel_writer!(u8, encode_8bit);
+pixel_writer!(U9, encode_9bit);
+pixel_writer!(u16, encode_16bit);
+pixel_writer!(U18, encode_18bit);
+
+/// Reads `dummy` words and then decodes every group of `group_words` words into `group_pixels`
+/// pixels, until `pixels` is full.
+fn read_decoded<I, W>(
+    interface: &mut I,
+    dummy: usize,
+    pixels: &mut [RGBPixel],
+    group_words: usize,
+    group_pixels: usize,
+    decode: fn(&[u32; 3]) -> [RGBPixel; 2],
+) -> Result<(), DisplayError>
 where
-    T: ReadWriteInterface<U18>,
+    I: ReadInterface<W> + ?Sized,
+    W: Into<u32>,
 {
-    fn write_pixel_data(
-        &mut self,
-        pixel_format: &PixelFormat,
-        pixel_a: &RGBPixel,
-        pixel_b: Option<&RGBPixel>,
-    ) -> Result<(), DisplayError> {
+    if pixels.is_empty() {
+        return Ok(());
+    }
+
+    // a trailing pixel of a 16-bit group is read in full
+    let groups = pixels.len().div_ceil(group_pixels);
+    let total = dummy + groups * group_words;
+    let mut group = [0u32; 3];
+    let mut index = 0;
+    let mut pixel = 0;
+    interface.read_stream(&mut |word: W| {
+        if index >= dummy {
+            let offset = (index - dummy) % group_words;
+            group[offset] = word.into();
+            if offset + 1 == group_words {
+                for decoded in decode(&group).iter().take(group_pixels) {
+                    if pixel < pixels.len() {
+                        pixels[pixel] = *decoded;
+                        pixel += 1;
+                    }
+                }
+            }
+        }
+        index += 1;
+        index < total
+    })
+}
+
+fn component(bits: u32) -> u8 {
+    (bits & 0x3f) as u8
+}
+
+fn decode_8bit(words: &[u32; 3]) -> [RGBPixel; 2] {
+    let pixel = (
+        component(words[0] >> 2),
+        component(words[1] >> 2),
+        component(words[2] >> 2),
+    );
+    [pixel, pixel]
+}
+
+fn decode_9bit(words: &[u32; 3]) -> [RGBPixel; 2] {
+    decode_18bit(&[(words[0] & 0x1ff) << 9 | (words[1] & 0x1ff), 0, 0])
+}
+
+fn decode_16bit(words: &[u32; 3]) -> [RGBPixel; 2] {
+    [
+        (
+            component(words[0] >> 10),
+            component(words[0] >> 2),
+            component(words[1] >> 10),
+        ),
+        (
+            component(words[1] >> 2),
+            component(words[2] >> 10),
+            component(words[2] >> 2),
+        ),
+    ]
+}
+
+fn decode_18bit(words: &[u32; 3]) -> [RGBPixel; 2] {
+    let pixel = (
+        component(words[0] >> 12),
+        component(words[0] >> 6),
+        component(words[0]),
+    );
+    [pixel, pixel]
+}
+
+macro_rules! pixel_reader {
+    ($word:ty, $group_words:expr, $group_pixels:expr, $decode:ident) => {
+        impl ReadbackWord for $word {
+            const GROUP_WORDS: usize = $group_words;
+            const GROUP_PIXELS: usize = $group_pixels;
+        }
+
+        impl<T> PixelReader<$word> for T
+        where
+            T: ReadInterface<$word>,
+        {
+            fn read_pixel_data(
+                &mut self,
+                dummy: usize,
+                pixels: &mut [RGBPixel],
+            ) -> Result<(), DisplayError> {
+                read_decoded(self, dummy, pixels, $group_words, $group_pixels, $decode)
+            }
+        }
+    };
+}
+
+pixel_reader!(u8, 3, 1, decode_8bit);
+pixel_reader!(U9, 2, 1, decode_9bit);
+pixel_reader!(u16, 3, 2, decode_16bit);
+pixel_reader!(U18, 1, 1, decode_18bit);
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::buffer::BufferWriter;
+    use crate::emulator::{Emulator, Rgb666};
+    use std::vec::Vec;
+
+    /// Format, whether it is supported, whether it packs pixels, the words of two pixels and
+    /// the words of a lone pixel.
+    type Row = (PixelFormat, bool, bool, usize, usize);
+
+    const BITS_8: [Row; 4] = [
+        (PixelFormat::Rgb565, true, false, 4, 2),
+        (PixelFormat::Rgb666, true, false, 6, 3),
+        (PixelFormat::Rgb666TwoTransfer, true, false, 6, 3),
+        (PixelFormat::Rgb111, true, true, 1, 1),
+    ];
+    const BITS_9: [Row; 4] = [
+        (PixelFormat::Rgb565, false, false, 0, 0),
+        (PixelFormat::Rgb666, true, false, 4, 2),
+        (PixelFormat::Rgb666TwoTransfer, true, false, 4, 2),
+        (PixelFormat::Rgb111, false, false, 0, 0),
+    ];
+    const BITS_16: [Row; 4] = [
+        (PixelFormat::Rgb565, true, false, 2, 1),
+        (PixelFormat::Rgb666, true, true, 3, 3),
+        (PixelFormat::Rgb666TwoTransfer, true, false, 4, 2),
+        (PixelFormat::Rgb111, false, false, 0, 0),
+    ];
+    const BITS_18: [Row; 4] = [
+        (PixelFormat::Rgb565, true, false, 2, 1),
+        (PixelFormat::Rgb666, true, false, 2, 1),
+        (PixelFormat::Rgb666TwoTransfer, true, false, 2, 1),
+        (PixelFormat::Rgb111, false, false, 0, 0),
+    ];
+
+    /// Pixels in the components of `pixel_format` and how the controller stores them.
+    fn pixels(pixel_format: PixelFormat) -> [(RGBPixel, Rgb666); 3] {
         match pixel_format {
-            PixelFormat::Rgb565 => Err(DisplayError::DataFormatNotImplemented),
-            PixelFormat::Rgb666 => match pixel_b {
-                Some(b) => self.write(
-                    WriteMode::Data,
-                    &[encode_rgb666_18bit(pixel_a), encode_rgb666_18bit(b)],
-                ),
-                None => self.write(WriteMode::Data, &[encode_rgb666_18bit(pixel_a)]),
-            },
+            PixelFormat::Rgb565 => [
+                ((31, 0, 1), (63, 0, 2)),
+                ((0, 63, 16), (0, 63, 33)),
+                ((5, 6, 7), (10, 6, 14)),
+            ],
+            PixelFormat::Rgb666 | PixelFormat::Rgb666TwoTransfer => [
+                ((63, 0, 1), (63, 0, 1)),
+                ((0, 62, 3), (0, 62, 3)),
+                ((4, 5, 6), (4, 5, 6)),
+            ],
+            PixelFormat::Rgb111 => [
+                ((1, 0, 0), (63, 0, 0)),
+                ((0, 1, 1), (0, 63, 63)),
+                ((1, 1, 0), (63, 63, 0)),
+            ],
+        }
+    }
+
+    fn window<W: From<u8> + Copy>(emulator: &mut Emulator, x: u8, width: u8)
+    where
+        Emulator: WriteInterface<W>,
+    {
+        let command = |emulator: &mut Emulator, opcode: u8, data: &[u8]| {
+            emulator
+                .write(WriteMode::Command, &[W::from(opcode)])
+                .unwrap();
+            for byte in data {
+                emulator.write(WriteMode::Data, &[W::from(*byte)]).unwrap();
+            }
+        };
+        command(emulator, 0x2a, &[0, x, 0, x + width - 1]);
+        command(emulator, 0x2b, &[0, 0, 0, 0]);
+        command(emulator, 0x2c, &[]);
+    }
+
+    fn check<W>(rows: &[Row])
+    where
+        W: From<u8> + Copy + Default,
+        Emulator: ReadWriteInterface<W> + PixelWriter<W>,
+        for<'a> BufferWriter<'a, W>: PixelWriter<W>,
+    {
+        for (pixel_format, supported, packs, pair, lone) in rows.iter() {
+            let mut words = [W::default(); 6];
+            let mut writer = BufferWriter::new(&mut words);
+            assert_eq!(writer.supports_pixel_format(pixel_format), *supported);
+            assert_eq!(writer.packs_pixels(pixel_format), *packs);
+            let black = (0, 0, 0);
+            if !supported {
+                assert!(writer
+                    .write_pixel_data(pixel_format, &black, Some(&black))
+                    .is_err());
+                assert!(writer.write_pixel_data(pixel_format, &black, None).is_err());
+                continue;
+            }
+            writer
+                .write_pixel_data(pixel_format, &black, Some(&black))
+                .unwrap();
+            assert_eq!(writer.len(), *pair, "{:?}", pixel_format);
+            let mut writer = BufferWriter::new(&mut words);
+            writer.write_pixel_data(pixel_format, &black, None).unwrap();
+            assert_eq!(writer.len(), *lone, "{:?}", pixel_format);
+
+            let mut emulator = Emulator::new();
+            emulator
+                .write(WriteMode::Command, &[W::from(0x3a)])
+                .unwrap();
+            emulator
+                .write(WriteMode::Data, &[W::from(0x10 | pixel_format.dbi_bits())])
+                .unwrap();
+            emulator.set_two_transfer_mode(*pixel_format == PixelFormat::Rgb666TwoTransfer);
+            assert_eq!(emulator.pixel_format(), Some(*pixel_format));
+
+            // an odd number of pixels, the copy of a lone last pixel of a packing format lands
+            // at the next position
+            let pixels = pixels(*pixel_format);
+            window::<W>(&mut emulator, 0, 4);
+            PixelWriter::<W>::write_pixels(
+                &mut emulator,
+                pixel_format,
+                &mut pixels.iter().map(|p| p.0),
+            )
+            .unwrap();
+            for (x, (_, stored)) in pixels.iter().enumerate() {
+                assert_eq!(emulator.pixel(x, 0), *stored, "{:?}", pixel_format);
+            }
+            let copy = if *packs { pixels[2].1 } else { (0, 0, 0) };
+            assert_eq!(emulator.pixel(3, 0), copy, "{:?}", pixel_format);
+            assert_eq!(emulator.pixel(4, 0), (0, 0, 0));
+
+            // a lone pixel in a 1x1 window, the same with the other pixel writes
+            window::<W>(&mut emulator, 10, 1);
+            PixelWriter::<W>::write_pixel_data(&mut emulator, pixel_format, &pixels[2].0, None)
+                .unwrap();
+            window::<W>(&mut emulator, 12, 2);
+            PixelWriter::<W>::write_repeated_pixel_data(
+                &mut emulator,
+                pixel_format,
+                &pixels[1].0,
+                3,
+            )
+            .unwrap();
+            assert_eq!(emulator.pixel(10, 0), pixels[2].1, "{:?}", pixel_format);
+            assert_eq!(emulator.pixel(11, 0), (0, 0, 0));
+            assert_eq!(emulator.pixel(12, 0), pixels[1].1, "{:?}", pixel_format);
+            assert_eq!(emulator.pixel(13, 0), pixels[1].1, "{:?}", pixel_format);
+            assert_eq!(emulator.pixel(14, 0), (0, 0, 0));
         }
     }
+
+    #[test]
+    fn formats_and_bus_widths() {
+        check::<u8>(&BITS_8);
+        check::<U9>(&BITS_9);
+        check::<u16>(&BITS_16);
+        check::<U18>(&BITS_18);
+    }
+
+    /// Collects the words of each stream before passing them on to the emulator, and records
+    /// the stream lengths.
+    struct Streams {
+        emulator: Emulator,
+        lengths: Vec<usize>,
+    }
+
+    impl<W> WriteInterface<W> for Streams
+    where
+        W: Copy,
+        Emulator: WriteInterface<W>,
+    {
+        fn write_stream<'a>(
+            &mut self,
+            mode: WriteMode,
+            func: &mut dyn FnMut() -> Option<&'a W>,
+        ) -> Result<(), DisplayError> {
+            // every word handed out has to stay valid until the stream ends
+            let mut words: Vec<&'a W> = Vec::new();
+            while let Some(word) = func() {
+                words.push(word);
+            }
+            self.lengths.push(words.len());
+            let words: Vec<W> = words.into_iter().copied().collect();
+            self.emulator.write(mode, &words)
+        }
+    }
+
+    impl<W> ReadInterface<W> for Streams
+    where
+        Emulator: ReadInterface<W>,
+    {
+        fn read_stream(&mut self, f: &mut dyn FnMut(W) -> bool) -> Result<(), DisplayError> {
+            self.emulator.read_stream(f)
+        }
+    }
+
+    fn chunked<W>(pixel_format: PixelFormat)
+    where
+        W: From<u8> + Copy + Default,
+        Emulator: ReadWriteInterface<W>,
+        Streams: PixelWriter<W>,
+    {
+        let mut emulator = Emulator::new();
+        emulator
+            .write(WriteMode::Command, &[W::from(0x3a)])
+            .unwrap();
+        emulator
+            .write(WriteMode::Data, &[W::from(0x10 | pixel_format.dbi_bits())])
+            .unwrap();
+        emulator.set_two_transfer_mode(pixel_format == PixelFormat::Rgb666TwoTransfer);
+        // room for the copy of a lone last pixel
+        window::<W>(&mut emulator, 0, 102);
+        let mut streams = Streams {
+            emulator,
+            lengths: Vec::new(),
+        };
+        let pixels = pixels(pixel_format);
+        PixelWriter::<W>::write_pixels(
+            &mut streams,
+            &pixel_format,
+            &mut (0..101).map(|x| pixels[x % 3].0),
+        )
+        .unwrap();
+        // full chunks, up to the last pair that fits, and the rest
+        let (last, full) = streams.lengths.split_last().unwrap();
+        assert!(!full.is_empty(), "{:?}", pixel_format);
+        assert!(full.iter().all(|len| (59..=64).contains(len)), "{:?}", full);
+        assert!(*last <= 64);
+        for x in 0..101 {
+            assert_eq!(streams.emulator.pixel(x, 0), pixels[x % 3].1, "{}", x);
+        }
+    }
+
+    #[test]
+    fn writes_pixels_in_chunks() {
+        chunked::<u8>(PixelFormat::Rgb565);
+        chunked::<u8>(PixelFormat::Rgb666);
+        chunked::<u16>(PixelFormat::Rgb666);
+        chunked::<u16>(PixelFormat::Rgb666TwoTransfer);
+        chunked::<U18>(PixelFormat::Rgb666);
+    }
+
+    #[test]
+    fn readback() {
+        let mut emulator = Emulator::new();
+        window::<u16>(&mut emulator, 0, 4);
+        let drawn = [(1, 2, 3), (4, 5, 6), (7, 8, 9)];
+        PixelWriter::<u16>::write_pixels(
+            &mut emulator,
+            &PixelFormat::Rgb666,
+            &mut drawn.iter().cloned(),
+        )
+        .unwrap();
+        assert_eq!(u16::readback_words(3), 6);
+        assert_eq!(u8::readback_words(3), 9);
+        assert_eq!(U18::readback_words(3), 3);
+
+        emulator.write(WriteMode::Command, &[0x2eu16]).unwrap();
+        let mut pixels = [(0, 0, 0); 3];
+        PixelReader::<u16>::read_pixel_data(&mut emulator, 1, &mut pixels).unwrap();
+        assert_eq!(pixels, drawn);
+    }
+
+    #[test]
+    fn scale() {
+        assert_eq!(scale_component(31, 31, 63), 63);
+        assert_eq!(scale_component(16, 31, 63), 33);
+        assert_eq!(scale_component(63, 63, 1), 1);
+        assert_eq!(scale_component(31, 63, 1), 0);
+        assert_eq!(scale_component(40, 31, 63), 63);
+        assert_eq!(
+            PixelFormat::Rgb565.convert(&(255, 128, 0), &(255, 255, 255)),
+            (31, 32, 0)
+        );
+    }
 }
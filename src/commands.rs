@@ -1,6 +1,8 @@
 use crate::PixelFormat;
+use core::convert::TryFrom;
 use display_interface::DisplayError;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Command {
     Nop = 0x00,
     SoftReset = 0x01,
@@ -91,6 +93,232 @@ impl Into<u8> for Command {
     }
 }
 
+impl TryFrom<u8> for Command {
+    type Error = u8;
+
+    /// Converts an opcode into a [Command](Command), unknown opcodes are returned as the error.
+    fn try_from(opcode: u8) -> Result<Command, u8> {
+        match opcode {
+            0x00 => Ok(Command::Nop),
+            0x01 => Ok(Command::SoftReset),
+            0x04 => Ok(Command::ReadDisplayId),
+            0x05 => Ok(Command::ReadErrors),
+            0x09 => Ok(Command::ReadDisplayStatus),
+            0x0a => Ok(Command::ReadDisplayPowerMode),
+            0x0b => Ok(Command::ReadDisplayMADCTL),
+            0x0c => Ok(Command::ReadDisplayPixelFormat),
+            0x0d => Ok(Command::ReadDisplayImageMode),
+            0x0e => Ok(Command::ReadDisplaySignalMode),
+            0x0f => Ok(Command::ReadDisplaySelfDiagResult),
+            0x10 => Ok(Command::SleepIn),
+            0x11 => Ok(Command::SleepOut),
+            0x12 => Ok(Command::PartialModeOn),
+            0x13 => Ok(Command::NormalDisplayMode),
+            0x20 => Ok(Command::DisplayInversionOff),
+            0x21 => Ok(Command::DisplayInversionOn),
+            0x28 => Ok(Command::DisplayOff),
+            0x29 => Ok(Command::DisplayOn),
+            0x2a => Ok(Command::ColumnAddressSet),
+            0x2b => Ok(Command::PageAddressSet),
+            0x2c => Ok(Command::MemoryWrite),
+            0x2e => Ok(Command::MemoryRead),
+            0x30 => Ok(Command::PartialArea),
+            0x33 => Ok(Command::VerticalScrollingDefinition),
+            0x34 => Ok(Command::TearingEffectLineOff),
+            0x35 => Ok(Command::TearingEffectLineOn),
+            0x36 => Ok(Command::MemoryAccessControl),
+            0x37 => Ok(Command::VerticalScrollingStartAddress),
+            0x38 => Ok(Command::IdleModeOff),
+            0x39 => Ok(Command::IdleModeOn),
+            0x3a => Ok(Command::InterfacePixelFormat),
+            0x3c => Ok(Command::MemoryWriteContinue),
+            0x3e => Ok(Command::MemoryReadContinue),
+            0x44 => Ok(Command::WriteTearScanLine),
+            0x45 => Ok(Command::ReadTearScanLine),
+            0x51 => Ok(Command::WriteDisplayBrightnessValue),
+            0x52 => Ok(Command::ReadDisplayBrigthnessValue),
+            0x53 => Ok(Command::WriteCTRLDisplayValue),
+            0x54 => Ok(Command::ReadCTRLDisplayValue),
+            0x55 => Ok(Command::WriteCABrigthnessControl),
+            0x56 => Ok(Command::ReadCABrigthnessControl),
+            0x5e => Ok(Command::WriteCABCMinBrigthness),
+            0x5f => Ok(Command::ReadCABCMinBrigthness),
+            0xaa => Ok(Command::ReadFirstChecksum),
+            0xab => Ok(Command::ReadContinueChecksum),
+            0xda => Ok(Command::ReadID1),
+            0xdb => Ok(Command::ReadID2),
+            0xdc => Ok(Command::ReadID3),
+            0xb0 => Ok(Command::InterfaceModeControl),
+            0xb1 => Ok(Command::FrameRateControlNormal),
+            0xb2 => Ok(Command::FrameRateControlIdle),
+            0xb3 => Ok(Command::FrameRateControlPartial),
+            0xb4 => Ok(Command::DisplayInversionControl),
+            0xb5 => Ok(Command::BlankingPorchControl),
+            0xb6 => Ok(Command::DisplayFunctionControl),
+            0xb7 => Ok(Command::EntryModeSet),
+            0xc0 => Ok(Command::PowerControl1),
+            0xc1 => Ok(Command::PowerControl2),
+            0xc2 => Ok(Command::PowerControl3),
+            0xc3 => Ok(Command::PowerControl4),
+            0xc4 => Ok(Command::PowerControl5),
+            0xc5 => Ok(Command::VCOMControl),
+            0xc6 => Ok(Command::CABCControl9),
+            0xc8 => Ok(Command::CABCControl1),
+            0xc9 => Ok(Command::CABCControl2),
+            0xca => Ok(Command::CABCControl3),
+            0xcb => Ok(Command::CABCControl4),
+            0xcc => Ok(Command::CABCControl5),
+            0xcd => Ok(Command::CABCControl6),
+            0xce => Ok(Command::CABCControl7),
+            0xcf => Ok(Command::CABCControl8),
+            0xd0 => Ok(Command::NVMemoryWrite),
+            0xd1 => Ok(Command::NVMemoryProtectionKey),
+            0xd2 => Ok(Command::NVMemoryStatusRead),
+            0xd3 => Ok(Command::ReadID4),
+            0xe0 => Ok(Command::PGAMCTRL),
+            0xe1 => Ok(Command::NGAMCTRL),
+            0xe2 => Ok(Command::DigitalGammaControl1),
+            0xe3 => Ok(Command::DigitalGammaControl2),
+            0xfb => Ok(Command::SPIReadCommandSetting),
+            _ => Err(opcode),
+        }
+    }
+}
+
+impl Command {
+    /// The short name used in the datasheet, e.g. `CASET`.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Command::Nop => "NOP",
+            Command::SoftReset => "SWRESET",
+            Command::ReadDisplayId => "RDDIDIF",
+            Command::ReadErrors => "RDNUMED",
+            Command::ReadDisplayStatus => "RDDST",
+            Command::ReadDisplayPowerMode => "RDDPM",
+            Command::ReadDisplayMADCTL => "RDDMADCTL",
+            Command::ReadDisplayPixelFormat => "RDDCOLMOD",
+            Command::ReadDisplayImageMode => "RDDIM",
+            Command::ReadDisplaySignalMode => "RDDSM",
+            Command::ReadDisplaySelfDiagResult => "RDDSDR",
+            Command::SleepIn => "SLPIN",
+            Command::SleepOut => "SLPOUT",
+            Command::PartialModeOn => "PTLON",
+            Command::NormalDisplayMode => "NORON",
+            Command::DisplayInversionOff => "INVOFF",
+            Command::DisplayInversionOn => "INVON",
+            Command::DisplayOff => "DISPOFF",
+            Command::DisplayOn => "DISPON",
+            Command::ColumnAddressSet => "CASET",
+            Command::PageAddressSet => "PASET",
+            Command::MemoryWrite => "RAMWR",
+            Command::MemoryRead => "RAMRD",
+            Command::PartialArea => "PTLAR",
+            Command::VerticalScrollingDefinition => "VSCRDEF",
+            Command::TearingEffectLineOff => "TEOFF",
+            Command::TearingEffectLineOn => "TEON",
+            Command::MemoryAccessControl => "MADCTL",
+            Command::VerticalScrollingStartAddress => "VSCRSADD",
+            Command::IdleModeOff => "IDMOFF",
+            Command::IdleModeOn => "IDMON",
+            Command::InterfacePixelFormat => "COLMOD",
+            Command::MemoryWriteContinue => "RAMWRC",
+            Command::MemoryReadContinue => "RAMRDC",
+            Command::WriteTearScanLine => "TESLWR",
+            Command::ReadTearScanLine => "TESLRD",
+            Command::WriteDisplayBrightnessValue => "WRDISBV",
+            Command::ReadDisplayBrigthnessValue => "RDDISBV",
+            Command::WriteCTRLDisplayValue => "WRCTRLD",
+            Command::ReadCTRLDisplayValue => "RDCTRLD",
+            Command::WriteCABrigthnessControl => "WRCABC",
+            Command::ReadCABrigthnessControl => "RDCABC",
+            Command::WriteCABCMinBrigthness => "WRCABCMB",
+            Command::ReadCABCMinBrigthness => "RDCABCMB",
+            Command::ReadFirstChecksum => "RDFCS",
+            Command::ReadContinueChecksum => "RDCFCS",
+            Command::ReadID1 => "RDID1",
+            Command::ReadID2 => "RDID2",
+            Command::ReadID3 => "RDID3",
+            Command::InterfaceModeControl => "IFMODE",
+            Command::FrameRateControlNormal => "FRMCTR1",
+            Command::FrameRateControlIdle => "FRMCTR2",
+            Command::FrameRateControlPartial => "FRMCTR3",
+            Command::DisplayInversionControl => "INVTR",
+            Command::BlankingPorchControl => "PRCTR",
+            Command::DisplayFunctionControl => "DISCTRL",
+            Command::EntryModeSet => "ETMOD",
+            Command::PowerControl1 => "PWCTRL1",
+            Command::PowerControl2 => "PWCTRL2",
+            Command::PowerControl3 => "PWCTRL3",
+            Command::PowerControl4 => "PWCTRL4",
+            Command::PowerControl5 => "PWCTRL5",
+            Command::VCOMControl => "VMCTRL",
+            Command::CABCControl9 => "CABCCTRL9",
+            Command::CABCControl1 => "CABCCTRL1",
+            Command::CABCControl2 => "CABCCTRL2",
+            Command::CABCControl3 => "CABCCTRL3",
+            Command::CABCControl4 => "CABCCTRL4",
+            Command::CABCControl5 => "CABCCTRL5",
+            Command::CABCControl6 => "CABCCTRL6",
+            Command::CABCControl7 => "CABCCTRL7",
+            Command::CABCControl8 => "CABCCTRL8",
+            Command::NVMemoryWrite => "NVMWR",
+            Command::NVMemoryProtectionKey => "NVMPKEY",
+            Command::NVMemoryStatusRead => "RDNVM",
+            Command::ReadID4 => "RDID4",
+            Command::PGAMCTRL => "PGAMCTRL",
+            Command::NGAMCTRL => "NGAMCTRL",
+            Command::DigitalGammaControl1 => "DGAMCTRL1",
+            Command::DigitalGammaControl2 => "DGAMCTRL2",
+            Command::SPIReadCommandSetting => "SPIRCS",
+        }
+    }
+
+    /// Whether the controller answers the command with data, register and frame memory reads.
+    pub fn is_read(&self) -> bool {
+        matches!(
+            self,
+            Command::ReadDisplayId
+                | Command::ReadErrors
+                | Command::ReadDisplayStatus
+                | Command::ReadDisplayPowerMode
+                | Command::ReadDisplayMADCTL
+                | Command::ReadDisplayPixelFormat
+                | Command::ReadDisplayImageMode
+                | Command::ReadDisplaySignalMode
+                | Command::ReadDisplaySelfDiagResult
+                | Command::MemoryRead
+                | Command::MemoryReadContinue
+                | Command::ReadTearScanLine
+                | Command::ReadDisplayBrigthnessValue
+                | Command::ReadCTRLDisplayValue
+                | Command::ReadCABrigthnessControl
+                | Command::ReadCABCMinBrigthness
+                | Command::ReadFirstChecksum
+                | Command::ReadContinueChecksum
+                | Command::ReadID1
+                | Command::ReadID2
+                | Command::ReadID3
+                | Command::NVMemoryStatusRead
+                | Command::ReadID4
+        )
+    }
+
+    /// Number of dummy words the controller sends on the parallel bus before the response, 1 for
+    /// commands that [read](Command::is_read) and 0 for the others.
+    ///
+    /// On the parallel bus the ILI9486 does not tell register reads and frame memory reads apart,
+    /// both start with one dummy word whatever the bus width. The serial interfaces differ, e.g.
+    /// 3-wire register reads have no dummy word, and make up for it so the driver discards the
+    /// same number of words on every interface.
+    pub fn dummy_reads(&self) -> usize {
+        if self.is_read() {
+            1
+        } else {
+            0
+        }
+    }
+}
+
 pub trait Commands {
     fn set_interface_pixel_format(
         &mut self,
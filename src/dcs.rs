@@ -0,0 +1,685 @@
+use crate::color::{PixelFormat, PixelWriter};
+use crate::nvm::{NvmStatus, NvmTarget};
+use crate::{health, Command, ILI9486};
+use display_interface::v2::*;
+use display_interface::DisplayError;
+
+/// Number of parameters converted to bus words at a time, the largest command has 64.
+pub const MAX_PARAMETERS: usize = 64;
+
+/// The largest number of response bytes of any read command, excluding the dummy read.
+pub const MAX_RESPONSE_LEN: usize = 4;
+
+/// A command with strongly typed parameters.
+///
+/// `Parameters` is a fixed size array, e.g. `[u8; 4]`, so a command can only be sent with the
+/// number of parameters the controller expects. The parameters sent are exactly the ones
+/// returned by [encode](DcsCommand::encode).
+pub trait DcsCommand {
+    /// The command opcode
+    const COMMAND: Command;
+
+    type Parameters: AsRef<[u8]>;
+
+    /// Encodes the parameters in the order they are sent.
+    fn encode(&self) -> Self::Parameters;
+}
+
+/// A command that reads a response from the controller.
+pub trait DcsRead: DcsCommand {
+    type Response;
+
+    /// Number of response bytes, excluding the dummy read
+    const RESPONSE_LEN: usize;
+
+    /// Decodes the response, `data` is `RESPONSE_LEN` bytes long.
+    fn decode(data: &[u8]) -> Self::Response;
+}
+
+fn split_u16(value: u16) -> [u8; 2] {
+    [(value >> 8) as u8, (value & 0xff) as u8]
+}
+
+fn join_u16(data: &[u8]) -> u16 {
+    ((data[0] as u16) << 8) | data[1] as u16
+}
+
+macro_rules! no_parameters {
+    ($($(#[$meta:meta])* $name:ident,)+) => {
+        $(
+            $(#[$meta])*
+            #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+            pub struct $name;
+
+            impl DcsCommand for $name {
+                const COMMAND: Command = Command::$name;
+                type Parameters = [u8; 0];
+
+                fn encode(&self) -> [u8; 0] {
+                    []
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! raw_parameters {
+    ($($(#[$meta:meta])* $name:ident: $count:expr,)+) => {
+        $(
+            $(#[$meta])*
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub struct $name(pub [u8; $count]);
+
+            impl DcsCommand for $name {
+                const COMMAND: Command = Command::$name;
+                type Parameters = [u8; $count];
+
+                fn encode(&self) -> [u8; $count] {
+                    self.0
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! byte_parameter {
+    ($($(#[$meta:meta])* $name:ident,)+) => {
+        $(
+            $(#[$meta])*
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub struct $name(pub u8);
+
+            impl DcsCommand for $name {
+                const COMMAND: Command = Command::$name;
+                type Parameters = [u8; 1];
+
+                fn encode(&self) -> [u8; 1] {
+                    [self.0]
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! byte_response {
+    ($($(#[$meta:meta])* $name:ident,)+) => {
+        no_parameters!($($(#[$meta])* $name,)+);
+
+        $(
+            impl DcsRead for $name {
+                type Response = u8;
+                const RESPONSE_LEN: usize = 1;
+
+                fn decode(data: &[u8]) -> u8 {
+                    data[0]
+                }
+            }
+        )+
+    };
+}
+
+no_parameters!(
+    Nop,
+    SoftReset,
+    SleepIn,
+    SleepOut,
+    PartialModeOn,
+    NormalDisplayMode,
+    DisplayInversionOff,
+    DisplayInversionOn,
+    DisplayOff,
+    DisplayOn,
+    /// Starts a pixel transfer at the start of the column and page window
+    MemoryWrite,
+    /// Reads pixels from the start of the column and page window, the pixel data is read with
+    /// [reader](ILI9486::reader)
+    MemoryRead,
+    TearingEffectLineOff,
+    IdleModeOff,
+    IdleModeOn,
+    /// Continues a pixel transfer from where the last one ended
+    MemoryWriteContinue,
+    /// Continues reading pixels from where the last read ended
+    MemoryReadContinue,
+);
+
+byte_response!(
+    /// Number of errors on the DSI link
+    ReadErrors,
+    ReadDisplayImageMode,
+    ReadDisplaySignalMode,
+    ReadDisplaySelfDiagResult,
+    ReadDisplayBrigthnessValue,
+    ReadCTRLDisplayValue,
+    ReadCABrigthnessControl,
+    ReadCABCMinBrigthness,
+    ReadFirstChecksum,
+    ReadContinueChecksum,
+    ReadID1,
+    ReadID2,
+    ReadID3,
+);
+
+no_parameters!(
+    ReadDisplayId,
+    ReadDisplayStatus,
+    ReadDisplayPowerMode,
+    ReadDisplayMADCTL,
+    /// Reads the pixel format of the MCU interface, `None` for DBI bits of no
+    /// [PixelFormat](PixelFormat)
+    ReadDisplayPixelFormat,
+    ReadTearScanLine,
+    ReadID4,
+    NVMemoryStatusRead,
+);
+
+byte_parameter!(
+    WriteDisplayBrightnessValue,
+    WriteCTRLDisplayValue,
+    WriteCABrigthnessControl,
+    WriteCABCMinBrigthness,
+    InterfaceModeControl,
+    DisplayInversionControl,
+    EntryModeSet,
+    PowerControl2,
+    PowerControl3,
+    PowerControl4,
+    PowerControl5,
+    CABCControl1,
+    CABCControl2,
+    CABCControl3,
+    CABCControl4,
+    CABCControl5,
+    CABCControl6,
+    CABCControl7,
+    CABCControl8,
+    CABCControl9,
+    SPIReadCommandSetting,
+);
+
+raw_parameters!(
+    FrameRateControlNormal: 2,
+    FrameRateControlIdle: 2,
+    FrameRateControlPartial: 2,
+    BlankingPorchControl: 4,
+    DisplayFunctionControl: 3,
+    PowerControl1: 2,
+    VCOMControl: 4,
+    PGAMCTRL: 15,
+    NGAMCTRL: 15,
+    DigitalGammaControl1: 16,
+    DigitalGammaControl2: 64,
+);
+
+/// Sets the column window, `start` and `end` are inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColumnAddressSet {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl DcsCommand for ColumnAddressSet {
+    const COMMAND: Command = Command::ColumnAddressSet;
+    type Parameters = [u8; 4];
+
+    fn encode(&self) -> [u8; 4] {
+        let start = split_u16(self.start);
+        let end = split_u16(self.end);
+        [start[0], start[1], end[0], end[1]]
+    }
+}
+
+/// Sets the page window, `start` and `end` are inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageAddressSet {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl DcsCommand for PageAddressSet {
+    const COMMAND: Command = Command::PageAddressSet;
+    type Parameters = [u8; 4];
+
+    fn encode(&self) -> [u8; 4] {
+        let start = split_u16(self.start);
+        let end = split_u16(self.end);
+        [start[0], start[1], end[0], end[1]]
+    }
+}
+
+/// The address window of an area, programmed before `MemoryWrite` or `MemoryRead` by both the
+/// blocking and the async driver.
+pub(crate) fn address_window(
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+) -> (ColumnAddressSet, PageAddressSet) {
+    let columns = ColumnAddressSet {
+        start: x,
+        end: x + width - 1,
+    };
+    let pages = PageAddressSet {
+        start: y,
+        end: y + height - 1,
+    };
+    (columns, pages)
+}
+
+/// Sets the rows shown in partial mode, `start` and `end` are inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartialArea {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl DcsCommand for PartialArea {
+    const COMMAND: Command = Command::PartialArea;
+    type Parameters = [u8; 4];
+
+    fn encode(&self) -> [u8; 4] {
+        let start = split_u16(self.start);
+        let end = split_u16(self.end);
+        [start[0], start[1], end[0], end[1]]
+    }
+}
+
+/// Splits the frame memory into a top fixed area, a scrolling area and a bottom fixed area, in lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerticalScrollingDefinition {
+    pub top_fixed: u16,
+    pub scroll_area: u16,
+    pub bottom_fixed: u16,
+}
+
+impl DcsCommand for VerticalScrollingDefinition {
+    const COMMAND: Command = Command::VerticalScrollingDefinition;
+    type Parameters = [u8; 6];
+
+    fn encode(&self) -> [u8; 6] {
+        let top = split_u16(self.top_fixed);
+        let scroll = split_u16(self.scroll_area);
+        let bottom = split_u16(self.bottom_fixed);
+        [top[0], top[1], scroll[0], scroll[1], bottom[0], bottom[1]]
+    }
+}
+
+/// Sets the line of the frame memory shown at the top of the scrolling area.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerticalScrollingStartAddress(pub u16);
+
+impl DcsCommand for VerticalScrollingStartAddress {
+    const COMMAND: Command = Command::VerticalScrollingStartAddress;
+    type Parameters = [u8; 2];
+
+    fn encode(&self) -> [u8; 2] {
+        split_u16(self.0)
+    }
+}
+
+/// Sets the line at which the tearing effect signal is output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WriteTearScanLine(pub u16);
+
+impl DcsCommand for WriteTearScanLine {
+    const COMMAND: Command = Command::WriteTearScanLine;
+    type Parameters = [u8; 2];
+
+    fn encode(&self) -> [u8; 2] {
+        split_u16(self.0)
+    }
+}
+
+/// Turns on the tearing effect output, on vertical blanking only or also on horizontal blanking.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TearingEffectLineOn {
+    pub horizontal_blanking: bool,
+}
+
+impl DcsCommand for TearingEffectLineOn {
+    const COMMAND: Command = Command::TearingEffectLineOn;
+    type Parameters = [u8; 1];
+
+    fn encode(&self) -> [u8; 1] {
+        [self.horizontal_blanking as u8]
+    }
+}
+
+/// MADCTL, the bits are available as constants.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryAccessControl(pub u8);
+
+impl MemoryAccessControl {
+    /// MY, row address order
+    pub const ROW_ADDRESS_ORDER: u8 = 1 << 7;
+    /// MX, column address order
+    pub const COLUMN_ADDRESS_ORDER: u8 = 1 << 6;
+    /// MV, row/column exchange
+    pub const ROW_COLUMN_EXCHANGE: u8 = 1 << 5;
+    /// ML, vertical refresh order
+    pub const VERTICAL_REFRESH_ORDER: u8 = 1 << 4;
+    /// BGR, swaps red and blue
+    pub const BGR: u8 = 1 << 3;
+    /// MH, horizontal refresh order
+    pub const HORIZONTAL_REFRESH_ORDER: u8 = 1 << 2;
+
+    /// Number of columns and pages, swapped when rows and columns are exchanged.
+    pub fn dimensions(&self) -> (u16, u16) {
+        if self.0 & MemoryAccessControl::ROW_COLUMN_EXCHANGE != 0 {
+            (480, 320)
+        } else {
+            (320, 480)
+        }
+    }
+}
+
+impl DcsCommand for MemoryAccessControl {
+    const COMMAND: Command = Command::MemoryAccessControl;
+    type Parameters = [u8; 1];
+
+    fn encode(&self) -> [u8; 1] {
+        [self.0]
+    }
+}
+
+/// COLMOD, sets the pixel format of the MCU interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterfacePixelFormat(pub PixelFormat);
+
+impl DcsCommand for InterfacePixelFormat {
+    const COMMAND: Command = Command::InterfacePixelFormat;
+    type Parameters = [u8; 1];
+
+    fn encode(&self) -> [u8; 1] {
+        // the same format is used for the RGB (DPI) interface
+        let bits = self.0.dbi_bits();
+        [(bits << 4) | bits]
+    }
+}
+
+/// Programs a value into NV memory, it is only written once the protection key is sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NVMemoryWrite {
+    pub target: NvmTarget,
+    pub value: u8,
+}
+
+impl DcsCommand for NVMemoryWrite {
+    const COMMAND: Command = Command::NVMemoryWrite;
+    type Parameters = [u8; 2];
+
+    fn encode(&self) -> [u8; 2] {
+        [self.target.address(), self.value]
+    }
+}
+
+/// Starts NV memory programming.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NVMemoryProtectionKey;
+
+impl DcsCommand for NVMemoryProtectionKey {
+    const COMMAND: Command = Command::NVMemoryProtectionKey;
+    type Parameters = [u8; 3];
+
+    fn encode(&self) -> [u8; 3] {
+        [0x55, 0xaa, 0x66]
+    }
+}
+
+/// Result of [ReadDisplayId].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayId {
+    pub manufacturer: u8,
+    pub version: u8,
+    pub id: u8,
+}
+
+impl DcsRead for ReadDisplayId {
+    type Response = DisplayId;
+    const RESPONSE_LEN: usize = 3;
+
+    fn decode(data: &[u8]) -> DisplayId {
+        DisplayId {
+            manufacturer: data[0],
+            version: data[1],
+            id: data[2],
+        }
+    }
+}
+
+impl DcsRead for ReadDisplayStatus {
+    type Response = u32;
+    const RESPONSE_LEN: usize = 4;
+
+    fn decode(data: &[u8]) -> u32 {
+        ((data[0] as u32) << 24)
+            | ((data[1] as u32) << 16)
+            | ((data[2] as u32) << 8)
+            | data[3] as u32
+    }
+}
+
+/// Result of [ReadDisplayPowerMode].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PowerMode(pub u8);
+
+impl PowerMode {
+    pub fn booster_on(&self) -> bool {
+        self.0 & (1 << 7) != 0
+    }
+
+    pub fn idle_mode(&self) -> bool {
+        self.0 & (1 << 6) != 0
+    }
+
+    pub fn partial_mode(&self) -> bool {
+        self.0 & (1 << 5) != 0
+    }
+
+    pub fn sleep_out(&self) -> bool {
+        self.0 & (1 << 4) != 0
+    }
+
+    pub fn normal_mode(&self) -> bool {
+        self.0 & (1 << 3) != 0
+    }
+
+    pub fn display_on(&self) -> bool {
+        self.0 & (1 << 2) != 0
+    }
+}
+
+impl DcsRead for ReadDisplayPowerMode {
+    type Response = PowerMode;
+    const RESPONSE_LEN: usize = 1;
+
+    fn decode(data: &[u8]) -> PowerMode {
+        PowerMode(data[0])
+    }
+}
+
+impl DcsRead for ReadDisplayMADCTL {
+    type Response = MemoryAccessControl;
+    const RESPONSE_LEN: usize = 1;
+
+    fn decode(data: &[u8]) -> MemoryAccessControl {
+        MemoryAccessControl(data[0])
+    }
+}
+
+impl DcsRead for ReadDisplayPixelFormat {
+    type Response = Option<PixelFormat>;
+    const RESPONSE_LEN: usize = 1;
+
+    fn decode(data: &[u8]) -> Option<PixelFormat> {
+        PixelFormat::from_dbi_bits(data[0])
+    }
+}
+
+impl DcsRead for ReadTearScanLine {
+    type Response = u16;
+    const RESPONSE_LEN: usize = 2;
+
+    fn decode(data: &[u8]) -> u16 {
+        join_u16(data)
+    }
+}
+
+impl DcsRead for ReadID4 {
+    type Response = [u8; 3];
+    const RESPONSE_LEN: usize = 3;
+
+    fn decode(data: &[u8]) -> [u8; 3] {
+        [data[0], data[1], data[2]]
+    }
+}
+
+impl DcsRead for NVMemoryStatusRead {
+    type Response = NvmStatus;
+    const RESPONSE_LEN: usize = 2;
+
+    fn decode(data: &[u8]) -> NvmStatus {
+        NvmStatus::from_registers(data[0], data[1])
+    }
+}
+
+impl<RW, T> ILI9486<RW, T>
+where
+    RW: ReadWriteInterface<T> + PixelWriter<T>,
+    T: From<u8> + Copy + Default,
+{
+    /// Sends a command and its parameters.
+    ///
+    /// Returns `BusWriteError` while a [write_area_dma](ILI9486::write_area_dma) transfer is
+    /// running.
+    pub fn send<C>(&mut self, command: &C) -> Result<(), DisplayError>
+    where
+        C: DcsCommand,
+    {
+        self.check_idle()?;
+        self.window.command(C::COMMAND);
+        self.power_mode = health::expected_power_mode(self.power_mode, C::COMMAND);
+        self.rw_interface
+            .write(WriteMode::Command, &[(C::COMMAND as u8).into()])?;
+
+        let parameters = command.encode();
+        let mut data = [T::default(); MAX_PARAMETERS];
+        for chunk in parameters.as_ref().chunks(MAX_PARAMETERS) {
+            for (word, byte) in data.iter_mut().zip(chunk) {
+                *word = (*byte).into();
+            }
+            self.rw_interface
+                .write(WriteMode::Data, &data[..chunk.len()])?;
+        }
+        Ok(())
+    }
+}
+
+impl<RW, T> ILI9486<RW, T>
+where
+    RW: ReadWriteInterface<T> + PixelWriter<T>,
+    T: From<u8> + Into<u32> + Copy + Default,
+{
+    /// Sends a read command and decodes the response.
+    pub fn query<C>(&mut self, command: &C) -> Result<C::Response, DisplayError>
+    where
+        C: DcsRead,
+    {
+        self.send(command)?;
+
+        // the response follows the dummy reads
+        let dummy = C::COMMAND.dummy_reads();
+        let mut data = [T::default(); MAX_RESPONSE_LEN + 1];
+        self.rw_interface
+            .read(&mut data[..dummy + C::RESPONSE_LEN])?;
+
+        let mut response = [0u8; MAX_RESPONSE_LEN];
+        for (byte, word) in response.iter_mut().zip(&data[dummy..]) {
+            *byte = (*word).into() as u8;
+        }
+        Ok(C::decode(&response[..C::RESPONSE_LEN]))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::decode::BusWidth;
+    use crate::emulator::{Emulator, NoDelay, NoPin};
+    use crate::io::shim::OutputOnlyIoPin;
+    use crate::recording::{Record, Recording};
+    use std::vec::Vec;
+
+    fn sent<C: DcsCommand>(command: &C) -> Vec<Record> {
+        let recording = Recording::new(Emulator::new(), BusWidth::Bits8, Vec::new());
+        let mut lcd: ILI9486<_, u8> = ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb565,
+            recording,
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap();
+        lcd.writer().clear();
+        lcd.send(command).unwrap();
+        lcd.writer().buffer().clone()
+    }
+
+    fn expected<C: DcsCommand>(command: &C) -> Vec<Record> {
+        let mut records = std::vec![Record::Command(C::COMMAND as u32)];
+        records.extend(
+            command
+                .encode()
+                .as_ref()
+                .iter()
+                .map(|byte| Record::Data(*byte as u32)),
+        );
+        records
+    }
+
+    #[test]
+    fn sends_encoded_parameters() {
+        let column = ColumnAddressSet {
+            start: 0x0102,
+            end: 0x013f,
+        };
+        assert_eq!(sent(&column), expected(&column));
+        assert_eq!(sent(&DisplayOn), expected(&DisplayOn));
+
+        let mut gamma = [0u8; 64];
+        for (i, value) in gamma.iter_mut().enumerate() {
+            *value = i as u8;
+        }
+        let gamma = DigitalGammaControl2(gamma);
+        assert_eq!(sent(&gamma).len(), 65);
+        assert_eq!(sent(&gamma), expected(&gamma));
+    }
+
+    #[test]
+    fn decodes_the_pixel_format() {
+        assert_eq!(
+            ReadDisplayPixelFormat::decode(&[0x55]),
+            Some(PixelFormat::Rgb565)
+        );
+        assert_eq!(
+            ReadDisplayPixelFormat::decode(&[0x66]),
+            Some(PixelFormat::Rgb666)
+        );
+        assert_eq!(
+            ReadDisplayPixelFormat::decode(&[0x11]),
+            Some(PixelFormat::Rgb111)
+        );
+        assert_eq!(ReadDisplayPixelFormat::decode(&[0x07]), None);
+
+        let mut lcd: ILI9486<_, u8> = ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb666,
+            Emulator::new(),
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap();
+        assert_eq!(
+            lcd.query(&ReadDisplayPixelFormat).unwrap(),
+            Some(PixelFormat::Rgb666)
+        );
+    }
+}
@@ -0,0 +1,534 @@
+use crate::color::PixelFormat;
+use crate::Command;
+use core::convert::TryFrom;
+use core::fmt;
+
+/// Number of parameter words kept per event, longer parameter lists are counted but truncated.
+pub const MAX_WORDS: usize = 16;
+
+/// Width of the bus the words were captured on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusWidth {
+    Bits8,
+    Bits9,
+    Bits16,
+    Bits18,
+}
+
+/// The words sent with or read after a command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Words {
+    data: [u32; MAX_WORDS],
+    len: usize,
+}
+
+impl Words {
+    fn new() -> Words {
+        Words {
+            data: [0; MAX_WORDS],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, word: u32) {
+        if self.len < MAX_WORDS {
+            self.data[self.len] = word;
+        }
+        self.len += 1;
+    }
+
+    /// The total number of words, including the ones that were not kept.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The first [MAX_WORDS](MAX_WORDS) words.
+    pub fn as_slice(&self) -> &[u32] {
+        &self.data[..self.len.min(MAX_WORDS)]
+    }
+
+    fn u16_at(&self, index: usize) -> Option<u16> {
+        let words = self.as_slice();
+        if words.len() < index + 2 {
+            return None;
+        }
+        Some((((words[index] & 0xff) as u16) << 8) | (words[index + 1] & 0xff) as u16)
+    }
+}
+
+impl fmt::Display for Words {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, word) in self.as_slice().iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02x}", word)?;
+        }
+        if self.len > MAX_WORDS {
+            write!(f, " .. ({} words)", self.len)?;
+        }
+        Ok(())
+    }
+}
+
+/// A decoded bus transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A command with its parameters and the words read back after it
+    Command {
+        command: Command,
+        parameters: Words,
+        read: Words,
+    },
+    /// `MemoryWrite` or `MemoryWriteContinue` followed by pixel data
+    MemoryWrite {
+        command: Command,
+        words: usize,
+        pixels: usize,
+    },
+    /// `MemoryRead` or `MemoryReadContinue` followed by pixel data
+    MemoryRead {
+        command: Command,
+        words: usize,
+        pixels: usize,
+    },
+    /// An opcode that is not a known [Command](Command)
+    Unknown {
+        opcode: u8,
+        parameters: Words,
+        read: Words,
+    },
+    /// Data that was not preceded by a command
+    Data { written: Words, read: Words },
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Command {
+                command,
+                parameters,
+                read,
+            } => {
+                f.write_str(command.mnemonic())?;
+                match command {
+                    Command::ColumnAddressSet | Command::PageAddressSet | Command::PartialArea => {
+                        match (parameters.u16_at(0), parameters.u16_at(2)) {
+                            (Some(start), Some(end)) => write!(f, " {}..{}", start, end)?,
+                            _ => write!(f, " {}", parameters)?,
+                        }
+                    }
+                    Command::VerticalScrollingDefinition => {
+                        match (
+                            parameters.u16_at(0),
+                            parameters.u16_at(2),
+                            parameters.u16_at(4),
+                        ) {
+                            (Some(top), Some(scroll), Some(bottom)) => {
+                                write!(f, " top {} scroll {} bottom {}", top, scroll, bottom)?
+                            }
+                            _ => write!(f, " {}", parameters)?,
+                        }
+                    }
+                    Command::VerticalScrollingStartAddress | Command::WriteTearScanLine => {
+                        match parameters.u16_at(0) {
+                            Some(value) => write!(f, " {}", value)?,
+                            None => write!(f, " {}", parameters)?,
+                        }
+                    }
+                    _ => {
+                        if !parameters.is_empty() {
+                            write!(f, " {}", parameters)?;
+                        }
+                    }
+                }
+                if !read.is_empty() {
+                    write!(f, " <- {}", read)?;
+                }
+                Ok(())
+            }
+            Event::MemoryWrite {
+                command, pixels, ..
+            } => write!(f, "{} {} px", command.mnemonic(), pixels),
+            Event::MemoryRead {
+                command, pixels, ..
+            } => write!(f, "{} <- {} px", command.mnemonic(), pixels),
+            Event::Unknown {
+                opcode,
+                parameters,
+                read,
+            } => {
+                write!(f, "?{:02x}", opcode)?;
+                if !parameters.is_empty() {
+                    write!(f, " {}", parameters)?;
+                }
+                if !read.is_empty() {
+                    write!(f, " <- {}", read)?;
+                }
+                Ok(())
+            }
+            Event::Data { written, read } => {
+                f.write_str("DATA")?;
+                if !written.is_empty() {
+                    write!(f, " {}", written)?;
+                }
+                if !read.is_empty() {
+                    write!(f, " <- {}", read)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+struct Pending {
+    opcode: Option<u8>,
+    written: Words,
+    read: Words,
+}
+
+/// Decodes a stream of bus words into [Event](Event)s.
+///
+/// Events are only complete once the next command starts, so [push](Decoder::push) returns the
+/// previous event when a command is pushed, and [finish](Decoder::finish) returns the last one.
+pub struct Decoder {
+    width: BusWidth,
+    pixel_format: PixelFormat,
+    pending: Option<Pending>,
+}
+
+impl Decoder {
+    pub fn new(width: BusWidth) -> Decoder {
+        Decoder {
+            width,
+            // the reset default of the controller
+            pixel_format: PixelFormat::Rgb666,
+            pending: None,
+        }
+    }
+
+    /// The pixel format used to count pixels, updated when an `InterfacePixelFormat` is decoded.
+    ///
+    /// `Rgb666TwoTransfer` has the same parameter as `Rgb666` and is kept, it is only set with
+    /// [set_pixel_format](Decoder::set_pixel_format).
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.pixel_format
+    }
+
+    pub fn set_pixel_format(&mut self, pixel_format: PixelFormat) {
+        self.pixel_format = pixel_format;
+    }
+
+    /// Adds a written word.
+    ///
+    /// # Arguments
+    ///
+    /// `dcx` - The level of D/CX, low for a command, high for data
+    ///
+    /// `word` - The word on the data lines
+    ///
+    pub fn push(&mut self, dcx: bool, word: u32) -> Option<Event> {
+        if !dcx {
+            let event = self.finish();
+            self.pending = Some(Pending {
+                opcode: Some(word as u8),
+                written: Words::new(),
+                read: Words::new(),
+            });
+            return event;
+        }
+
+        self.pending_mut().written.push(word);
+        None
+    }
+
+    /// Adds a word that was read from the controller.
+    pub fn push_read(&mut self, word: u32) {
+        self.pending_mut().read.push(word);
+    }
+
+    /// Returns the event that is in progress.
+    pub fn finish(&mut self) -> Option<Event> {
+        let pending = self.pending.take()?;
+
+        let opcode = match pending.opcode {
+            Some(opcode) => opcode,
+            None => {
+                return Some(Event::Data {
+                    written: pending.written,
+                    read: pending.read,
+                })
+            }
+        };
+
+        let command = match Command::try_from(opcode) {
+            Ok(command) => command,
+            Err(opcode) => {
+                return Some(Event::Unknown {
+                    opcode,
+                    parameters: pending.written,
+                    read: pending.read,
+                })
+            }
+        };
+
+        match command {
+            Command::MemoryWrite | Command::MemoryWriteContinue => Some(Event::MemoryWrite {
+                command,
+                words: pending.written.len(),
+                pixels: self.pixels(pending.written.len()),
+            }),
+            Command::MemoryRead | Command::MemoryReadContinue => {
+                // the first word is a dummy read
+                let words = pending.read.len().saturating_sub(1);
+                Some(Event::MemoryRead {
+                    command,
+                    words,
+                    pixels: self.read_pixels(words),
+                })
+            }
+            _ => {
+                if command == Command::InterfacePixelFormat {
+                    let format = pending.written.as_slice().first();
+                    match format.and_then(|f| PixelFormat::from_dbi_bits(*f as u8)) {
+                        // the transfer mode is not part of the parameter
+                        Some(PixelFormat::Rgb666)
+                            if self.pixel_format == PixelFormat::Rgb666TwoTransfer => {}
+                        Some(format) => self.pixel_format = format,
+                        None => {}
+                    }
+                }
+                Some(Event::Command {
+                    command,
+                    parameters: pending.written,
+                    read: pending.read,
+                })
+            }
+        }
+    }
+
+    fn pending_mut(&mut self) -> &mut Pending {
+        self.pending.get_or_insert(Pending {
+            opcode: None,
+            written: Words::new(),
+            read: Words::new(),
+        })
+    }
+
+    /// Number of pixels in `words` written words.
+    fn pixels(&self, words: usize) -> usize {
+        match (self.width, self.pixel_format) {
+            (BusWidth::Bits8, PixelFormat::Rgb565) => words / 2,
+            (BusWidth::Bits8, PixelFormat::Rgb666)
+            | (BusWidth::Bits8, PixelFormat::Rgb666TwoTransfer) => words / 3,
+            (BusWidth::Bits8, PixelFormat::Rgb111) => words * 2,
+            (BusWidth::Bits16, PixelFormat::Rgb565) => words,
+            (BusWidth::Bits16, PixelFormat::Rgb666) => words / 3 * 2,
+            (BusWidth::Bits9, _) | (BusWidth::Bits16, _) => words / 2,
+            (BusWidth::Bits18, _) => words,
+        }
+    }
+
+    /// Number of pixels in `words` read words, pixels are always read as 18 bits.
+    fn read_pixels(&self, words: usize) -> usize {
+        match self.width {
+            BusWidth::Bits8 => words / 3,
+            BusWidth::Bits9 => words / 2,
+            BusWidth::Bits16 => words * 2 / 3,
+            BusWidth::Bits18 => words,
+        }
+    }
+}
+
+/// Decodes a whole capture, calling `f` with every event.
+pub fn decode<I>(width: BusWidth, words: I, f: &mut dyn FnMut(Event))
+where
+    I: IntoIterator<Item = (bool, u32)>,
+{
+    let mut decoder = Decoder::new(width);
+    for (dcx, word) in words {
+        if let Some(event) = decoder.push(dcx, word) {
+            f(event);
+        }
+    }
+    if let Some(event) = decoder.finish() {
+        f(event);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+    use std::vec::Vec;
+
+    fn events(width: BusWidth, words: &[(bool, u32)]) -> Vec<Event> {
+        let mut events = Vec::new();
+        decode(width, words.iter().cloned(), &mut |event| {
+            events.push(event)
+        });
+        events
+    }
+
+    fn text(width: BusWidth, words: &[(bool, u32)]) -> Vec<std::string::String> {
+        events(width, words)
+            .iter()
+            .map(|event| event.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn commands_and_parameters() {
+        let words = [
+            (false, 0x2a),
+            (true, 0x00),
+            (true, 0x10),
+            (true, 0x01),
+            (true, 0x3f),
+            (false, 0x33),
+            (true, 0),
+            (true, 8),
+            (true, 0x01),
+            (true, 0xd0),
+            (true, 0),
+            (true, 8),
+            (false, 0x29),
+        ];
+        assert_eq!(
+            text(BusWidth::Bits8, &words),
+            [
+                "CASET 16..319",
+                "VSCRDEF top 8 scroll 464 bottom 8",
+                "DISPON"
+            ]
+        );
+
+        match events(BusWidth::Bits8, &words)[0] {
+            Event::Command {
+                command,
+                parameters,
+                read,
+            } => {
+                assert_eq!(command, Command::ColumnAddressSet);
+                assert_eq!(parameters.as_slice(), [0x00, 0x10, 0x01, 0x3f]);
+                assert!(read.is_empty());
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+    }
+
+    #[test]
+    fn reads_unknown_and_stray_data() {
+        let mut decoder = Decoder::new(BusWidth::Bits8);
+        assert_eq!(decoder.push(true, 0x55), None);
+        assert_eq!(
+            decoder.push(false, 0x04),
+            Some(Event::Data {
+                written: {
+                    let mut words = Words::new();
+                    words.push(0x55);
+                    words
+                },
+                read: Words::new(),
+            })
+        );
+        for word in [0x00, 0x54, 0x80, 0x66].iter() {
+            decoder.push_read(*word);
+        }
+        assert_eq!(
+            decoder.push(false, 0xfe).unwrap().to_string(),
+            "RDDIDIF <- 00 54 80 66"
+        );
+        decoder.push(true, 0x01);
+        assert_eq!(decoder.finish().unwrap().to_string(), "?fe 01");
+        assert_eq!(decoder.finish(), None);
+    }
+
+    #[test]
+    fn truncates_long_parameter_lists() {
+        let mut words = std::vec![(false, 0xe3)];
+        words.extend((0..64).map(|i| (true, i)));
+        match events(BusWidth::Bits8, &words)[0] {
+            Event::Command { parameters, .. } => {
+                assert_eq!(parameters.len(), 64);
+                assert_eq!(parameters.as_slice().len(), MAX_WORDS);
+                assert!(parameters.to_string().ends_with("0f .. (64 words)"));
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+    }
+
+    #[test]
+    fn counts_pixels_in_the_current_format() {
+        let mut words = std::vec![(false, 0x3a), (true, 0x55), (false, 0x2c)];
+        words.extend((0..10).map(|_| (true, 0)));
+        words.extend([(false, 0x3a), (true, 0x66), (false, 0x3c)].iter().cloned());
+        words.extend((0..9).map(|_| (true, 0)));
+        words.extend([(false, 0x3a), (true, 0x11), (false, 0x2c)].iter().cloned());
+        words.extend((0..4).map(|_| (true, 0)));
+        assert_eq!(
+            text(BusWidth::Bits8, &words),
+            [
+                "COLMOD 55",
+                "RAMWR 5 px",
+                "COLMOD 66",
+                "RAMWRC 3 px",
+                "COLMOD 11",
+                "RAMWR 8 px"
+            ]
+        );
+
+        let mut decoder = Decoder::new(BusWidth::Bits16);
+        assert_eq!(decoder.pixel_format(), PixelFormat::Rgb666);
+        decoder.push(false, 0x2c);
+        for _ in 0..5 {
+            decoder.push(true, 0);
+        }
+        match decoder.finish() {
+            Some(Event::MemoryWrite { words, pixels, .. }) => {
+                assert_eq!(words, 5);
+                // the pixels of an incomplete group are not written
+                assert_eq!(pixels, 2);
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+
+        // one pixel in two words, kept when the 18-bit format is sent again
+        decoder.set_pixel_format(PixelFormat::Rgb666TwoTransfer);
+        for (dc, word) in [(false, 0x3a), (true, 0x66), (false, 0x2c)].iter() {
+            decoder.push(*dc, *word);
+        }
+        for _ in 0..5 {
+            decoder.push(true, 0);
+        }
+        assert_eq!(decoder.pixel_format(), PixelFormat::Rgb666TwoTransfer);
+        match decoder.finish() {
+            Some(Event::MemoryWrite { words, pixels, .. }) => {
+                assert_eq!(words, 5);
+                assert_eq!(pixels, 2);
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+    }
+
+    #[test]
+    fn memory_read_skips_the_dummy_word() {
+        let mut decoder = Decoder::new(BusWidth::Bits8);
+        decoder.push(false, 0x2e);
+        for _ in 0..7 {
+            decoder.push_read(0);
+        }
+        assert_eq!(
+            decoder.finish(),
+            Some(Event::MemoryRead {
+                command: Command::MemoryRead,
+                words: 6,
+                pixels: 2,
+            })
+        );
+    }
+}
@@ -0,0 +1,344 @@
+//! Non-blocking frame memory writes.
+//!
+//! The driver programs the address window and `MemoryWrite` over the blocking interface and
+//! hands a buffer of encoded pixels to the interface's [DmaWrite](DmaWrite), which writes it in
+//! the background, e.g. with DMA. The returned [Transfer](Transfer) is polled with `nb` and
+//! gives the buffer back once it has been written:
+//!
+//! ```ignore
+//! static mut FRAME: [u8; 320 * 480 * 2] = [0; 320 * 480 * 2];
+//!
+//! let buffer = unsafe { &mut FRAME[..] };
+//! let mut writer = BufferWriter::new(buffer);
+//! writer.write_pixels(&PixelFormat::Rgb565, &mut pixels)?;
+//!
+//! let mut transfer = lcd.write_area_dma(0, 0, 320, 480, writer.release())?;
+//! let buffer = loop {
+//!     match transfer.poll() {
+//!         Ok(buffer) => break buffer,
+//!         Err(nb::Error::WouldBlock) => service_sensors(),
+//!         Err(nb::Error::Other(error)) => return Err(error),
+//!     }
+//! };
+//! ```
+
+use crate::color::{PixelFormat, PixelWriter};
+use crate::{dcs, ILI9486};
+use display_interface::v2::*;
+use display_interface::DisplayError;
+
+pub use crate::buffer::BufferWriter;
+
+/// Writes data words to the controller in the background, implemented by interfaces that can
+/// hand a buffer to DMA.
+///
+/// The interface selects the controller with D/CX high (data) when a transfer starts and
+/// deselects it once the transfer is done. It keeps the buffer while the transfer is running,
+/// so the buffer can't be changed before it is returned by [poll](DmaWrite::poll).
+pub trait DmaWrite<W> {
+    /// Starts writing `buffer` as data.
+    fn start(&mut self, buffer: &'static mut [W]) -> Result<(), DisplayError>;
+
+    /// Returns the buffer once the transfer is done and `WouldBlock` while it is running.
+    ///
+    /// Only called after a transfer was started.
+    fn poll(&mut self) -> nb::Result<&'static mut [W], DisplayError>;
+}
+
+/// A running transfer of [write_area_dma](ILI9486::write_area_dma), the driver can't be used
+/// until it is done.
+///
+/// Dropping the handle doesn't stop the transfer. Until it is polled to completion, with
+/// [poll_dma](ILI9486::poll_dma) once the handle is gone, the driver returns `BusWriteError`
+/// instead of writing to the interface.
+#[must_use = "the transfer has to be polled to completion before the driver is used again"]
+pub struct Transfer<'a, RW, T>
+where
+    RW: ReadWriteInterface<T> + PixelWriter<T> + DmaWrite<T>,
+{
+    driver: &'a mut ILI9486<RW, T>,
+}
+
+impl<'a, RW, T> Transfer<'a, RW, T>
+where
+    RW: ReadWriteInterface<T> + PixelWriter<T> + DmaWrite<T>,
+{
+    /// Returns the buffer once it has been written and `WouldBlock` while it is written.
+    pub fn poll(&mut self) -> nb::Result<&'static mut [T], DisplayError> {
+        self.driver.poll_dma()
+    }
+
+    /// Blocks until the buffer has been written and returns it.
+    pub fn wait(mut self) -> Result<&'static mut [T], DisplayError> {
+        nb::block!(self.poll())
+    }
+}
+
+/// Number of words `pixels` pixels take in `pixel_format`.
+///
+/// The copy of a lone last pixel of a format that packs pixels would land after the area, so
+/// those formats need an even number of pixels.
+fn area_words<T>(pixel_format: &PixelFormat, pixels: usize) -> Result<usize, DisplayError>
+where
+    T: Copy + Default,
+    for<'b> BufferWriter<'b, T>: PixelWriter<T>,
+{
+    let black = (0, 0, 0);
+    let mut words = [T::default(); 6];
+    let mut writer = BufferWriter::new(&mut words);
+    if pixels % 2 == 1 && writer.packs_pixels(pixel_format) {
+        return Err(DisplayError::InvalidFormatError);
+    }
+    writer.write_pixel_data(pixel_format, &black, Some(&black))?;
+    let pair = writer.len();
+    let mut writer = BufferWriter::new(&mut words);
+    writer.write_pixel_data(pixel_format, &black, None)?;
+    let lone = writer.len();
+    Ok(pixels / 2 * pair + pixels % 2 * lone)
+}
+
+impl<RW, T> ILI9486<RW, T>
+where
+    RW: ReadWriteInterface<T> + PixelWriter<T> + DmaWrite<T>,
+    T: From<u8> + Copy + Default,
+    for<'b> BufferWriter<'b, T>: PixelWriter<T>,
+{
+    /// Programs the area and `MemoryWrite` and starts writing `buffer` in the background.
+    ///
+    /// `buffer` holds the pixels of the area, row by row, encoded for the current
+    /// [color mode](ILI9486::color_mode), see [BufferWriter](crate::buffer::BufferWriter).
+    ///
+    /// Returns `InvalidFormatError` if the area is empty, has an odd number of pixels in a format
+    /// that [packs](crate::color::PixelWriter::packs_pixels) two pixels, or `buffer` doesn't hold
+    /// exactly its pixels, before anything is sent, and `BusWriteError` while another transfer
+    /// is running.
+    #[must_use = "the transfer has to be polled to completion before the driver is used again"]
+    pub fn write_area_dma(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        buffer: &'static mut [T],
+    ) -> Result<Transfer<'_, RW, T>, DisplayError> {
+        let pixels = width as usize * height as usize;
+        if pixels == 0 || buffer.len() != area_words::<T>(&self.color_mode, pixels)? {
+            return Err(DisplayError::InvalidFormatError);
+        }
+
+        self.start_area(x, y, width, height, &dcs::MemoryWrite)?;
+        self.rw_interface.start(buffer)?;
+        self.dma_busy = true;
+
+        Ok(Transfer { driver: self })
+    }
+}
+
+impl<RW, T> ILI9486<RW, T>
+where
+    RW: ReadWriteInterface<T> + PixelWriter<T> + DmaWrite<T>,
+{
+    /// Polls the running transfer, like [Transfer::poll](Transfer::poll), e.g. after its handle
+    /// was dropped. The driver can be used again once the buffer is returned.
+    ///
+    /// Returns `InvalidFormatError` if no transfer is running.
+    pub fn poll_dma(&mut self) -> nb::Result<&'static mut [T], DisplayError> {
+        if !self.dma_busy {
+            return Err(nb::Error::Other(DisplayError::InvalidFormatError));
+        }
+        let result = self.rw_interface.poll();
+        // a failed transfer is over as well
+        if !matches!(result, Err(nb::Error::WouldBlock)) {
+            self.dma_busy = false;
+        }
+        result
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::emulator::{Emulator, NoDelay, NoPin};
+    use crate::io::shim::OutputOnlyIoPin;
+    use crate::Command;
+    use std::boxed::Box;
+    use std::vec;
+
+    /// Writes the buffer to the emulator after `busy` polls.
+    struct Dma {
+        emulator: Emulator,
+        buffer: Option<&'static mut [u8]>,
+        busy: usize,
+    }
+
+    impl WriteInterface<u8> for Dma {
+        fn write_stream<'a>(
+            &mut self,
+            mode: WriteMode,
+            func: &mut dyn FnMut() -> Option<&'a u8>,
+        ) -> Result<(), DisplayError> {
+            assert!(self.buffer.is_none(), "written during a transfer");
+            self.emulator.write_stream(mode, func)
+        }
+    }
+
+    impl ReadInterface<u8> for Dma {
+        fn read_stream(&mut self, f: &mut dyn FnMut(u8) -> bool) -> Result<(), DisplayError> {
+            self.emulator.read_stream(f)
+        }
+    }
+
+    impl DmaWrite<u8> for Dma {
+        fn start(&mut self, buffer: &'static mut [u8]) -> Result<(), DisplayError> {
+            self.buffer = Some(buffer);
+            self.busy = 3;
+            Ok(())
+        }
+
+        fn poll(&mut self) -> nb::Result<&'static mut [u8], DisplayError> {
+            if self.busy > 0 {
+                self.busy -= 1;
+                return Err(nb::Error::WouldBlock);
+            }
+            let buffer = self.buffer.take().unwrap();
+            self.emulator.write(WriteMode::Data, buffer)?;
+            Ok(buffer)
+        }
+    }
+
+    fn buffer(len: usize) -> &'static mut [u8] {
+        Box::leak(vec![0u8; len].into_boxed_slice())
+    }
+
+    #[test]
+    fn writes_in_the_background() {
+        let dma = Dma {
+            emulator: Emulator::new(),
+            buffer: None,
+            busy: 0,
+        };
+        let mut lcd: ILI9486<_, u8> = ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb565,
+            dma,
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap();
+
+        let pixels = [(31, 0, 0), (0, 63, 0), (0, 0, 31)];
+        let buffer = buffer(6);
+        let mut writer = BufferWriter::new(buffer);
+        writer
+            .write_pixels(&PixelFormat::Rgb565, &mut pixels.iter().cloned())
+            .unwrap();
+        let buffer = writer.release();
+
+        let mut transfer = lcd.write_area_dma(10, 20, 3, 1, buffer).unwrap();
+        let mut busy = 0;
+        let buffer = loop {
+            match transfer.poll() {
+                Ok(buffer) => break buffer,
+                Err(nb::Error::WouldBlock) => busy += 1,
+                Err(nb::Error::Other(error)) => panic!("{:?}", error),
+            }
+        };
+        assert_eq!(busy, 3);
+        assert_eq!(buffer.len(), 6);
+
+        let emulator = &lcd.writer().emulator;
+        assert_eq!(emulator.pixel(10, 20), (63, 0, 0));
+        assert_eq!(emulator.pixel(11, 20), (0, 63, 0));
+        assert_eq!(emulator.pixel(12, 20), (0, 0, 63));
+    }
+
+    #[test]
+    fn busy_until_polled() {
+        let dma = Dma {
+            emulator: Emulator::new(),
+            buffer: None,
+            busy: 0,
+        };
+        let mut lcd: ILI9486<_, u8> = ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb565,
+            dma,
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap();
+        assert!(matches!(
+            lcd.poll_dma(),
+            Err(nb::Error::Other(DisplayError::InvalidFormatError))
+        ));
+
+        // the handle is dropped while the transfer is running
+        drop(lcd.write_area_dma(0, 0, 2, 1, buffer(4)).unwrap());
+        assert!(matches!(
+            lcd._draw_pixel(5, 5, 31, 0, 0),
+            Err(DisplayError::BusWriteError)
+        ));
+        assert!(matches!(
+            lcd.write_command(Command::DisplayOn, &[]),
+            Err(DisplayError::BusWriteError)
+        ));
+        assert!(matches!(
+            lcd.write_area_dma(0, 0, 2, 1, buffer(4)),
+            Err(DisplayError::BusWriteError)
+        ));
+
+        let mut polls = 0;
+        let buffer = loop {
+            match lcd.poll_dma() {
+                Ok(buffer) => break buffer,
+                Err(nb::Error::WouldBlock) => polls += 1,
+                Err(nb::Error::Other(error)) => panic!("{:?}", error),
+            }
+        };
+        assert_eq!((polls, buffer.len()), (3, 4));
+        lcd._draw_pixel(5, 5, 31, 0, 0).unwrap();
+        assert_eq!(lcd.writer().emulator.pixel(5, 5), (63, 0, 0));
+    }
+
+    #[test]
+    fn checks_the_area() {
+        let dma = Dma {
+            emulator: Emulator::new(),
+            buffer: None,
+            busy: 0,
+        };
+        let mut lcd: ILI9486<_, u8> = ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb666,
+            dma,
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap();
+
+        for (width, height, len) in [(0, 1, 0), (1, 0, 0), (2, 2, 11), (2, 2, 13)].iter() {
+            assert!(matches!(
+                lcd.write_area_dma(0, 0, *width, *height, buffer(*len)),
+                Err(DisplayError::InvalidFormatError)
+            ));
+        }
+        // 3 words per pixel
+        assert!(lcd.write_area_dma(0, 0, 2, 2, buffer(12)).is_ok());
+
+        // two pixels per word
+        let dma = Dma {
+            emulator: Emulator::new(),
+            buffer: None,
+            busy: 0,
+        };
+        let mut lcd: ILI9486<_, u8> = ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb111,
+            dma,
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap();
+        assert!(matches!(
+            lcd.write_area_dma(0, 0, 3, 1, buffer(2)),
+            Err(DisplayError::InvalidFormatError)
+        ));
+        assert!(lcd.write_area_dma(0, 0, 2, 2, buffer(2)).is_ok());
+    }
+}
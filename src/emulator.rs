@@ -0,0 +1,845 @@
+use crate::color::PixelFormat;
+use crate::decode::BusWidth;
+use crate::gpio::{U18, U9};
+use crate::Command;
+use core::convert::{Infallible, TryFrom};
+use display_interface::v2::*;
+use display_interface::DisplayError;
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::OutputPin;
+use std::collections::VecDeque;
+use std::vec;
+use std::vec::Vec;
+
+/// Width of the frame memory in pixels.
+pub const WIDTH: usize = 320;
+
+/// Height of the frame memory in pixels.
+pub const HEIGHT: usize = 480;
+
+/// Values returned by `ReadDisplayId`, `ReadID1`, `ReadID2` and `ReadID3`.
+pub const DISPLAY_ID: [u8; 3] = [0x54, 0x80, 0x66];
+
+/// Value returned by `ReadID4`.
+pub const ID4: [u8; 3] = [0x00, 0x94, 0x86];
+
+const MADCTL_MY: u8 = 1 << 7;
+const MADCTL_MX: u8 = 1 << 6;
+const MADCTL_MV: u8 = 1 << 5;
+const MADCTL_BGR: u8 = 1 << 3;
+
+/// An RGB666 pixel, each component is 6 bits.
+pub type Rgb666 = (u8, u8, u8);
+
+enum ReadSource {
+    Registers(VecDeque<u32>),
+    Memory { dummy: bool, words: VecDeque<u32> },
+}
+
+/// A host-side model of the ILI9486 controller.
+///
+/// It implements the read and write interfaces for 8, 9, 16 and 18-bit buses, so the driver can run
+/// against it off-target:
+///
+/// ```ignore
+/// let mut lcd: ILI9486<Emulator, u8> = ILI9486::new(
+///     &mut NoDelay,
+///     PixelFormat::Rgb565,
+///     Emulator::new(),
+///     OutputOnlyIoPin::new(NoPin),
+/// )?;
+/// lcd.clear_screen()?;
+/// assert_eq!(lcd.writer().pixel(0, 0), (0, 0, 0));
+/// ```
+///
+/// The frame memory is kept as RGB666, pixels written in RGB565 are expanded to 6 bits per
+/// component the same way the controller does.
+pub struct Emulator {
+    gram: Vec<Rgb666>,
+    column: (u16, u16),
+    page: (u16, u16),
+    x: u16,
+    y: u16,
+    madctl: u8,
+    colmod: u8,
+    two_transfer: bool,
+    sleep_out: bool,
+    display_on: bool,
+    normal_mode: bool,
+    partial_mode: bool,
+    idle_mode: bool,
+    inverted: bool,
+    tearing_effect: bool,
+    tear_scan_line: u16,
+    scroll_area: (u16, u16, u16),
+    scroll_start: u16,
+    command: Option<u8>,
+    parameters: Vec<u8>,
+    pixel_words: Vec<u32>,
+    read: ReadSource,
+}
+
+impl Default for Emulator {
+    fn default() -> Emulator {
+        Emulator::new()
+    }
+}
+
+impl Emulator {
+    /// Creates an emulator in the state after a hardware reset, with black frame memory.
+    pub fn new() -> Emulator {
+        let mut emulator = Emulator {
+            gram: vec![(0, 0, 0); WIDTH * HEIGHT],
+            column: (0, 0),
+            page: (0, 0),
+            x: 0,
+            y: 0,
+            madctl: 0,
+            colmod: 0,
+            two_transfer: false,
+            sleep_out: false,
+            display_on: false,
+            normal_mode: false,
+            partial_mode: false,
+            idle_mode: false,
+            inverted: false,
+            tearing_effect: false,
+            tear_scan_line: 0,
+            scroll_area: (0, 0, 0),
+            scroll_start: 0,
+            command: None,
+            parameters: Vec::new(),
+            pixel_words: Vec::new(),
+            read: ReadSource::Registers(VecDeque::new()),
+        };
+        emulator.reset();
+        emulator
+    }
+
+    /// Resets the registers, the frame memory keeps its contents.
+    pub fn reset(&mut self) {
+        self.column = (0, WIDTH as u16 - 1);
+        self.page = (0, HEIGHT as u16 - 1);
+        self.x = 0;
+        self.y = 0;
+        self.madctl = 0;
+        self.colmod = 0x66;
+        self.sleep_out = false;
+        self.display_on = false;
+        self.normal_mode = true;
+        self.partial_mode = false;
+        self.idle_mode = false;
+        self.inverted = false;
+        self.tearing_effect = false;
+        self.tear_scan_line = 0;
+        self.scroll_area = (0, HEIGHT as u16, 0);
+        self.scroll_start = 0;
+        self.command = None;
+        self.parameters.clear();
+        self.pixel_words.clear();
+        self.read = ReadSource::Registers(VecDeque::new());
+    }
+
+    /// The frame memory, row by row.
+    pub fn gram(&self) -> &[Rgb666] {
+        &self.gram
+    }
+
+    /// The pixel at `(x, y)` in the frame memory.
+    pub fn pixel(&self, x: usize, y: usize) -> Rgb666 {
+        self.gram[y * WIDTH + x]
+    }
+
+    /// The pixel shown on the panel at `(x, y)`, after vertical scrolling, BGR order, inversion
+    /// and the display on/off state are applied.
+    pub fn displayed_pixel(&self, x: usize, y: usize) -> Rgb666 {
+        if !self.display_on || !self.sleep_out {
+            return (0, 0, 0);
+        }
+
+        let (top, scroll, _) = self.scroll_area;
+        let (top, scroll) = (top as usize, scroll as usize);
+        let line = if y >= top && y < top + scroll && scroll > 0 {
+            let start = (self.scroll_start as usize).max(top) - top;
+            top + (y - top + start) % scroll
+        } else {
+            y
+        };
+
+        let (mut r, g, mut b) = self.pixel(x, line.min(HEIGHT - 1));
+        if self.madctl & MADCTL_BGR != 0 {
+            core::mem::swap(&mut r, &mut b);
+        }
+        if self.inverted {
+            (0x3f - r, 0x3f - g, 0x3f - b)
+        } else {
+            (r, g, b)
+        }
+    }
+
+    pub fn madctl(&self) -> u8 {
+        self.madctl
+    }
+
+    /// The pixel format set with `InterfacePixelFormat`, `None` if it is not supported.
+    pub fn pixel_format(&self) -> Option<PixelFormat> {
+        match PixelFormat::from_dbi_bits(self.colmod) {
+            Some(PixelFormat::Rgb666) if self.two_transfer => Some(PixelFormat::Rgb666TwoTransfer),
+            pixel_format => pixel_format,
+        }
+    }
+
+    /// Selects how 18-bit pixels are sent on a 16-bit bus, one pixel in two transfers instead of
+    /// two pixels in three, like the board configuration of a panel. This is kept across resets.
+    pub fn set_two_transfer_mode(&mut self, enabled: bool) {
+        self.two_transfer = enabled;
+    }
+
+    pub fn is_sleeping(&self) -> bool {
+        !self.sleep_out
+    }
+
+    pub fn is_display_on(&self) -> bool {
+        self.display_on
+    }
+
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// The column window, inclusive.
+    pub fn column_window(&self) -> (u16, u16) {
+        self.column
+    }
+
+    /// The page window, inclusive.
+    pub fn page_window(&self) -> (u16, u16) {
+        self.page
+    }
+
+    /// Top fixed area, scroll area and bottom fixed area, in lines.
+    pub fn scroll_area(&self) -> (u16, u16, u16) {
+        self.scroll_area
+    }
+
+    pub fn scroll_start(&self) -> u16 {
+        self.scroll_start
+    }
+
+    fn power_mode(&self) -> u8 {
+        ((self.sleep_out as u8) << 7)
+            | ((self.idle_mode as u8) << 6)
+            | ((self.partial_mode as u8) << 5)
+            | ((self.sleep_out as u8) << 4)
+            | ((self.normal_mode as u8) << 3)
+            | ((self.display_on as u8) << 2)
+    }
+
+    fn display_status(&self) -> [u8; 4] {
+        [
+            ((self.sleep_out as u8) << 7) | ((self.madctl >> 1) & 0b0111_1110),
+            ((self.colmod & 0b111) << 4)
+                | ((self.idle_mode as u8) << 3)
+                | ((self.partial_mode as u8) << 2)
+                | ((self.sleep_out as u8) << 1)
+                | self.normal_mode as u8,
+            ((self.inverted as u8) << 5)
+                | ((self.display_on as u8) << 2)
+                | ((self.tearing_effect as u8) << 1),
+            0,
+        ]
+    }
+
+    fn start_read(&mut self, data: &[u8]) {
+        // the first word is a dummy read
+        let mut words: VecDeque<u32> = data.iter().map(|b| *b as u32).collect();
+        words.push_front(0);
+        self.read = ReadSource::Registers(words);
+    }
+
+    fn command(&mut self, opcode: u8) {
+        self.command = Some(opcode);
+        self.parameters.clear();
+        self.pixel_words.clear();
+        self.read = ReadSource::Registers(VecDeque::new());
+
+        let command = match Command::try_from(opcode) {
+            Ok(command) => command,
+            Err(_) => return,
+        };
+
+        match command {
+            Command::SoftReset => self.reset(),
+            Command::SleepIn => self.sleep_out = false,
+            Command::SleepOut => self.sleep_out = true,
+            Command::PartialModeOn => {
+                self.partial_mode = true;
+                self.normal_mode = false;
+            }
+            Command::NormalDisplayMode => {
+                self.partial_mode = false;
+                self.normal_mode = true;
+            }
+            Command::DisplayInversionOff => self.inverted = false,
+            Command::DisplayInversionOn => self.inverted = true,
+            Command::DisplayOff => self.display_on = false,
+            Command::DisplayOn => self.display_on = true,
+            Command::TearingEffectLineOff => self.tearing_effect = false,
+            Command::IdleModeOff => self.idle_mode = false,
+            Command::IdleModeOn => self.idle_mode = true,
+            Command::MemoryWrite | Command::MemoryRead => {
+                self.x = self.column.0;
+                self.y = self.page.0;
+            }
+            _ => {}
+        }
+
+        match command {
+            Command::MemoryRead | Command::MemoryReadContinue => {
+                self.read = ReadSource::Memory {
+                    dummy: true,
+                    words: VecDeque::new(),
+                }
+            }
+            Command::ReadDisplayId => self.start_read(&DISPLAY_ID),
+            Command::ReadDisplayStatus => {
+                let status = self.display_status();
+                self.start_read(&status)
+            }
+            Command::ReadDisplayPowerMode => {
+                let power_mode = self.power_mode();
+                self.start_read(&[power_mode])
+            }
+            Command::ReadDisplayMADCTL => {
+                let madctl = self.madctl & 0b1111_1100;
+                self.start_read(&[madctl])
+            }
+            Command::ReadDisplayPixelFormat => {
+                let colmod = self.colmod;
+                self.start_read(&[colmod])
+            }
+            Command::ReadDisplayImageMode => {
+                let image_mode = (self.inverted as u8) << 5;
+                self.start_read(&[image_mode])
+            }
+            Command::ReadDisplaySignalMode => {
+                let signal_mode = (self.tearing_effect as u8) << 7;
+                self.start_read(&[signal_mode])
+            }
+            Command::ReadTearScanLine => {
+                let line = self.tear_scan_line;
+                self.start_read(&[(line >> 8) as u8, line as u8])
+            }
+            Command::ReadID1 => self.start_read(&DISPLAY_ID[0..1]),
+            Command::ReadID2 => self.start_read(&DISPLAY_ID[1..2]),
+            Command::ReadID3 => self.start_read(&DISPLAY_ID[2..3]),
+            Command::ReadID4 => self.start_read(&ID4),
+            Command::ReadErrors
+            | Command::ReadDisplaySelfDiagResult
+            | Command::ReadDisplayBrigthnessValue
+            | Command::ReadCTRLDisplayValue
+            | Command::ReadCABrigthnessControl
+            | Command::ReadCABCMinBrigthness
+            | Command::ReadFirstChecksum
+            | Command::ReadContinueChecksum => self.start_read(&[0]),
+            Command::NVMemoryStatusRead => self.start_read(&[0, 0]),
+            _ => {}
+        }
+    }
+
+    fn parameter(&mut self, command: Command, byte: u8) {
+        self.parameters.push(byte);
+        let p = &self.parameters;
+        let u16_at = |i: usize| ((p[i] as u16) << 8) | p[i + 1] as u16;
+
+        match (command, p.len()) {
+            (Command::ColumnAddressSet, 4) => self.column = (u16_at(0), u16_at(2)),
+            (Command::PageAddressSet, 4) => self.page = (u16_at(0), u16_at(2)),
+            (Command::MemoryAccessControl, 1) => self.madctl = p[0],
+            (Command::InterfacePixelFormat, 1) => self.colmod = p[0],
+            (Command::VerticalScrollingDefinition, 6) => {
+                self.scroll_area = (u16_at(0), u16_at(2), u16_at(4))
+            }
+            (Command::VerticalScrollingStartAddress, 2) => self.scroll_start = u16_at(0),
+            (Command::TearingEffectLineOn, 1) => self.tearing_effect = true,
+            (Command::WriteTearScanLine, 2) => self.tear_scan_line = u16_at(0),
+            _ => {}
+        }
+    }
+
+    /// Maps the current address to a frame memory index, following MADCTL.
+    fn address(&self) -> Option<usize> {
+        let (columns, pages) = if self.madctl & MADCTL_MV != 0 {
+            (HEIGHT, WIDTH)
+        } else {
+            (WIDTH, HEIGHT)
+        };
+        let (column, page) = (self.x as usize, self.y as usize);
+        if column >= columns || page >= pages {
+            return None;
+        }
+
+        let column = if self.madctl & MADCTL_MX != 0 {
+            columns - 1 - column
+        } else {
+            column
+        };
+        let page = if self.madctl & MADCTL_MY != 0 {
+            pages - 1 - page
+        } else {
+            page
+        };
+        let (x, y) = if self.madctl & MADCTL_MV != 0 {
+            (page, column)
+        } else {
+            (column, page)
+        };
+        Some(y * WIDTH + x)
+    }
+
+    fn advance(&mut self) {
+        if self.x >= self.column.1 {
+            self.x = self.column.0;
+            if self.y >= self.page.1 {
+                self.y = self.page.0;
+            } else {
+                self.y += 1;
+            }
+        } else {
+            self.x += 1;
+        }
+    }
+
+    fn write_pixel(&mut self, pixel: Rgb666) {
+        if let Some(index) = self.address() {
+            self.gram[index] = pixel;
+        }
+        self.advance();
+    }
+
+    fn read_pixel(&mut self) -> Rgb666 {
+        let pixel = self.address().map(|index| self.gram[index]);
+        self.advance();
+        pixel.unwrap_or((0, 0, 0))
+    }
+
+    /// Number of words that make up a group of pixels on `width`.
+    fn pixel_group(&self, width: BusWidth) -> usize {
+        match (width, self.pixel_format()) {
+            (BusWidth::Bits8, Some(PixelFormat::Rgb565)) => 2,
+            (BusWidth::Bits8, Some(PixelFormat::Rgb111)) => 1,
+            (BusWidth::Bits8, _) => 3,
+            (BusWidth::Bits9, _) => 2,
+            (BusWidth::Bits16, Some(PixelFormat::Rgb565)) => 1,
+            (BusWidth::Bits16, Some(PixelFormat::Rgb666TwoTransfer)) => 2,
+            (BusWidth::Bits16, _) => 3,
+            (BusWidth::Bits18, _) => 1,
+        }
+    }
+
+    /// Collects pixel data, pixels are only written once the transfers of their group are
+    /// complete.
+    fn pixel_data(&mut self, width: BusWidth, word: u32) {
+        self.pixel_words.push(word);
+        if self.pixel_words.len() < self.pixel_group(width) {
+            return;
+        }
+
+        let w = core::mem::take(&mut self.pixel_words);
+        match (width, self.pixel_format()) {
+            (BusWidth::Bits8, Some(PixelFormat::Rgb565)) => {
+                self.write_pixel(expand_rgb565(((w[0] as u16) << 8) | (w[1] & 0xff) as u16))
+            }
+            (BusWidth::Bits8, Some(PixelFormat::Rgb111)) => {
+                self.write_pixel(expand_rgb111(w[0] >> 3));
+                self.write_pixel(expand_rgb111(w[0]));
+            }
+            (BusWidth::Bits8, _) => self.write_pixel((
+                ((w[0] >> 2) & 0x3f) as u8,
+                ((w[1] >> 2) & 0x3f) as u8,
+                ((w[2] >> 2) & 0x3f) as u8,
+            )),
+            (BusWidth::Bits9, _) => {
+                let rgb = ((w[0] & 0x1ff) << 9) | (w[1] & 0x1ff);
+                self.write_pixel(split_rgb666(rgb))
+            }
+            (BusWidth::Bits16, Some(PixelFormat::Rgb565)) => {
+                self.write_pixel(expand_rgb565(w[0] as u16))
+            }
+            (BusWidth::Bits16, Some(PixelFormat::Rgb666TwoTransfer)) => {
+                self.write_pixel(split_rgb666(((w[0] & 0b11) << 16) | (w[1] & 0xffff)))
+            }
+            (BusWidth::Bits16, _) => {
+                // two pixels in three words
+                let upper = |word: u32| ((word >> 10) & 0x3f) as u8;
+                let lower = |word: u32| ((word >> 2) & 0x3f) as u8;
+                self.write_pixel((upper(w[0]), lower(w[0]), upper(w[1])));
+                self.write_pixel((lower(w[1]), upper(w[2]), lower(w[2])));
+            }
+            (BusWidth::Bits18, Some(PixelFormat::Rgb565)) => {
+                // the 5-bit components are in the upper bits of their lanes
+                let pixel = split_rgb666(w[0]);
+                self.write_pixel(expand_rgb565(
+                    (((pixel.0 >> 1) as u16) << 11)
+                        | ((pixel.1 as u16) << 5)
+                        | (pixel.2 >> 1) as u16,
+                ))
+            }
+            (BusWidth::Bits18, _) => self.write_pixel(split_rgb666(w[0])),
+        }
+        self.pixel_words = w;
+        self.pixel_words.clear();
+    }
+
+    pub(crate) fn write_word(&mut self, width: BusWidth, mode: &WriteMode, word: u32) {
+        if let WriteMode::Command = mode {
+            self.command(word as u8);
+            return;
+        }
+
+        let command = match self.command.map(Command::try_from) {
+            Some(Ok(command)) => command,
+            _ => return,
+        };
+
+        match command {
+            Command::MemoryWrite | Command::MemoryWriteContinue => self.pixel_data(width, word),
+            _ => self.parameter(command, word as u8),
+        }
+    }
+
+    pub(crate) fn read_word(&mut self, width: BusWidth) -> u32 {
+        let needs_pixel = match &self.read {
+            ReadSource::Registers(_) => false,
+            ReadSource::Memory { dummy, words } => !*dummy && words.is_empty(),
+        };
+        if needs_pixel {
+            // pixels are always read as RGB666
+            let pixel = self.read_pixel();
+            let words: Vec<u32> = match width {
+                BusWidth::Bits8 => vec![
+                    (pixel.0 as u32) << 2,
+                    (pixel.1 as u32) << 2,
+                    (pixel.2 as u32) << 2,
+                ],
+                BusWidth::Bits9 => {
+                    let rgb = join_rgb666(pixel);
+                    vec![rgb >> 9, rgb & 0x1ff]
+                }
+                BusWidth::Bits16 => {
+                    // two pixels are packed into three words
+                    let next = self.read_pixel();
+                    vec![
+                        ((pixel.0 as u32) << 10) | ((pixel.1 as u32) << 2),
+                        ((pixel.2 as u32) << 10) | ((next.0 as u32) << 2),
+                        ((next.1 as u32) << 10) | ((next.2 as u32) << 2),
+                    ]
+                }
+                BusWidth::Bits18 => vec![join_rgb666(pixel)],
+            };
+            if let ReadSource::Memory { words: queue, .. } = &mut self.read {
+                queue.extend(words);
+            }
+        }
+
+        match &mut self.read {
+            ReadSource::Registers(words) => words.pop_front().unwrap_or(0),
+            ReadSource::Memory { dummy, words } => {
+                if *dummy {
+                    *dummy = false;
+                    0
+                } else {
+                    words.pop_front().unwrap_or(0)
+                }
+            }
+        }
+    }
+}
+
+/// Expands RGB565 to RGB666 by repeating the most significant bit of red and blue.
+fn expand_rgb565(word: u16) -> Rgb666 {
+    let r = ((word >> 11) & 0x1f) as u8;
+    let g = ((word >> 5) & 0x3f) as u8;
+    let b = (word & 0x1f) as u8;
+    ((r << 1) | (r >> 4), g, (b << 1) | (b >> 4))
+}
+
+/// Expands the lower three bits, red, green and blue, to RGB666.
+fn expand_rgb111(bits: u32) -> Rgb666 {
+    let component = |bit: u32| if bits & bit != 0 { 0x3f } else { 0 };
+    (component(0b100), component(0b010), component(0b001))
+}
+
+fn split_rgb666(word: u32) -> Rgb666 {
+    (
+        ((word >> 12) & 0x3f) as u8,
+        ((word >> 6) & 0x3f) as u8,
+        (word & 0x3f) as u8,
+    )
+}
+
+fn join_rgb666(pixel: Rgb666) -> u32 {
+    ((pixel.0 as u32) << 12) | ((pixel.1 as u32) << 6) | pixel.2 as u32
+}
+
+macro_rules! emulator_interface {
+    ($word:ty, $width:expr) => {
+        impl WriteInterface<$word> for Emulator {
+            fn write_stream<'a>(
+                &mut self,
+                mode: WriteMode,
+                func: &mut dyn FnMut() -> Option<&'a $word>,
+            ) -> Result<(), DisplayError> {
+                while let Some(word) = func() {
+                    self.write_word($width, &mode, u32::from(*word));
+                }
+                Ok(())
+            }
+        }
+
+        impl ReadInterface<$word> for Emulator {
+            fn read_stream(
+                &mut self,
+                f: &mut dyn FnMut($word) -> bool,
+            ) -> Result<(), DisplayError> {
+                loop {
+                    let word = <$word>::try_from(self.read_word($width)).unwrap_or_default();
+                    if !f(word) {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+emulator_interface!(u8, BusWidth::Bits8);
+emulator_interface!(U9, BusWidth::Bits9);
+emulator_interface!(u16, BusWidth::Bits16);
+emulator_interface!(U18, BusWidth::Bits18);
+
+/// A delay that returns immediately, for use with the [Emulator](Emulator).
+pub struct NoDelay;
+
+impl DelayUs<u32> for NoDelay {
+    fn delay_us(&mut self, _us: u32) {}
+}
+
+/// A pin that ignores writes, for the reset pin when running against the [Emulator](Emulator).
+pub struct NoPin;
+
+impl OutputPin for NoPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send<T: Copy>(emulator: &mut Emulator, command: T, data: &[T])
+    where
+        Emulator: WriteInterface<T>,
+    {
+        emulator.write(WriteMode::Command, &[command]).unwrap();
+        emulator.write(WriteMode::Data, data).unwrap();
+    }
+
+    fn read<T: Copy + Default>(emulator: &mut Emulator, command: T, len: usize) -> Vec<T>
+    where
+        Emulator: ReadWriteInterface<T>,
+    {
+        let mut data = vec![T::default(); len];
+        emulator.write(WriteMode::Command, &[command]).unwrap();
+        emulator.read(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn reset_state() {
+        let emulator = Emulator::new();
+        assert_eq!(emulator.column_window(), (0, 319));
+        assert_eq!(emulator.page_window(), (0, 479));
+        assert_eq!(emulator.pixel_format(), Some(PixelFormat::Rgb666));
+        assert_eq!(emulator.madctl(), 0);
+        assert!(emulator.is_sleeping());
+        assert!(!emulator.is_display_on());
+        assert_eq!(emulator.displayed_pixel(0, 0), (0, 0, 0));
+    }
+
+    #[test]
+    fn address_window_and_write_pointer() {
+        let mut emulator = Emulator::new();
+        send(&mut emulator, 0x3au8, &[0x55]);
+        send(&mut emulator, 0x2au8, &[0, 10, 0, 11]);
+        send(&mut emulator, 0x2bu8, &[0x01, 0x00, 0x01, 0x01]);
+        assert_eq!(emulator.column_window(), (10, 11));
+        assert_eq!(emulator.page_window(), (256, 257));
+
+        // four pixels fill the window, the fifth wraps to the start
+        let mut data = Vec::new();
+        for i in 1..=5u8 {
+            data.extend_from_slice(&[i << 3, 0]);
+        }
+        send(&mut emulator, 0x2cu8, &data);
+        assert_eq!(emulator.pixel(10, 256), (10, 0, 0));
+        assert_eq!(emulator.pixel(11, 256), (4, 0, 0));
+        assert_eq!(emulator.pixel(10, 257), (6, 0, 0));
+        assert_eq!(emulator.pixel(11, 257), (8, 0, 0));
+        assert_eq!(emulator.pixel(12, 256), (0, 0, 0));
+
+        // MemoryWriteContinue carries on from the pointer
+        send(&mut emulator, 0x3cu8, &[0xf8, 0x00]);
+        assert_eq!(emulator.pixel(11, 256), (0x3f, 0, 0));
+    }
+
+    #[test]
+    fn memory_access_control() {
+        let cases = [
+            (0u8, (0, 0)),
+            (MADCTL_MX, (WIDTH - 1, 0)),
+            (MADCTL_MY, (0, HEIGHT - 1)),
+            (MADCTL_MV, (0, 0)),
+            (MADCTL_MV | MADCTL_MX, (0, HEIGHT - 1)),
+        ];
+        for (madctl, (x, y)) in cases.iter() {
+            let mut emulator = Emulator::new();
+            send(&mut emulator, 0x36u8, &[*madctl]);
+            send(&mut emulator, 0x2cu8, &[0xfc, 0, 0, 0, 0xfc, 0]);
+            assert_eq!(emulator.madctl(), *madctl);
+            assert_eq!(
+                emulator.pixel(*x, *y),
+                (0x3f, 0, 0),
+                "MADCTL {:02x}",
+                madctl
+            );
+            // the second pixel is next along the column
+            let (x, y) = if madctl & MADCTL_MV != 0 {
+                (*x, if *y == 0 { 1 } else { y - 1 })
+            } else {
+                (if *x == 0 { 1 } else { x - 1 }, *y)
+            };
+            assert_eq!(emulator.pixel(x, y), (0, 0x3f, 0), "MADCTL {:02x}", madctl);
+        }
+    }
+
+    #[test]
+    fn pixel_formats_and_bus_widths() {
+        let mut emulator = Emulator::new();
+        send(&mut emulator, 0x3au8, &[0x11]);
+        assert_eq!(emulator.pixel_format(), Some(PixelFormat::Rgb111));
+        send(&mut emulator, 0x2cu8, &[0b100_010]);
+        assert_eq!(emulator.pixel(0, 0), (0x3f, 0, 0));
+        assert_eq!(emulator.pixel(1, 0), (0, 0x3f, 0));
+
+        let mut emulator = Emulator::new();
+        send(&mut emulator, U9(0x3a), &[U9(0x66)]);
+        send(
+            &mut emulator,
+            U9(0x2c),
+            &[U9(0b0_0000_1000), U9(0b0_1000_0011)],
+        );
+        assert_eq!(emulator.pixel(0, 0), (1, 2, 3));
+
+        let mut emulator = Emulator::new();
+        send(&mut emulator, 0x3au16, &[0x55]);
+        send(&mut emulator, 0x2cu16, &[0xffff, 0x001f]);
+        assert_eq!(emulator.pixel(0, 0), (0x3f, 0x3f, 0x3f));
+        assert_eq!(emulator.pixel(1, 0), (0, 0, 0x3f));
+
+        // two pixels packed into three words
+        send(&mut emulator, 0x3au16, &[0x66]);
+        send(
+            &mut emulator,
+            0x2cu16,
+            &[
+                (1 << 10) | (2 << 2),
+                (3 << 10) | (4 << 2),
+                (5 << 10) | (6 << 2),
+            ],
+        );
+        assert_eq!(emulator.pixel(0, 0), (1, 2, 3));
+        assert_eq!(emulator.pixel(1, 0), (4, 5, 6));
+
+        let mut emulator = Emulator::new();
+        let pixel: U18 = (1 << 12) | (2 << 6) | 3;
+        send(&mut emulator, 0x2c, &[pixel]);
+        assert_eq!(emulator.pixel(0, 0), (1, 2, 3));
+    }
+
+    #[test]
+    fn register_reads() {
+        let mut emulator = Emulator::new();
+        assert_eq!(read(&mut emulator, 0x04u8, 4), [0, 0x54, 0x80, 0x66]);
+        assert_eq!(read(&mut emulator, 0xd3u8, 4), [0, 0x00, 0x94, 0x86]);
+
+        send(&mut emulator, 0x36u8, &[0x4b]);
+        send(&mut emulator, 0x3au8, &[0x55]);
+        send(&mut emulator, 0x11u8, &[]);
+        send(&mut emulator, 0x29u8, &[]);
+        assert_eq!(read(&mut emulator, 0x0bu8, 2), [0, 0x48]);
+        assert_eq!(read(&mut emulator, 0x0cu8, 2), [0, 0x55]);
+        assert_eq!(read(&mut emulator, 0x0au8, 2), [0, 0x9c]);
+
+        // a software reset restores the defaults
+        send(&mut emulator, 0x01u8, &[]);
+        assert_eq!(emulator.madctl(), 0);
+        assert_eq!(emulator.pixel_format(), Some(PixelFormat::Rgb666));
+        assert!(!emulator.is_display_on());
+    }
+
+    #[test]
+    fn memory_reads() {
+        let mut emulator = Emulator::new();
+        send(&mut emulator, 0x2cu8, &[4, 8, 12, 16, 20, 24]);
+
+        assert_eq!(read(&mut emulator, 0x2eu8, 7), [0, 4, 8, 12, 16, 20, 24]);
+        assert_eq!(
+            read(&mut emulator, 0x2eu16, 4),
+            [
+                0,
+                (1 << 10) | (2 << 2),
+                (3 << 10) | (4 << 2),
+                (5 << 10) | (6 << 2)
+            ]
+        );
+        assert_eq!(
+            read(&mut emulator, U9(0x2e), 3),
+            [U9(0), U9(0b0_0000_1000), U9(0b0_1000_0011)]
+        );
+    }
+
+    #[test]
+    fn displayed_pixels() {
+        let mut emulator = Emulator::new();
+        send(&mut emulator, 0x2au8, &[0, 0, 0, 0]);
+        send(&mut emulator, 0x2cu8, &[4, 8, 12, 16, 20, 24]);
+        assert_eq!(emulator.displayed_pixel(0, 0), (0, 0, 0));
+
+        send(&mut emulator, 0x11u8, &[]);
+        send(&mut emulator, 0x29u8, &[]);
+        assert_eq!(emulator.displayed_pixel(0, 0), (1, 2, 3));
+
+        send(&mut emulator, 0x36u8, &[MADCTL_BGR]);
+        assert_eq!(emulator.displayed_pixel(0, 0), (3, 2, 1));
+        send(&mut emulator, 0x21u8, &[]);
+        assert_eq!(emulator.displayed_pixel(0, 0), (0x3c, 0x3d, 0x3e));
+        send(&mut emulator, 0x20u8, &[]);
+        send(&mut emulator, 0x36u8, &[0]);
+
+        // scroll the second line to the top
+        send(&mut emulator, 0x33u8, &[0, 0, 0x01, 0xe0, 0, 0]);
+        send(&mut emulator, 0x37u8, &[0, 1]);
+        assert_eq!(emulator.scroll_start(), 1);
+        assert_eq!(emulator.displayed_pixel(0, 0), (4, 5, 6));
+        assert_eq!(emulator.displayed_pixel(0, HEIGHT - 1), (1, 2, 3));
+    }
+}
@@ -0,0 +1,54 @@
+use super::ParallelInterface;
+use core::convert::TryFrom;
+
+/// A word on a 9-bit bus, only the lower 9 bits are used.
+///
+/// This is a separate type rather than an alias of `u16`, so the 9-bit pixel encoding does not
+/// conflict with the 16-bit one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct U9(pub u16);
+
+impl From<u8> for U9 {
+    fn from(byte: u8) -> U9 {
+        U9(byte as u16)
+    }
+}
+
+impl From<U9> for u32 {
+    fn from(word: U9) -> u32 {
+        (word.0 & 0x1ff) as u32
+    }
+}
+
+impl TryFrom<u32> for U9 {
+    type Error = u32;
+
+    fn try_from(word: u32) -> Result<U9, u32> {
+        if word > 0x1ff {
+            return Err(word);
+        }
+        Ok(U9(word as u16))
+    }
+}
+
+/// An 8080-style parallel interface with 9 data lines on separate pins, DB0 first.
+///
+/// ```ignore
+/// let parallel = GPIO9ParallelInterface::new((db0, db1, ..., db8), cs, dcx, rdx, wrx)?;
+/// ```
+pub type GPIO9ParallelInterface<
+    DB0,
+    DB1,
+    DB2,
+    DB3,
+    DB4,
+    DB5,
+    DB6,
+    DB7,
+    DB8,
+    CS,
+    DCX,
+    RDX,
+    WRX,
+    D = (),
+> = ParallelInterface<(DB0, DB1, DB2, DB3, DB4, DB5, DB6, DB7, DB8), CS, DCX, RDX, WRX, D>;
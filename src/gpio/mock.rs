@@ -0,0 +1,319 @@
+//! Pin-level model of the parallel bus, requires the `std` feature.
+//!
+//! All pins created from a [Bus](Bus) share its state. Like the controller, the bus latches the
+//! data lines on the rising edge of WRX and returns queued words while RDX is low, and it logs
+//! every level change and any [Violation](Violation) of the 8080 signal order:
+//!
+//! ```ignore
+//! let bus = Bus::new();
+//! let data = (bus.pin(Line::Data(0)), ..., bus.pin(Line::Data(7)));
+//! let mut parallel = ParallelInterface::new(
+//!     data,
+//!     bus.pin(Line::Cs),
+//!     bus.pin(Line::Dcx),
+//!     bus.pin(Line::Rdx),
+//!     bus.pin(Line::Wrx),
+//! )?;
+//! parallel.write(WriteMode::Command, &[0x2c])?;
+//! assert_eq!(bus.transfers(), vec![Record::Command(0x2c)]);
+//! assert!(bus.violations().is_empty() && bus.is_idle());
+//! ```
+//!
+//! A bus created with [with_emulator](Bus::with_emulator) passes the words to an
+//! [Emulator](Emulator) and returns its responses, including the dummy reads, so the driver can
+//! be run against the controller model at the pin level.
+
+use crate::decode::BusWidth;
+use crate::emulator::Emulator;
+use crate::recording::Record;
+use crate::IoPin;
+use display_interface::v2::WriteMode;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use std::cell::{Ref, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::vec::Vec;
+
+/// A signal of the bus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Line {
+    /// Data line DBn
+    Data(u8),
+    Cs,
+    Dcx,
+    Rdx,
+    Wrx,
+}
+
+/// A deviation from the 8080 signal order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// WRX or RDX rose while CSX was high
+    StrobeWithoutChipSelect(Line),
+    /// D/CX changed while CSX was low
+    DcxChangedWhileSelected,
+    /// A data line changed while WRX was low, the data was not set up before the strobe
+    DataChangedDuringWrite(u8),
+    /// WRX and RDX were low at the same time
+    WriteDuringRead,
+}
+
+/// Returned by a pin that is set to fail with [fail](Bus::fail).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PinError;
+
+struct State {
+    data: u32,
+    cs: bool,
+    dcx: bool,
+    rdx: bool,
+    wrx: bool,
+    events: Vec<(Line, bool)>,
+    transfers: Vec<Record>,
+    violations: Vec<Violation>,
+    reads: VecDeque<u32>,
+    failing: Option<Line>,
+    emulator: Option<(Emulator, BusWidth)>,
+}
+
+impl State {
+    fn level(&self, line: Line) -> bool {
+        match line {
+            Line::Data(index) => self.data & (1 << index) != 0,
+            Line::Cs => self.cs,
+            Line::Dcx => self.dcx,
+            Line::Rdx => self.rdx,
+            Line::Wrx => self.wrx,
+        }
+    }
+
+    fn set(&mut self, line: Line, high: bool) -> Result<(), PinError> {
+        if self.failing == Some(line) {
+            return Err(PinError);
+        }
+        self.events.push((line, high));
+        if self.level(line) == high {
+            return Ok(());
+        }
+
+        let selected = !self.cs;
+        match line {
+            Line::Data(index) => {
+                if selected && !self.wrx {
+                    self.violations
+                        .push(Violation::DataChangedDuringWrite(index));
+                }
+                self.data ^= 1 << index;
+            }
+            Line::Cs => self.cs = high,
+            Line::Dcx => {
+                if selected {
+                    self.violations.push(Violation::DcxChangedWhileSelected);
+                }
+                self.dcx = high;
+            }
+            Line::Rdx => {
+                self.rdx = high;
+                if !high && selected && self.reads.is_empty() {
+                    // the controller drives the data lines from the falling edge
+                    if let Some((emulator, width)) = &mut self.emulator {
+                        self.reads.push_back(emulator.read_word(*width));
+                    }
+                }
+                if high && !selected {
+                    self.violations
+                        .push(Violation::StrobeWithoutChipSelect(Line::Rdx));
+                } else if high {
+                    let word = self.reads.pop_front().unwrap_or(0);
+                    self.transfers.push(Record::Read(word));
+                }
+            }
+            Line::Wrx => {
+                self.wrx = high;
+                if high && !selected {
+                    self.violations
+                        .push(Violation::StrobeWithoutChipSelect(Line::Wrx));
+                } else if high {
+                    self.transfers.push(if self.dcx {
+                        Record::Data(self.data)
+                    } else {
+                        Record::Command(self.data)
+                    });
+                    if let Some((emulator, width)) = &mut self.emulator {
+                        let mode = if self.dcx {
+                            WriteMode::Data
+                        } else {
+                            WriteMode::Command
+                        };
+                        emulator.write_word(*width, &mode, self.data);
+                    }
+                }
+            }
+        }
+        if !self.wrx && !self.rdx {
+            self.violations.push(Violation::WriteDuringRead);
+        }
+        Ok(())
+    }
+
+    fn get(&self, line: Line) -> Result<bool, PinError> {
+        if self.failing == Some(line) {
+            return Err(PinError);
+        }
+        Ok(match line {
+            // the controller drives the data lines while RDX is low
+            Line::Data(index) if !self.cs && !self.rdx => {
+                self.reads.front().cloned().unwrap_or(0) & (1 << index) != 0
+            }
+            _ => self.level(line),
+        })
+    }
+}
+
+/// The shared state of the mock pins.
+#[derive(Clone)]
+pub struct Bus {
+    state: Rc<RefCell<State>>,
+}
+
+impl Default for Bus {
+    fn default() -> Bus {
+        Bus::new()
+    }
+}
+
+impl Bus {
+    /// A bus with all data lines low and the control lines high.
+    pub fn new() -> Bus {
+        Bus::connect(None)
+    }
+
+    /// A bus connected to `emulator` with `width` data lines.
+    ///
+    /// Latched words are written to the emulator and, once the queued reads run out, RDX pulses
+    /// return what the emulator responds.
+    pub fn with_emulator(emulator: Emulator, width: BusWidth) -> Bus {
+        Bus::connect(Some((emulator, width)))
+    }
+
+    fn connect(emulator: Option<(Emulator, BusWidth)>) -> Bus {
+        Bus {
+            state: Rc::new(RefCell::new(State {
+                data: 0,
+                cs: true,
+                dcx: true,
+                rdx: true,
+                wrx: true,
+                events: Vec::new(),
+                transfers: Vec::new(),
+                violations: Vec::new(),
+                reads: VecDeque::new(),
+                failing: None,
+                emulator,
+            })),
+        }
+    }
+
+    /// A pin connected to `line`.
+    pub fn pin(&self, line: Line) -> MockPin {
+        MockPin {
+            line,
+            state: self.state.clone(),
+        }
+    }
+
+    pub fn level(&self, line: Line) -> bool {
+        self.state.borrow().level(line)
+    }
+
+    /// Whether CSX, RDX, WRX and D/CX are high.
+    pub fn is_idle(&self) -> bool {
+        let state = self.state.borrow();
+        state.cs && state.rdx && state.wrx && state.dcx
+    }
+
+    /// Every call to `set_high` and `set_low` since the last [clear](Bus::clear).
+    pub fn events(&self) -> Vec<(Line, bool)> {
+        self.state.borrow().events.clone()
+    }
+
+    /// The words latched by WRX and read with RDX.
+    pub fn transfers(&self) -> Vec<Record> {
+        self.state.borrow().transfers.clone()
+    }
+
+    pub fn violations(&self) -> Vec<Violation> {
+        self.state.borrow().violations.clone()
+    }
+
+    /// Clears the events, transfers and violations.
+    pub fn clear(&self) {
+        let mut state = self.state.borrow_mut();
+        state.events.clear();
+        state.transfers.clear();
+        state.violations.clear();
+    }
+
+    /// Words driven on the data lines by the following RDX pulses, zero once they run out.
+    pub fn queue_reads(&self, words: &[u32]) {
+        self.state.borrow_mut().reads.extend(words);
+    }
+
+    /// The emulator of a bus created with [with_emulator](Bus::with_emulator).
+    pub fn emulator(&self) -> Option<Ref<'_, Emulator>> {
+        Ref::filter_map(self.state.borrow(), |state| {
+            state.emulator.as_ref().map(|(emulator, _)| emulator)
+        })
+        .ok()
+    }
+
+    /// Makes every access to `line` fail, `None` to stop.
+    pub fn fail(&self, line: Option<Line>) {
+        self.state.borrow_mut().failing = line;
+    }
+}
+
+/// A pin of a [Bus](Bus).
+pub struct MockPin {
+    line: Line,
+    state: Rc<RefCell<State>>,
+}
+
+impl IoPin for MockPin {
+    type InputPinError = PinError;
+    type OutputPinError = PinError;
+    type Input = Self;
+    type Output = Self;
+
+    fn into_input(&mut self) -> &mut Self::Input {
+        self
+    }
+
+    fn into_output(&mut self) -> &mut Self::Output {
+        self
+    }
+}
+
+impl InputPin for MockPin {
+    type Error = PinError;
+
+    fn is_high(&self) -> Result<bool, PinError> {
+        self.state.borrow().get(self.line)
+    }
+
+    fn is_low(&self) -> Result<bool, PinError> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+impl OutputPin for MockPin {
+    type Error = PinError;
+
+    fn set_low(&mut self) -> Result<(), PinError> {
+        self.state.borrow_mut().set(self.line, false)
+    }
+
+    fn set_high(&mut self) -> Result<(), PinError> {
+        self.state.borrow_mut().set(self.line, true)
+    }
+}
@@ -22,7 +22,15 @@ macro_rules! write_bit {
 pub use self::gpio16::*;
 pub use self::gpio18::*;
 pub use self::gpio8::*;
+pub use self::gpio9::*;
+pub use self::parallel::*;
 
 mod gpio16;
 mod gpio18;
 mod gpio8;
+mod gpio9;
+mod parallel;
+
+/// This module provides mock pins that model the parallel bus, requires the `std` feature.
+#[cfg(feature = "std")]
+pub mod mock;
@@ -0,0 +1,751 @@
+use super::{U18, U9};
+use crate::IoPin;
+use core::convert::TryFrom;
+use core::fmt::Debug;
+use display_interface::v2::*;
+use display_interface::DisplayError;
+
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+///
+/// ParallelPort drives or samples all data lines of the bus at once.
+///
+/// Boards that wire the data bus to consecutive pins of one GPIO port can implement this with a
+/// single register access per word, e.g. BSRR on STM32, see [Port8](crate::io::stm32f1xx::Port8).
+/// It is implemented for tuples of [DataPin](DataPin)s of any type and arrays of one pin type,
+/// with 8, 9, 16 or 18 pins, which write one pin at a time.
+///
+pub trait ParallelPort<T> {
+    type Error: Debug;
+
+    /// Switch the data lines to output mode
+    fn set_output_mode(&mut self) -> Result<(), Self::Error>;
+
+    /// Switch the data lines to input mode
+    fn set_input_mode(&mut self) -> Result<(), Self::Error>;
+
+    /// Sets all data lines to `word`
+    fn write(&mut self, word: T) -> Result<(), Self::Error>;
+
+    /// Samples all data lines
+    fn read(&mut self) -> Result<T, Self::Error>;
+}
+
+/// Delays used for the [BusTiming](BusTiming) of a parallel interface.
+///
+/// `()` does not delay, which is the default.
+pub trait DelayNs {
+    /// Pauses for at least `ns` nanoseconds.
+    fn delay_ns(&mut self, ns: u32);
+}
+
+impl DelayNs for () {
+    #[inline(always)]
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// Uses a microsecond delay for the [BusTiming](BusTiming), rounding up, e.g. for long cables
+/// where speed does not matter.
+pub struct MicrosecondDelay<D>(pub D);
+
+impl<D> DelayNs for MicrosecondDelay<D>
+where
+    D: DelayUs<u32>,
+{
+    fn delay_ns(&mut self, ns: u32) {
+        self.0.delay_us(ns.div_ceil(1000));
+    }
+}
+
+/// Minimum durations of the bus signals in nanoseconds, zero skips the delay.
+///
+/// The GPIO toggling speed of the MCU adds to these, so fast parts or boards with long cables
+/// need them while slow parts usually don't.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BusTiming {
+    /// WRX low time, after the data lines are set
+    pub write_low: u32,
+    /// WRX high time, before the next word
+    pub write_high: u32,
+    /// RDX low time, before the data lines are sampled
+    pub read_low: u32,
+    /// RDX high time, before the next word
+    pub read_high: u32,
+    /// Time from CSX low to the first strobe
+    pub cs_setup: u32,
+}
+
+impl BusTiming {
+    /// The minimums of the ILI9486 datasheet for the 8080-I interface, with the read cycle of
+    /// frame memory reads, which is slower than register reads.
+    pub const ILI9486: BusTiming = BusTiming {
+        write_low: 15,
+        write_high: 15,
+        read_low: 355,
+        read_high: 90,
+        cs_setup: 15,
+    };
+}
+
+#[inline(always)]
+pub(crate) fn pause(delay: &mut dyn DelayNs, ns: u32) {
+    if ns > 0 {
+        delay.delay_ns(ns);
+    }
+}
+
+/// An 8080-style parallel interface on top of a [ParallelPort](ParallelPort).
+///
+/// Between transfers the bus is idle with CSX, WRX and RDX high, D/CX high (data) and the data
+/// lines driven by the port, which is only an input while a read is in progress. D/CX is only
+/// changed while CSX is high, and the data lines are set up before WRX falls. Errors of
+/// any pin are returned as `BusWriteError`, or `BusReadError` for the data lines while reading,
+/// after the bus has been returned to idle.
+///
+/// The strobes are as fast as the pins can be toggled, unless a [BusTiming](BusTiming) is set
+/// with [with_timing](ParallelInterface::with_timing).
+pub struct ParallelInterface<P, CS, DCX, RDX, WRX, D = ()>
+where
+    CS: IoPin,
+    DCX: IoPin,
+    RDX: IoPin,
+    WRX: IoPin,
+{
+    port: P,
+    cs: CS,
+    dcx: DCX,
+    rdx: RDX,
+    wrx: WRX,
+    timing: BusTiming,
+    delay: D,
+}
+
+impl<P, CS, DCX, RDX, WRX> ParallelInterface<P, CS, DCX, RDX, WRX>
+where
+    CS: IoPin,
+    DCX: IoPin,
+    RDX: IoPin,
+    WRX: IoPin,
+{
+    pub fn new(
+        port: P,
+        mut cs: CS,
+        mut dcx: DCX,
+        mut rdx: RDX,
+        mut wrx: WRX,
+    ) -> Result<ParallelInterface<P, CS, DCX, RDX, WRX>, DisplayError> {
+        let dcx_output = dcx.into_output();
+        let cs_output = cs.into_output();
+        let rdx_output = rdx.into_output();
+        let wrx_output = wrx.into_output();
+
+        wrap_output_err!(cs_output.set_high())?;
+        wrap_output_err!(rdx_output.set_high())?;
+        wrap_output_err!(wrx_output.set_high())?;
+        wrap_output_err!(dcx_output.set_high())?;
+
+        Ok(ParallelInterface {
+            port,
+            cs,
+            dcx,
+            rdx,
+            wrx,
+            timing: BusTiming::default(),
+            delay: (),
+        })
+    }
+}
+
+impl<P, CS, DCX, RDX, WRX, D> ParallelInterface<P, CS, DCX, RDX, WRX, D>
+where
+    CS: IoPin,
+    DCX: IoPin,
+    RDX: IoPin,
+    WRX: IoPin,
+    D: DelayNs,
+{
+    /// Uses `delay` to hold the bus signals for at least the durations in `timing`.
+    pub fn with_timing<E>(
+        self,
+        timing: BusTiming,
+        delay: E,
+    ) -> ParallelInterface<P, CS, DCX, RDX, WRX, E>
+    where
+        E: DelayNs,
+    {
+        ParallelInterface {
+            port: self.port,
+            cs: self.cs,
+            dcx: self.dcx,
+            rdx: self.rdx,
+            wrx: self.wrx,
+            timing,
+            delay,
+        }
+    }
+
+    pub fn timing(&self) -> BusTiming {
+        self.timing
+    }
+
+    pub fn port(&mut self) -> &mut P {
+        &mut self.port
+    }
+
+    pub fn release(self) -> (P, CS, DCX, RDX, WRX) {
+        (self.port, self.cs, self.dcx, self.rdx, self.wrx)
+    }
+}
+
+/// Strobes RDX for every word until `f` returns false.
+fn read_words<T, P, R>(
+    port: &mut P,
+    rdx: &mut R,
+    delay: &mut dyn DelayNs,
+    timing: BusTiming,
+    f: &mut dyn FnMut(T) -> bool,
+) -> Result<(), DisplayError>
+where
+    P: ParallelPort<T>,
+    R: OutputPin,
+{
+    loop {
+        wrap_output_err!(rdx.set_low())?;
+        pause(delay, timing.read_low);
+        // the controller drives the data lines until RDX rises
+        let word = wrap_input_err!(port.read())?;
+        wrap_output_err!(rdx.set_high())?;
+        pause(delay, timing.read_high);
+
+        if !f(word) {
+            return Ok(());
+        }
+    }
+}
+
+/// Sets up every word on the data lines and latches it with a WRX pulse.
+fn write_words<'a, T, P, W>(
+    port: &mut P,
+    wrx: &mut W,
+    delay: &mut dyn DelayNs,
+    timing: BusTiming,
+    func: &mut dyn FnMut() -> Option<&'a T>,
+) -> Result<(), DisplayError>
+where
+    T: Copy + 'a,
+    P: ParallelPort<T>,
+    W: OutputPin,
+{
+    while let Some(word) = func() {
+        // the controller latches the data lines on the rising edge of WRX
+        wrap_output_err!(port.write(*word))?;
+        wrap_output_err!(wrx.set_low())?;
+        pause(delay, timing.write_low);
+        wrap_output_err!(wrx.set_high())?;
+        pause(delay, timing.write_high);
+    }
+    Ok(())
+}
+
+impl<P, T, CS, DCX, RDX, WRX, D> ReadInterface<T> for ParallelInterface<P, CS, DCX, RDX, WRX, D>
+where
+    P: ParallelPort<T>,
+    D: DelayNs,
+    CS: IoPin,
+    DCX: IoPin,
+    RDX: IoPin,
+    WRX: IoPin,
+{
+    fn read_stream(&mut self, f: &mut dyn FnMut(T) -> bool) -> Result<(), DisplayError> {
+        let timing = self.timing;
+        wrap_input_err!(self.port.set_input_mode())?;
+        let cs = self.cs.into_output();
+        let rdx = self.rdx.into_output();
+        let dcx = self.dcx.into_output();
+        let wrx = self.wrx.into_output();
+
+        wrap_output_err!(rdx.set_high())?;
+        wrap_output_err!(wrx.set_high())?;
+        wrap_output_err!(dcx.set_high())?;
+        wrap_output_err!(cs.set_low())?;
+        pause(&mut self.delay, timing.cs_setup);
+
+        let result = read_words(&mut self.port, rdx, &mut self.delay, timing, f);
+
+        // back to idle even if a read failed, the port drives the data lines again once the
+        // controller is deselected
+        let idle = wrap_output_err!(rdx.set_high())
+            .and(wrap_output_err!(cs.set_high()))
+            .and(wrap_output_err!(self.port.set_output_mode()));
+        result.and(idle)
+    }
+}
+
+impl<P, T, CS, DCX, RDX, WRX, D> WriteInterface<T> for ParallelInterface<P, CS, DCX, RDX, WRX, D>
+where
+    P: ParallelPort<T>,
+    T: Copy,
+    D: DelayNs,
+    CS: IoPin,
+    DCX: IoPin,
+    RDX: IoPin,
+    WRX: IoPin,
+{
+    #[inline(always)]
+    fn write_stream<'a>(
+        &mut self,
+        mode: WriteMode,
+        func: &mut dyn FnMut() -> Option<&'a T>,
+    ) -> Result<(), DisplayError> {
+        let timing = self.timing;
+        wrap_output_err!(self.port.set_output_mode())?;
+        let cs = self.cs.into_output();
+        let rdx = self.rdx.into_output();
+        let dcx = self.dcx.into_output();
+        let wrx = self.wrx.into_output();
+
+        wrap_output_err!(rdx.set_high())?;
+        wrap_output_err!(wrx.set_high())?;
+        match mode {
+            WriteMode::Command => {
+                wrap_output_err!(dcx.set_low())?;
+            }
+            _ => {
+                wrap_output_err!(dcx.set_high())?;
+            }
+        }
+        wrap_output_err!(cs.set_low())?;
+        pause(&mut self.delay, timing.cs_setup);
+
+        let result = write_words(&mut self.port, wrx, &mut self.delay, timing, func);
+
+        // back to idle even if a write failed, D/CX changes only while CSX is high
+        let idle = wrap_output_err!(wrx.set_high())
+            .and(wrap_output_err!(cs.set_high()))
+            .and(wrap_output_err!(dcx.set_high()));
+        result.and(idle)
+    }
+}
+
+/// A pin that is its own input and output pin, like the [io](crate::io) pins, so its mode can be
+/// switched once per transfer instead of once per word.
+///
+/// Tuple and array ports only accept these, an [IoPin](IoPin) with separate input and output
+/// types can't be used as a data line.
+pub trait DataPin: IoPin<Input = Self, Output = Self> + InputPin + OutputPin {}
+
+impl<P> DataPin for P where P: IoPin<Input = P, Output = P> + InputPin + OutputPin {}
+
+macro_rules! tuple_port {
+    ($word:ty, $($pin:ident: $index:tt),+) => {
+        impl<$($pin),+> ParallelPort<$word> for ($($pin,)+)
+        where
+            $($pin: DataPin,)+
+        {
+            type Error = DisplayError;
+
+            fn set_output_mode(&mut self) -> Result<(), DisplayError> {
+                $(self.$index.into_output();)+
+                Ok(())
+            }
+
+            fn set_input_mode(&mut self) -> Result<(), DisplayError> {
+                $(self.$index.into_input();)+
+                Ok(())
+            }
+
+            #[inline(always)]
+            fn write(&mut self, word: $word) -> Result<(), DisplayError> {
+                let bits = u32::from(word);
+                $(write_bit!(self.$index, (1 << $index) & bits != 0);)+
+                Ok(())
+            }
+
+            fn read(&mut self) -> Result<$word, DisplayError> {
+                let mut bits: u32 = 0;
+                $(
+                    if wrap_input_err!(self.$index.is_high())? {
+                        bits |= 1 << $index;
+                    }
+                )+
+                <$word>::try_from(bits).map_err(|_| DisplayError::BusReadError)
+            }
+        }
+    };
+}
+
+macro_rules! array_port {
+    ($word:ty, $width:expr) => {
+        impl<P> ParallelPort<$word> for [P; $width]
+        where
+            P: DataPin,
+        {
+            type Error = DisplayError;
+
+            fn set_output_mode(&mut self) -> Result<(), DisplayError> {
+                for pin in self.iter_mut() {
+                    pin.into_output();
+                }
+                Ok(())
+            }
+
+            fn set_input_mode(&mut self) -> Result<(), DisplayError> {
+                for pin in self.iter_mut() {
+                    pin.into_input();
+                }
+                Ok(())
+            }
+
+            #[inline(always)]
+            fn write(&mut self, word: $word) -> Result<(), DisplayError> {
+                let bits = u32::from(word);
+                for (index, pin) in self.iter_mut().enumerate() {
+                    write_bit!(pin, (1 << index) & bits != 0);
+                }
+                Ok(())
+            }
+
+            fn read(&mut self) -> Result<$word, DisplayError> {
+                let mut bits: u32 = 0;
+                for (index, pin) in self.iter_mut().enumerate() {
+                    if wrap_input_err!(pin.is_high())? {
+                        bits |= 1 << index;
+                    }
+                }
+                <$word>::try_from(bits).map_err(|_| DisplayError::BusReadError)
+            }
+        }
+    };
+}
+
+tuple_port!(u8, DB0: 0, DB1: 1, DB2: 2, DB3: 3, DB4: 4, DB5: 5, DB6: 6, DB7: 7);
+tuple_port!(U9, DB0: 0, DB1: 1, DB2: 2, DB3: 3, DB4: 4, DB5: 5, DB6: 6, DB7: 7, DB8: 8);
+tuple_port!(
+    u16, DB0: 0, DB1: 1, DB2: 2, DB3: 3, DB4: 4, DB5: 5, DB6: 6, DB7: 7, DB8: 8, DB9: 9, DB10: 10,
+    DB11: 11, DB12: 12, DB13: 13, DB14: 14, DB15: 15
+);
+tuple_port!(
+    U18, DB0: 0, DB1: 1, DB2: 2, DB3: 3, DB4: 4, DB5: 5, DB6: 6, DB7: 7, DB8: 8, DB9: 9, DB10: 10,
+    DB11: 11, DB12: 12, DB13: 13, DB14: 14, DB15: 15, DB16: 16, DB17: 17
+);
+
+array_port!(u8, 8);
+array_port!(U9, 9);
+array_port!(u16, 16);
+array_port!(U18, 18);
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::color::{PixelFormat, PixelReader, PixelWriter};
+    use crate::decode::BusWidth;
+    use crate::emulator::{Emulator, NoDelay, NoPin};
+    use crate::gpio::mock::{Bus, Line, MockPin};
+    use crate::io::shim::OutputOnlyIoPin;
+    use crate::recording::Record;
+    use crate::ILI9486;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec;
+    use std::vec::Vec;
+
+    fn data<const N: usize>(bus: &Bus) -> [MockPin; N] {
+        core::array::from_fn(|index| bus.pin(Line::Data(index as u8)))
+    }
+
+    fn interface<P>(
+        bus: &Bus,
+        port: P,
+    ) -> ParallelInterface<P, MockPin, MockPin, MockPin, MockPin> {
+        ParallelInterface::new(
+            port,
+            bus.pin(Line::Cs),
+            bus.pin(Line::Dcx),
+            bus.pin(Line::Rdx),
+            bus.pin(Line::Wrx),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn writes() {
+        let bus = Bus::new();
+        let [db0, db1, db2, db3, db4, db5, db6, db7] = data(&bus);
+        let mut parallel = interface(&bus, (db0, db1, db2, db3, db4, db5, db6, db7));
+        parallel.write(WriteMode::Command, &[0x2au8]).unwrap();
+        parallel.write(WriteMode::Data, &[0x12u8, 0xed]).unwrap();
+        parallel.write(WriteMode::Command, &[0x2cu8]).unwrap();
+
+        assert_eq!(
+            bus.transfers(),
+            vec![
+                Record::Command(0x2a),
+                Record::Data(0x12),
+                Record::Data(0xed),
+                Record::Command(0x2c)
+            ]
+        );
+        // the data is set up before WRX falls and D/CX only changes while CSX is high
+        assert_eq!(bus.violations(), vec![]);
+        assert!(bus.is_idle());
+
+        // every word is set up before its WRX pulse
+        let events = bus.events();
+        let strobes: Vec<usize> = (0..events.len())
+            .filter(|&index| events[index] == (Line::Wrx, false))
+            .collect();
+        assert_eq!(strobes.len(), 4);
+        for index in strobes {
+            assert!(events[index - 8..index]
+                .iter()
+                .all(|(line, _)| matches!(line, Line::Data(_))));
+            assert_eq!(events[index + 1], (Line::Wrx, true));
+        }
+    }
+
+    #[test]
+    fn reads() {
+        let bus = Bus::new();
+        let mut parallel = interface(&bus, data(&bus));
+        parallel.write(WriteMode::Command, &[0x0cu8]).unwrap();
+        bus.queue_reads(&[0xa5, 0x5a]);
+        let mut words = [0u8; 2];
+        parallel.read(&mut words).unwrap();
+
+        assert_eq!(words, [0xa5, 0x5a]);
+        assert_eq!(
+            bus.transfers(),
+            vec![
+                Record::Command(0x0c),
+                Record::Read(0xa5),
+                Record::Read(0x5a)
+            ]
+        );
+        assert_eq!(bus.violations(), vec![]);
+        assert!(bus.is_idle());
+    }
+
+    #[test]
+    fn pin_errors() {
+        let bus = Bus::new();
+        let mut parallel = interface(&bus, data(&bus));
+
+        bus.fail(Some(Line::Data(3)));
+        assert!(matches!(
+            parallel.write(WriteMode::Command, &[0x2cu8]),
+            Err(DisplayError::BusWriteError)
+        ));
+        assert!(bus.is_idle());
+
+        let mut words = [0u8; 1];
+        assert!(matches!(
+            parallel.read(&mut words),
+            Err(DisplayError::BusReadError)
+        ));
+        assert!(bus.is_idle());
+
+        // no word was latched, the strobe of the failed read is completed
+        assert_eq!(bus.transfers(), vec![Record::Read(0)]);
+        bus.clear();
+
+        bus.fail(Some(Line::Wrx));
+        assert!(matches!(
+            parallel.write(WriteMode::Data, &[0u8]),
+            Err(DisplayError::BusWriteError)
+        ));
+        bus.fail(None);
+        parallel.write(WriteMode::Data, &[0x42u8]).unwrap();
+        assert_eq!(bus.transfers(), vec![Record::Data(0x42)]);
+        assert!(bus.is_idle());
+    }
+
+    /// Draws pixels and reads them back through the pins of a bus with `N` data lines.
+    fn readback<T, const N: usize>(width: BusWidth)
+    where
+        T: From<u8> + Into<u32> + Copy + Default,
+        [MockPin; N]: ParallelPort<T>,
+        ParallelInterface<[MockPin; N], MockPin, MockPin, MockPin, MockPin>:
+            ReadWriteInterface<T> + PixelWriter<T> + PixelReader<T>,
+    {
+        for color_mode in [PixelFormat::Rgb565, PixelFormat::Rgb666].iter() {
+            let bus = Bus::with_emulator(Emulator::new(), width);
+            let mut lcd: ILI9486<_, T> = ILI9486::new(
+                &mut NoDelay,
+                *color_mode,
+                interface(&bus, data::<N>(&bus)),
+                OutputOnlyIoPin::new(NoPin),
+            )
+            .unwrap();
+            // reads the pixel format back
+            lcd.set_color_mode(*color_mode).unwrap();
+
+            let drawn = [(31, 0, 1), (0, 63, 2), (3, 4, 5)];
+            for (x, (r, g, b)) in drawn.iter().enumerate() {
+                lcd._draw_pixel(100 + x as u16, 200, *r, *g, *b).unwrap();
+            }
+            let mut pixels = [(0, 0, 0); 3];
+            lcd.read_pixels(100, 200, 3, 1, &mut pixels).unwrap();
+            assert_eq!(pixels, drawn, "{:?}", color_mode);
+
+            // a dummy word before the pixel format and before the pixels, 16-bit reads return
+            // two pixels in 3 words
+            let reads = bus
+                .transfers()
+                .iter()
+                .filter(|record| matches!(record, Record::Read(_)))
+                .count();
+            let pixel_words = match width {
+                BusWidth::Bits16 => 6,
+                _ => 3,
+            };
+            assert_eq!(reads, 2 + 1 + pixel_words, "{:?}", color_mode);
+            // and the bus is back to idle
+            assert_eq!(bus.violations(), vec![]);
+            assert!(bus.is_idle());
+        }
+    }
+
+    #[test]
+    fn reads_16_bit() {
+        readback::<u16, 16>(BusWidth::Bits16);
+    }
+
+    #[test]
+    fn reads_18_bit() {
+        readback::<U18, 18>(BusWidth::Bits18);
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Step {
+        Edge(Line, bool),
+        Delay(u32),
+    }
+
+    /// The delays with the number of bus events before each.
+    type DelayLog = Rc<RefCell<Vec<(usize, u32)>>>;
+
+    /// Records every delay in a [DelayLog](DelayLog).
+    struct Delays {
+        bus: Bus,
+        log: DelayLog,
+    }
+
+    impl DelayNs for Delays {
+        fn delay_ns(&mut self, ns: u32) {
+            let events = self.bus.events().len();
+            self.log.borrow_mut().push((events, ns));
+        }
+    }
+
+    /// The CSX, RDX and WRX edges and the delays between them, in order.
+    fn timeline(bus: &Bus, delays: &[(usize, u32)]) -> Vec<Step> {
+        let events = bus.events();
+        let mut steps = Vec::new();
+        let mut delays = delays.iter().peekable();
+        for index in 0..=events.len() {
+            while let Some((_, ns)) = delays.next_if(|(position, _)| *position == index) {
+                steps.push(Step::Delay(*ns));
+            }
+            match events.get(index) {
+                Some((line @ Line::Cs, high))
+                | Some((line @ Line::Rdx, high))
+                | Some((line @ Line::Wrx, high)) => steps.push(Step::Edge(*line, *high)),
+                _ => {}
+            }
+        }
+        steps
+    }
+
+    type Timed = ParallelInterface<[MockPin; 8], MockPin, MockPin, MockPin, MockPin, Delays>;
+
+    fn timed(bus: &Bus, timing: BusTiming) -> (Timed, DelayLog) {
+        let log = DelayLog::default();
+        let delays = Delays {
+            bus: bus.clone(),
+            log: log.clone(),
+        };
+        let parallel = interface(bus, data(bus)).with_timing(timing, delays);
+        assert_eq!(parallel.timing(), timing);
+        bus.clear();
+        (parallel, log)
+    }
+
+    #[test]
+    fn holds_the_strobes() {
+        let bus = Bus::new();
+        let timing = BusTiming {
+            write_low: 1,
+            write_high: 2,
+            read_low: 3,
+            read_high: 4,
+            cs_setup: 5,
+        };
+        let (mut parallel, log) = timed(&bus, timing);
+        parallel.write(WriteMode::Data, &[0x12u8, 0x34]).unwrap();
+        assert_eq!(
+            timeline(&bus, &log.borrow()),
+            vec![
+                Step::Edge(Line::Rdx, true),
+                Step::Edge(Line::Wrx, true),
+                Step::Edge(Line::Cs, false),
+                Step::Delay(5),
+                Step::Edge(Line::Wrx, false),
+                Step::Delay(1),
+                Step::Edge(Line::Wrx, true),
+                Step::Delay(2),
+                Step::Edge(Line::Wrx, false),
+                Step::Delay(1),
+                Step::Edge(Line::Wrx, true),
+                Step::Delay(2),
+                Step::Edge(Line::Wrx, true),
+                Step::Edge(Line::Cs, true)
+            ]
+        );
+
+        bus.clear();
+        log.borrow_mut().clear();
+        bus.queue_reads(&[0xa5, 0x5a]);
+        let mut words = [0u8; 2];
+        parallel.read(&mut words).unwrap();
+        assert_eq!(words, [0xa5, 0x5a]);
+        assert_eq!(
+            timeline(&bus, &log.borrow()),
+            vec![
+                Step::Edge(Line::Rdx, true),
+                Step::Edge(Line::Wrx, true),
+                Step::Edge(Line::Cs, false),
+                Step::Delay(5),
+                Step::Edge(Line::Rdx, false),
+                Step::Delay(3),
+                Step::Edge(Line::Rdx, true),
+                Step::Delay(4),
+                Step::Edge(Line::Rdx, false),
+                Step::Delay(3),
+                Step::Edge(Line::Rdx, true),
+                Step::Delay(4),
+                Step::Edge(Line::Rdx, true),
+                Step::Edge(Line::Cs, true)
+            ]
+        );
+        assert_eq!(bus.violations(), vec![]);
+    }
+
+    #[test]
+    fn skips_zero_delays() {
+        let bus = Bus::new();
+        let timing = BusTiming {
+            write_high: 7,
+            read_low: 9,
+            ..BusTiming::default()
+        };
+        let (mut parallel, log) = timed(&bus, timing);
+        parallel.write(WriteMode::Command, &[0x2cu8]).unwrap();
+        parallel.write(WriteMode::Data, &[0u8, 1]).unwrap();
+        let mut words = [0u8; 2];
+        parallel.read(&mut words).unwrap();
+
+        let delays: Vec<u32> = log.borrow().iter().map(|(_, ns)| *ns).collect();
+        assert_eq!(delays, [7, 7, 7, 9, 9]);
+    }
+}
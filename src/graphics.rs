@@ -0,0 +1,537 @@
+//! `DrawTarget` implementations for embedded-graphics.
+//!
+//! * `graphics` (default) - embedded-graphics 0.6, any `RgbColor`
+//! * `graphics-0-7` - embedded-graphics 0.7 (embedded-graphics-core 0.3), `Rgb565`, other colors
+//!   through [WithColor](WithColor)
+//! * `graphics-0-8` - embedded-graphics 0.8 (embedded-graphics-core 0.4), same as above
+//!
+//! The features can be enabled together while migrating between versions. Colors are converted
+//! to the [PixelFormat](crate::color::PixelFormat) of the driver with rounding.
+
+#[cfg(any(feature = "graphics-0-7", feature = "graphics-0-8"))]
+use crate::color::{PixelWriter, RGBPixel};
+#[cfg(any(feature = "graphics-0-7", feature = "graphics-0-8"))]
+use crate::{dcs, Commands, ILI9486};
+#[cfg(any(feature = "graphics-0-7", feature = "graphics-0-8"))]
+use core::marker::PhantomData;
+#[cfg(any(feature = "graphics-0-7", feature = "graphics-0-8"))]
+use display_interface::{v2::*, DisplayError};
+
+#[cfg(feature = "graphics")]
+mod graphics_0_6 {
+    use crate::color::{PixelFormat, PixelWriter, RGBPixel};
+    use crate::ILI9486;
+    use core::convert::Infallible;
+    use display_interface::v2::*;
+    use embedded_graphics::prelude::Pixel;
+    use embedded_graphics::prelude::RgbColor;
+    use embedded_graphics::prelude::Size;
+    use embedded_graphics::primitives::Rectangle;
+    use embedded_graphics::style::PrimitiveStyle;
+    use embedded_graphics::style::Styled;
+    use embedded_graphics::DrawTarget;
+
+    /// Converts `color` to the components of `pixel_format`.
+    fn native<C: RgbColor>(pixel_format: &PixelFormat, color: &C) -> RGBPixel {
+        pixel_format.convert(
+            &(color.r(), color.g(), color.b()),
+            &(C::MAX_R, C::MAX_G, C::MAX_B),
+        )
+    }
+
+    impl<RW, RGBC, T> DrawTarget<RGBC> for ILI9486<RW, T>
+    where
+        RGBC: RgbColor,
+        RW: ReadWriteInterface<T> + PixelWriter<T>,
+        T: From<u8> + Copy + Default,
+    {
+        type Error = Infallible;
+
+        fn draw_rectangle(
+            &mut self,
+            item: &Styled<Rectangle, PrimitiveStyle<RGBC>>,
+        ) -> Result<(), Self::Error> {
+            let color = native(
+                &self.color_mode,
+                &item.style.fill_color.unwrap_or(RgbColor::BLACK),
+            );
+            self._draw_rect(
+                item.primitive.top_left.x as u16,
+                item.primitive.top_left.y as u16,
+                (item.primitive.bottom_right.x - item.primitive.top_left.x) as u32,
+                (item.primitive.bottom_right.y - item.primitive.top_left.y) as u32,
+                color,
+            )
+            .unwrap();
+
+            if let Some(stroke_color) = item.style.stroke_color {
+                let stroke_color = native(&self.color_mode, &stroke_color);
+                self._draw_rect(
+                    item.primitive.top_left.x as u16,
+                    item.primitive.top_left.y as u16,
+                    (item.primitive.bottom_right.x - item.primitive.top_left.x) as u32,
+                    item.style.stroke_width,
+                    stroke_color,
+                )
+                .unwrap();
+
+                self._draw_rect(
+                    item.primitive.top_left.x as u16,
+                    (item.primitive.top_left.y as u32 + item.style.stroke_width) as u16,
+                    item.style.stroke_width,
+                    (item.primitive.bottom_right.y
+                        - item.primitive.top_left.y
+                        - item.style.stroke_width as i32) as u32,
+                    stroke_color,
+                )
+                .unwrap();
+
+                self._draw_rect(
+                    (item.primitive.bottom_right.x as u32 - item.style.stroke_width) as u16,
+                    (item.primitive.top_left.y as u32 + item.style.stroke_width) as u16,
+                    item.style.stroke_width,
+                    (item.primitive.bottom_right.y
+                        - item.primitive.top_left.y
+                        - item.style.stroke_width as i32) as u32,
+                    stroke_color,
+                )
+                .unwrap();
+
+                self._draw_rect(
+                    (item.primitive.top_left.x as u32 + item.style.stroke_width) as u16,
+                    (item.primitive.bottom_right.y as u32 - item.style.stroke_width) as u16,
+                    (item.primitive.bottom_right.x - item.primitive.top_left.x) as u32
+                        - item.style.stroke_width * 2,
+                    item.style.stroke_width,
+                    stroke_color,
+                )
+                .unwrap();
+            }
+            Ok(())
+        }
+
+        fn draw_pixel(&mut self, item: Pixel<RGBC>) -> Result<(), Self::Error> {
+            let color = native(&self.color_mode, &item.1);
+            self._draw_pixel(item.0.x as u16, item.0.y as u16, color.0, color.1, color.2)
+                .unwrap();
+            Ok(())
+        }
+        /// The size in the current orientation, width and height are swapped when rows and
+        /// columns are exchanged in MADCTL.
+        fn size(&self) -> Size {
+            let (columns, pages) = self.dimensions();
+            Size::new(columns as u32, pages as u32)
+        }
+    }
+}
+
+/// Draws with colors of type `C` through embedded-graphics 0.7 or later, created with
+/// [with_color](ILI9486::with_color).
+///
+/// `ILI9486` itself draws `Rgb565`, use this e.g. for `Rgb666` in the 18-bit mode:
+///
+/// ```ignore
+/// Circle::new(Point::new(80, 80), 64)
+///     .into_styled(PrimitiveStyle::with_fill(Rgb666::new(63, 20, 0)))
+///     .draw(&mut lcd.with_color::<Rgb666>())?;
+/// ```
+#[cfg(any(feature = "graphics-0-7", feature = "graphics-0-8"))]
+pub struct WithColor<'a, RW, T, C>
+where
+    RW: ReadWriteInterface<T> + PixelWriter<T>,
+{
+    driver: &'a mut ILI9486<RW, T>,
+    _color: PhantomData<C>,
+}
+
+#[cfg(any(feature = "graphics-0-7", feature = "graphics-0-8"))]
+impl<RW, T> ILI9486<RW, T>
+where
+    RW: ReadWriteInterface<T> + PixelWriter<T>,
+    T: From<u8> + Copy + Default,
+{
+    /// Returns a draw target for colors of type `C`.
+    pub fn with_color<C>(&mut self) -> WithColor<'_, RW, T, C> {
+        WithColor {
+            driver: self,
+            _color: PhantomData,
+        }
+    }
+
+    /// Draws pixels in the format of the driver, skipping the ones off screen.
+    fn draw_points(
+        &mut self,
+        pixels: &mut dyn Iterator<Item = ((i32, i32), RGBPixel)>,
+    ) -> Result<(), DisplayError> {
+        let (columns, pages) = self.dimensions();
+        for ((x, y), color) in pixels {
+            if x < 0 || y < 0 || x >= columns as i32 || y >= pages as i32 {
+                continue;
+            }
+            self._draw_pixel(x as u16, y as u16, color.0, color.1, color.2)?;
+        }
+        Ok(())
+    }
+
+    /// Fills an area with pixels in the format of the driver, row by row.
+    fn fill_area(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        colors: &mut dyn Iterator<Item = RGBPixel>,
+    ) -> Result<(), DisplayError> {
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let (columns, pages) = self.dimensions();
+        if x < 0
+            || y < 0
+            || x as i64 + width as i64 > columns as i64
+            || y as i64 + height as i64 > pages as i64
+        {
+            // partly off screen, the colors of the hidden pixels are skipped
+            let points = (0..height as i32)
+                .flat_map(|row| (0..width as i32).map(move |column| (x + column, y + row)));
+            return self.draw_points(&mut points.zip(colors));
+        }
+
+        self.column_address_set(x as u16, (x as u32 + width - 1) as u16)?;
+        self.page_address_set(y as u16, (y as u32 + height - 1) as u16)?;
+        self.send(&dcs::MemoryWrite)?;
+
+        // the copy of a lone last pixel of a packing format would land after the area, the
+        // pixel is drawn on its own instead
+        let color_mode = self.color_mode;
+        let area = (width * height) as usize;
+        let lone = area % 2 == 1 && self.rw_interface.packs_pixels(&color_mode);
+        let mut colors = colors.take(area);
+        self.rw_interface
+            .write_pixels(&color_mode, &mut colors.by_ref().take(area - lone as usize))?;
+        match colors.next() {
+            Some(last) if lone => self._draw_pixel(
+                (x as u32 + width - 1) as u16,
+                (y as u32 + height - 1) as u16,
+                last.0,
+                last.1,
+                last.2,
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fills the visible part of an area with a pixel in the format of the driver.
+    fn fill_solid_area(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        color: RGBPixel,
+    ) -> Result<(), DisplayError> {
+        let (columns, pages) = self.dimensions();
+        let left = (x as i64).max(0);
+        let top = (y as i64).max(0);
+        let right = (x as i64 + width as i64).min(columns as i64);
+        let bottom = (y as i64 + height as i64).min(pages as i64);
+        if right <= left || bottom <= top {
+            return Ok(());
+        }
+        self._draw_rect(
+            left as u16,
+            top as u16,
+            (right - left) as u32,
+            (bottom - top) as u32,
+            color,
+        )
+    }
+}
+
+/// Implements `DrawTarget` and `OriginDimensions` of an embedded-graphics-core version for
+/// `ILI9486` (drawing `Rgb565`) and [WithColor](WithColor).
+#[cfg(any(feature = "graphics-0-7", feature = "graphics-0-8"))]
+macro_rules! draw_target {
+    ($core:ident) => {
+        use super::WithColor;
+        use crate::color::{PixelFormat, PixelWriter, RGBPixel};
+        use crate::ILI9486;
+        use display_interface::v2::*;
+        use display_interface::DisplayError;
+        use $core::draw_target::DrawTarget;
+        use $core::geometry::{Dimensions, OriginDimensions, Size};
+        use $core::pixelcolor::{Rgb565, RgbColor};
+        use $core::primitives::Rectangle;
+        use $core::Pixel;
+
+        /// Converts `color` to the components of `pixel_format`.
+        fn native<C: RgbColor>(pixel_format: &PixelFormat, color: &C) -> RGBPixel {
+            pixel_format.convert(
+                &(color.r(), color.g(), color.b()),
+                &(C::MAX_R, C::MAX_G, C::MAX_B),
+            )
+        }
+
+        impl<RW, T> OriginDimensions for ILI9486<RW, T>
+        where
+            RW: ReadWriteInterface<T> + PixelWriter<T>,
+            T: From<u8> + Copy + Default,
+        {
+            /// The size in the current orientation, width and height are swapped when rows and
+            /// columns are exchanged in MADCTL.
+            fn size(&self) -> Size {
+                let (columns, pages) = self.dimensions();
+                Size::new(columns as u32, pages as u32)
+            }
+        }
+
+        impl<'a, RW, T, C> OriginDimensions for WithColor<'a, RW, T, C>
+        where
+            RW: ReadWriteInterface<T> + PixelWriter<T>,
+            T: From<u8> + Copy + Default,
+        {
+            fn size(&self) -> Size {
+                let (columns, pages) = self.driver.dimensions();
+                Size::new(columns as u32, pages as u32)
+            }
+        }
+
+        draw_target!($core, ILI9486<RW, T>, Rgb565, |target| target);
+        draw_target!($core, WithColor<'a, RW, T, C>, C, |target| target.driver, C: RgbColor);
+    };
+    ($core:ident, $target:ty, $color:ty, |$this:ident| $driver:expr $(, $param:ident: $bound:path)*) => {
+        impl<'a, RW, T $(, $param)*> DrawTarget for $target
+        where
+            RW: ReadWriteInterface<T> + PixelWriter<T>,
+            T: From<u8> + Copy + Default,
+            $($param: $bound,)*
+        {
+            type Color = $color;
+            type Error = DisplayError;
+
+            fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+            where
+                I: IntoIterator<Item = Pixel<Self::Color>>,
+            {
+                let $this = self;
+                let driver = &mut *$driver;
+                let color_mode = driver.color_mode;
+                driver.draw_points(&mut pixels.into_iter().map(|Pixel(point, color)| {
+                    ((point.x, point.y), native(&color_mode, &color))
+                }))
+            }
+
+            fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+            where
+                I: IntoIterator<Item = Self::Color>,
+            {
+                let $this = self;
+                let driver = &mut *$driver;
+                let color_mode = driver.color_mode;
+                driver.fill_area(
+                    area.top_left.x,
+                    area.top_left.y,
+                    area.size.width,
+                    area.size.height,
+                    &mut colors
+                        .into_iter()
+                        .map(|color| native(&color_mode, &color)),
+                )
+            }
+
+            fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+                let $this = self;
+                let driver = &mut *$driver;
+                let color = native(&driver.color_mode, &color);
+                driver.fill_solid_area(
+                    area.top_left.x,
+                    area.top_left.y,
+                    area.size.width,
+                    area.size.height,
+                    color,
+                )
+            }
+
+            fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+                let area = self.bounding_box();
+                self.fill_solid(&area, color)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "graphics-0-7")]
+mod graphics_0_7 {
+    draw_target!(embedded_graphics_core_03);
+}
+
+#[cfg(feature = "graphics-0-8")]
+mod graphics_0_8 {
+    draw_target!(embedded_graphics_core_04);
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::color::PixelFormat;
+    use crate::emulator::{Emulator, NoDelay, NoPin};
+    use crate::io::shim::OutputOnlyIoPin;
+    use crate::ILI9486;
+
+    fn driver() -> ILI9486<Emulator, u8> {
+        ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb565,
+            Emulator::new(),
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "graphics")]
+    fn size_0_6() {
+        use embedded_graphics::pixelcolor::Rgb565;
+        use embedded_graphics::prelude::Size;
+        use embedded_graphics::DrawTarget;
+
+        let mut lcd = driver();
+        assert_eq!(DrawTarget::<Rgb565>::size(&lcd), Size::new(320, 480));
+        lcd.set_memory_access_control(0x28).unwrap();
+        assert_eq!(DrawTarget::<Rgb565>::size(&lcd), Size::new(480, 320));
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-0-8")]
+    fn size_0_8() {
+        use embedded_graphics_core_04::geometry::{OriginDimensions, Size};
+        use embedded_graphics_core_04::pixelcolor::Rgb666;
+
+        let mut lcd = driver();
+        lcd.set_memory_access_control(0x28).unwrap();
+        assert_eq!(lcd.size(), Size::new(480, 320));
+        assert_eq!(lcd.with_color::<Rgb666>().size(), Size::new(480, 320));
+    }
+
+    /// Tests the `DrawTarget` of an embedded-graphics-core version on the emulator.
+    #[cfg(any(feature = "graphics-0-7", feature = "graphics-0-8"))]
+    macro_rules! draw_target_tests {
+        ($core:ident) => {
+            use super::driver;
+            use crate::color::PixelFormat;
+            use crate::emulator::Emulator;
+            use crate::ILI9486;
+            use $core::draw_target::DrawTarget;
+            use $core::geometry::{Point, Size};
+            use $core::pixelcolor::{Rgb565, Rgb666, RgbColor};
+            use $core::primitives::Rectangle;
+            use $core::Pixel;
+
+            const RED: (u8, u8, u8) = (0x3f, 0, 0);
+            const BLUE: (u8, u8, u8) = (0, 0, 0x3f);
+
+            fn drawn(lcd: &mut ILI9486<Emulator, u8>) -> usize {
+                let gram = lcd.writer().gram();
+                gram.iter().filter(|pixel| **pixel != (0, 0, 0)).count()
+            }
+
+            #[test]
+            fn draw_iter() {
+                let mut lcd = driver();
+                let pixels = [
+                    Pixel(Point::new(1, 2), Rgb565::RED),
+                    Pixel(Point::new(-1, 2), Rgb565::GREEN),
+                    Pixel(Point::new(319, 479), Rgb565::BLUE),
+                    Pixel(Point::new(320, 0), Rgb565::GREEN),
+                    Pixel(Point::new(0, 480), Rgb565::GREEN),
+                ];
+                lcd.draw_iter(pixels.iter().copied()).unwrap();
+
+                let emulator = lcd.writer();
+                assert_eq!(emulator.pixel(1, 2), RED);
+                assert_eq!(emulator.pixel(319, 479), BLUE);
+                assert_eq!(drawn(&mut lcd), 2);
+            }
+
+            #[test]
+            fn fill_contiguous() {
+                let mut lcd = driver();
+                lcd.set_color_mode(PixelFormat::Rgb666).unwrap();
+                let colors = (0..6).map(|i| Rgb666::new(i, 2 * i, 3 * i));
+                let area = Rectangle::new(Point::new(10, 20), Size::new(3, 2));
+                lcd.with_color::<Rgb666>()
+                    .fill_contiguous(&area, colors)
+                    .unwrap();
+
+                let emulator = lcd.writer();
+                assert_eq!(emulator.column_window(), (10, 12));
+                assert_eq!(emulator.page_window(), (20, 21));
+                assert_eq!(emulator.pixel(10, 20), (0, 0, 0));
+                assert_eq!(emulator.pixel(12, 20), (2, 4, 6));
+                assert_eq!(emulator.pixel(10, 21), (3, 6, 9));
+                assert_eq!(emulator.pixel(12, 21), (5, 10, 15));
+                assert_eq!(drawn(&mut lcd), 5);
+            }
+
+            #[test]
+            fn fill_contiguous_clipped() {
+                let mut lcd = driver();
+                lcd.set_color_mode(PixelFormat::Rgb666).unwrap();
+                // 3x3 pixels, the left column and the bottom row are off screen
+                let colors = (1..10).map(|i| Rgb666::new(i, 0, 0));
+                let area = Rectangle::new(Point::new(-1, 478), Size::new(3, 3));
+                lcd.with_color::<Rgb666>()
+                    .fill_contiguous(&area, colors)
+                    .unwrap();
+
+                let emulator = lcd.writer();
+                assert_eq!(emulator.pixel(0, 478), (2, 0, 0));
+                assert_eq!(emulator.pixel(1, 478), (3, 0, 0));
+                assert_eq!(emulator.pixel(0, 479), (5, 0, 0));
+                assert_eq!(emulator.pixel(1, 479), (6, 0, 0));
+                assert_eq!(drawn(&mut lcd), 4);
+            }
+
+            #[test]
+            fn fill_solid() {
+                let mut lcd = driver();
+                let area = Rectangle::new(Point::new(5, 6), Size::new(4, 3));
+                lcd.fill_solid(&area, Rgb565::RED).unwrap();
+                assert_eq!(lcd.writer().pixel(5, 6), RED);
+                assert_eq!(lcd.writer().pixel(8, 8), RED);
+                assert_eq!(drawn(&mut lcd), 12);
+
+                // clipped to the screen, or skipped when off screen
+                let mut lcd = driver();
+                let area = Rectangle::new(Point::new(318, -2), Size::new(4, 4));
+                lcd.fill_solid(&area, Rgb565::BLUE).unwrap();
+                let area = Rectangle::new(Point::new(-10, 0), Size::new(10, 10));
+                lcd.fill_solid(&area, Rgb565::BLUE).unwrap();
+                assert_eq!(lcd.writer().column_window(), (318, 319));
+                assert_eq!(lcd.writer().page_window(), (0, 1));
+                assert_eq!(lcd.writer().pixel(319, 1), BLUE);
+                assert_eq!(drawn(&mut lcd), 4);
+            }
+
+            #[test]
+            fn clear() {
+                let mut lcd = driver();
+                lcd.set_memory_access_control(0x20).unwrap();
+                lcd.clear(Rgb565::BLUE).unwrap();
+                let emulator = lcd.writer();
+                assert_eq!(emulator.column_window(), (0, 479));
+                assert_eq!(emulator.page_window(), (0, 319));
+                assert!(emulator.gram().iter().all(|pixel| *pixel == BLUE));
+            }
+        };
+    }
+
+    #[cfg(feature = "graphics-0-7")]
+    mod graphics_0_7 {
+        draw_target_tests!(embedded_graphics_core_03);
+    }
+
+    #[cfg(feature = "graphics-0-8")]
+    mod graphics_0_8 {
+        draw_target_tests!(embedded_graphics_core_04);
+    }
+}
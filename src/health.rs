@@ -0,0 +1,304 @@
+use crate::color::{PixelFormat, PixelWriter};
+use crate::dcs;
+use crate::{Command, ILI9486};
+use display_interface::v2::*;
+use display_interface::DisplayError;
+use embedded_hal::blocking::delay::DelayUs;
+
+/// Power mode bits that are compared: idle, partial, sleep, normal and display on/off.
+const POWER_MODE_MASK: u8 = 0b0111_1100;
+
+/// Power mode after a hardware or software reset: sleep in, normal display mode and display off.
+pub(crate) const POWER_MODE_RESET: u8 = 0b0000_1000;
+
+const IDLE_MODE_ON: u8 = 1 << 6;
+const PARTIAL_MODE_ON: u8 = 1 << 5;
+const SLEEP_OUT: u8 = 1 << 4;
+const NORMAL_MODE_ON: u8 = 1 << 3;
+const DISPLAY_ON: u8 = 1 << 2;
+
+/// The power mode the controller reports after `command`, when it reported `power_mode` before.
+pub(crate) fn expected_power_mode(power_mode: u8, command: Command) -> u8 {
+    match command {
+        Command::SoftReset => POWER_MODE_RESET,
+        Command::SleepIn => power_mode & !SLEEP_OUT,
+        Command::SleepOut => power_mode | SLEEP_OUT,
+        Command::PartialModeOn => (power_mode | PARTIAL_MODE_ON) & !NORMAL_MODE_ON,
+        Command::NormalDisplayMode => (power_mode | NORMAL_MODE_ON) & !PARTIAL_MODE_ON,
+        Command::DisplayOff => power_mode & !DISPLAY_ON,
+        Command::DisplayOn => power_mode | DISPLAY_ON,
+        Command::IdleModeOff => power_mode & !IDLE_MODE_ON,
+        Command::IdleModeOn => power_mode | IDLE_MODE_ON,
+        _ => power_mode,
+    }
+}
+
+/// MADCTL bits D1-D0 are unused and read back as zero.
+const MADCTL_MASK: u8 = 0b1111_1100;
+
+/// Checks the `ReadDisplayPixelFormat` response after switching to `color_mode`, returns
+/// `InvalidFormatError` if the controller reports a different format.
+pub(crate) fn verify_pixel_format(
+    response: Result<Option<PixelFormat>, DisplayError>,
+    color_mode: &PixelFormat,
+) -> Result<(), DisplayError> {
+    match response {
+        Ok(pixel_format) if !same_dbi_bits(pixel_format, color_mode) => {
+            Err(DisplayError::InvalidFormatError)
+        }
+        result => result.map(|_pixel_format| ()),
+    }
+}
+
+/// Whether the controller reports `pixel_format` for `color_mode`, the transfer mode of
+/// `Rgb666TwoTransfer` is not reported.
+fn same_dbi_bits(pixel_format: Option<PixelFormat>, color_mode: &PixelFormat) -> bool {
+    pixel_format.map(|pixel_format| pixel_format.dbi_bits()) == Some(color_mode.dbi_bits())
+}
+
+/// Status registers read back from the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayRegisters {
+    /// Result of `ReadDisplayPowerMode`
+    pub power_mode: u8,
+    /// Result of `ReadDisplayMADCTL`
+    pub madctl: u8,
+    /// Result of `ReadDisplayPixelFormat`
+    pub pixel_format: Option<PixelFormat>,
+}
+
+/// The result of [health_check](ILI9486::health_check).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Health {
+    /// The controller matches the driver's configuration.
+    Ok,
+    /// The controller had diverged and was re-initialized, the registers are the ones read before recovery.
+    Recovered(DisplayRegisters),
+    /// The interface is write-only, the controller was not checked.
+    Unchecked,
+}
+
+impl<RW, T> ILI9486<RW, T>
+where
+    RW: ReadWriteInterface<T> + PixelWriter<T>,
+    T: From<u8> + Into<u32> + Copy + Default,
+{
+    /// Reads the power mode, MADCTL and pixel format registers from the controller.
+    ///
+    /// Returns `BusReadError` if the interface is write-only, see
+    /// [write_only](ILI9486::write_only).
+    pub fn read_registers(&mut self) -> Result<DisplayRegisters, DisplayError> {
+        if !self.can_read {
+            return Err(DisplayError::BusReadError);
+        }
+        Ok(DisplayRegisters {
+            power_mode: self.query(&dcs::ReadDisplayPowerMode)?.0,
+            madctl: self.query(&dcs::ReadDisplayMADCTL)?.0,
+            pixel_format: self.query(&dcs::ReadDisplayPixelFormat)?,
+        })
+    }
+
+    /// Checks that the controller is still running with the configuration cached by the driver.
+    ///
+    /// Panels can reset themselves after an ESD event, which leaves the display blank while the
+    /// firmware keeps drawing. When the power mode, MADCTL or pixel format registers diverge from
+    /// what the driver has configured, the controller is re-initialized with [init](ILI9486::init),
+    /// the sleep, idle, partial and display off modes that were set are restored, and `redraw` is
+    /// called to restore the screen contents.
+    ///
+    /// The power mode, MADCTL and the pixel format are tracked when they are set through the
+    /// driver, including with [write_command](ILI9486::write_command), but not through
+    /// [writer](ILI9486::writer). Write-only interfaces can't read the
+    /// registers, the check is skipped and `Unchecked` returned.
+    ///
+    /// # Arguments
+    ///
+    /// `delay` - Delay used for the reset sequence
+    ///
+    /// `redraw` - Called after recovery to redraw the screen
+    ///
+    pub fn health_check(
+        &mut self,
+        delay: &mut dyn DelayUs<u32>,
+        redraw: &mut dyn FnMut(&mut Self) -> Result<(), DisplayError>,
+    ) -> Result<Health, DisplayError> {
+        if !self.can_read {
+            return Ok(Health::Unchecked);
+        }
+        let registers = self.read_registers()?;
+
+        if registers.power_mode & POWER_MODE_MASK == self.power_mode & POWER_MODE_MASK
+            && registers.madctl & MADCTL_MASK == self.madctl & MADCTL_MASK
+            && same_dbi_bits(registers.pixel_format, &self.color_mode)
+        {
+            return Ok(Health::Ok);
+        }
+
+        let power_mode = self.power_mode;
+        self.init(delay)?;
+        self.restore_power_mode(power_mode)?;
+        redraw(self)?;
+
+        Ok(Health::Recovered(registers))
+    }
+
+    /// Sends the commands that take the running controller left by [init](ILI9486::init) to
+    /// `power_mode`.
+    fn restore_power_mode(&mut self, power_mode: u8) -> Result<(), DisplayError> {
+        if power_mode & IDLE_MODE_ON != 0 {
+            self.send(&dcs::IdleModeOn)?;
+        }
+        if power_mode & PARTIAL_MODE_ON != 0 {
+            self.send(&dcs::PartialModeOn)?;
+        }
+        if power_mode & DISPLAY_ON == 0 {
+            self.send(&dcs::DisplayOff)?;
+        }
+        if power_mode & SLEEP_OUT == 0 {
+            self.send(&dcs::SleepIn)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::color::PixelFormat;
+    use crate::emulator::{Emulator, NoDelay, NoPin};
+    use crate::io::shim::OutputOnlyIoPin;
+    use crate::Command;
+
+    fn driver() -> ILI9486<Emulator, u8> {
+        let mut lcd = ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb565,
+            Emulator::new(),
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap();
+        lcd.init(&mut NoDelay).unwrap();
+        lcd
+    }
+
+    fn check(lcd: &mut ILI9486<Emulator, u8>) -> (Health, usize) {
+        let mut redraws = 0;
+        let health = lcd
+            .health_check(&mut NoDelay, &mut |_lcd| {
+                redraws += 1;
+                Ok(())
+            })
+            .unwrap();
+        (health, redraws)
+    }
+
+    #[test]
+    fn configured_with_write_command() {
+        let mut lcd = driver();
+        lcd.write_command(Command::MemoryAccessControl, &[0b1000_1000])
+            .unwrap();
+        lcd.write_command(Command::InterfacePixelFormat, &[0x66])
+            .unwrap();
+
+        assert_eq!(check(&mut lcd), (Health::Ok, 0));
+        assert_eq!(lcd.color_mode(), PixelFormat::Rgb666);
+    }
+
+    #[test]
+    fn recovers_after_reset() {
+        let mut lcd = driver();
+        lcd.set_memory_access_control(0b0100_1000).unwrap();
+        lcd.writer().reset();
+
+        let (health, redraws) = check(&mut lcd);
+        match health {
+            Health::Recovered(registers) => assert_eq!(registers.madctl, 0),
+            _ => panic!("the reset was not detected"),
+        }
+        assert_eq!(redraws, 1);
+        assert_eq!(lcd.writer().madctl(), 0b0100_1000);
+        assert_eq!(lcd.writer().pixel_format(), Some(PixelFormat::Rgb565));
+        assert!(lcd.writer().is_display_on());
+        assert_eq!(check(&mut lcd), (Health::Ok, 0));
+    }
+
+    #[test]
+    fn tracks_the_power_mode() {
+        let mut lcd = driver();
+        lcd.send(&dcs::IdleModeOn).unwrap();
+        lcd.write_command(Command::DisplayOff, &[]).unwrap();
+        assert_eq!(check(&mut lcd), (Health::Ok, 0));
+
+        // a command the driver doesn't see
+        lcd.writer()
+            .write(WriteMode::Command, &[Command::IdleModeOff as u8])
+            .unwrap();
+        assert!(matches!(check(&mut lcd).0, Health::Recovered(_)));
+        let registers = lcd.read_registers().unwrap();
+        assert_eq!(registers.power_mode & IDLE_MODE_ON, IDLE_MODE_ON);
+    }
+
+    #[test]
+    fn restores_the_power_mode() {
+        let mut lcd = driver();
+        lcd.send(&dcs::IdleModeOn).unwrap();
+        lcd.send(&dcs::PartialModeOn).unwrap();
+        lcd.write_command(Command::DisplayOff, &[]).unwrap();
+        let expected = lcd.read_registers().unwrap().power_mode;
+        lcd.writer().reset();
+
+        let (health, redraws) = check(&mut lcd);
+        match health {
+            Health::Recovered(registers) => {
+                assert_eq!(registers.power_mode & POWER_MODE_MASK, POWER_MODE_RESET)
+            }
+            _ => panic!("the reset was not detected"),
+        }
+        assert_eq!(redraws, 1);
+        assert_eq!(lcd.read_registers().unwrap().power_mode, expected);
+        assert!(!lcd.writer().is_display_on());
+        assert_eq!(check(&mut lcd), (Health::Ok, 0));
+    }
+
+    /// An emulator that can't read.
+    struct WriteOnly(Emulator);
+
+    impl WriteInterface<u8> for WriteOnly {
+        fn write_stream<'a>(
+            &mut self,
+            mode: WriteMode,
+            func: &mut dyn FnMut() -> Option<&'a u8>,
+        ) -> Result<(), DisplayError> {
+            self.0.write_stream(mode, func)
+        }
+    }
+
+    impl ReadInterface<u8> for WriteOnly {
+        fn read_stream(&mut self, _f: &mut dyn FnMut(u8) -> bool) -> Result<(), DisplayError> {
+            panic!("read from a write-only interface");
+        }
+    }
+
+    #[test]
+    fn write_only() {
+        let mut lcd = ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb565,
+            WriteOnly(Emulator::new()),
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap()
+        .write_only();
+        assert!(!lcd.can_read());
+        lcd.set_color_mode(PixelFormat::Rgb666).unwrap();
+        assert_eq!(lcd.color_mode(), PixelFormat::Rgb666);
+        assert_eq!(lcd.writer().0.pixel_format(), Some(PixelFormat::Rgb666));
+
+        assert!(matches!(
+            lcd.read_registers(),
+            Err(DisplayError::BusReadError)
+        ));
+        let health = lcd.health_check(&mut NoDelay, &mut |_lcd| panic!("redrawn"));
+        assert_eq!(health.unwrap(), Health::Unchecked);
+    }
+}
@@ -1,3 +1,6 @@
+use crate::gpio::ParallelPort;
+use core::convert::Infallible;
+
 pub trait InputPinType {}
 pub trait OutputPinType {}
 
@@ -185,3 +188,120 @@ stm32f1xx_io_pins!(
         PC15IOPin: (PC15, stm32f1xx_hal::gpio::gpioc::CRH),
     ]
 );
+
+/// CNF and MODE bits of a pin configured as push-pull output, 50 MHz
+const CR_OUTPUT: u64 = 0b0011;
+/// CNF and MODE bits of a pin configured as floating input
+const CR_INPUT: u64 = 0b0100;
+
+/// Sets the configuration of `count` pins starting at `shift` in CRL and CRH.
+fn configure_pins(
+    regs: &stm32f1xx_hal::pac::gpioa::RegisterBlock,
+    shift: u8,
+    count: u8,
+    config: u64,
+) {
+    let mut mask: u64 = 0;
+    let mut bits: u64 = 0;
+    for pin in shift..shift + count {
+        mask |= 0b1111 << (pin * 4);
+        bits |= config << (pin * 4);
+    }
+    if mask as u32 != 0 {
+        regs.crl
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(mask as u32)) | bits as u32) });
+    }
+    if (mask >> 32) as u32 != 0 {
+        regs.crh.modify(|r, w| unsafe {
+            w.bits((r.bits() & !((mask >> 32) as u32)) | (bits >> 32) as u32)
+        });
+    }
+}
+
+/// Eight consecutive pins of a GPIO port used as the data bus, written with a single BSRR store
+/// per byte.
+///
+/// The pins must not be used through the HAL at the same time, e.g. PB8..PB15:
+///
+/// ```ignore
+/// let port = Port8::new(unsafe { &*pac::GPIOB::ptr() }, 8);
+/// let parallel = ParallelInterface::new(port, cs, dcx, rdx, wrx)?;
+/// ```
+pub struct Port8<'a> {
+    regs: &'a stm32f1xx_hal::pac::gpioa::RegisterBlock,
+    shift: u8,
+}
+
+impl<'a> Port8<'a> {
+    /// Uses pins `shift..shift + 8` of the port, DB0 on pin `shift`.
+    pub fn new(regs: &'a stm32f1xx_hal::pac::gpioa::RegisterBlock, shift: u8) -> Port8<'a> {
+        assert!(shift <= 8);
+        Port8 { regs, shift }
+    }
+}
+
+impl<'a> ParallelPort<u8> for Port8<'a> {
+    type Error = Infallible;
+
+    fn set_output_mode(&mut self) -> Result<(), Infallible> {
+        configure_pins(self.regs, self.shift, 8, CR_OUTPUT);
+        Ok(())
+    }
+
+    fn set_input_mode(&mut self) -> Result<(), Infallible> {
+        configure_pins(self.regs, self.shift, 8, CR_INPUT);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn write(&mut self, byte: u8) -> Result<(), Infallible> {
+        // set the one bits and reset the zero bits at once
+        let set = (byte as u32) << self.shift;
+        let reset = (!byte as u32) << (self.shift + 16);
+        self.regs.bsrr.write(|w| unsafe { w.bits(set | reset) });
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn read(&mut self) -> Result<u8, Infallible> {
+        Ok((self.regs.idr.read().bits() >> self.shift) as u8)
+    }
+}
+
+/// All sixteen pins of a GPIO port used as the data bus, DB0 on pin 0.
+pub struct Port16<'a> {
+    regs: &'a stm32f1xx_hal::pac::gpioa::RegisterBlock,
+}
+
+impl<'a> Port16<'a> {
+    pub fn new(regs: &'a stm32f1xx_hal::pac::gpioa::RegisterBlock) -> Port16<'a> {
+        Port16 { regs }
+    }
+}
+
+impl<'a> ParallelPort<u16> for Port16<'a> {
+    type Error = Infallible;
+
+    fn set_output_mode(&mut self) -> Result<(), Infallible> {
+        configure_pins(self.regs, 0, 16, CR_OUTPUT);
+        Ok(())
+    }
+
+    fn set_input_mode(&mut self) -> Result<(), Infallible> {
+        configure_pins(self.regs, 0, 16, CR_INPUT);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn write(&mut self, word: u16) -> Result<(), Infallible> {
+        let set = word as u32;
+        let reset = (!word as u32) << 16;
+        self.regs.bsrr.write(|w| unsafe { w.bits(set | reset) });
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn read(&mut self) -> Result<u16, Infallible> {
+        Ok(self.regs.idr.read().bits() as u16)
+    }
+}
@@ -7,20 +7,17 @@
 //! with helpers in the [io](io) module.
 //!
 
+#[cfg(feature = "std")]
+extern crate std;
+
 use crate::color::PixelFormat;
 use crate::color::PixelWriter;
-use core::convert::Infallible;
+use crate::color::{PixelReader, RGBPixel};
+use core::convert::TryFrom;
 use core::marker::PhantomData;
 use display_interface::v2::*;
 use display_interface::DisplayError;
 
-use embedded_graphics::prelude::Pixel;
-use embedded_graphics::prelude::RgbColor;
-use embedded_graphics::prelude::Size;
-use embedded_graphics::primitives::Rectangle;
-use embedded_graphics::style::PrimitiveStyle;
-use embedded_graphics::style::Styled;
-use embedded_graphics::DrawTarget;
 use embedded_hal::digital::v2::OutputPin;
 
 /// This module provides I/O abstractions.
@@ -35,8 +32,49 @@ pub mod io;
 #[macro_use]
 pub mod gpio;
 
+/// This module provides the serial interfaces, 4-wire and 16-bit shift register boards on top of
+/// `embedded-hal` SPI and a bit-banged 3-wire interface.
+pub mod spi;
+
 pub mod color;
 
+/// This module provides strongly typed commands, see [send](ILI9486::send) and [query](ILI9486::query).
+pub mod dcs;
+
+/// This module decodes captured bus traffic into commands, e.g. `CASET 0..319`.
+pub mod decode;
+
+/// This module provides interfaces over word buffers.
+pub mod buffer;
+
+/// This module provides non-blocking frame memory writes, e.g. with DMA.
+pub mod dma;
+
+/// This module provides an async driver for async executors, requires the `async` feature.
+#[cfg(feature = "async")]
+pub mod asynch;
+
+/// This module records bus transactions and replays them into another interface.
+pub mod recording;
+
+/// This module counts bus traffic, e.g. redundant address window writes.
+pub mod stats;
+
+/// This module provides a host-side model of the controller, requires the `std` feature.
+#[cfg(feature = "std")]
+pub mod emulator;
+
+/// This module compares emulator renders against golden images, requires the `std` feature.
+#[cfg(feature = "std")]
+pub mod snapshot;
+
+/// This module imports sigrok captures of the 8-bit parallel bus, requires the `std` feature.
+#[cfg(feature = "std")]
+pub mod sigrok;
+
+/// This module provides programming of the NV memory (ID and VCOM values).
+pub mod nvm;
+
 use io::IoPin;
 
 use embedded_hal::blocking::delay::DelayUs;
@@ -48,61 +86,112 @@ where
 {
     rw_interface: RW,
     color_mode: PixelFormat,
+    madctl: u8,
+    /// The power mode the controller should report, see [health_check](ILI9486::health_check)
+    power_mode: u8,
+    window: AddressWindow,
+    /// Whether the interface can read from the controller
+    can_read: bool,
+    /// Whether a [write_area_dma](ILI9486::write_area_dma) transfer is running
+    dma_busy: bool,
     _marker: PhantomData<T>,
 }
 
+/// The address window last programmed into the controller and the position of its write pointer,
+/// `None` when unknown.
+#[derive(Clone, Copy, Default)]
+struct AddressWindow {
+    columns: Option<(u16, u16)>,
+    pages: Option<(u16, u16)>,
+    /// Where the next pixel sent with `MemoryWriteContinue` is written
+    pointer: Option<(u16, u16)>,
+}
+
+impl AddressWindow {
+    /// Forgets the state `command` changes, called before it is sent.
+    fn command(&mut self, command: Command) {
+        match command {
+            Command::MemoryWriteContinue => {}
+            Command::ColumnAddressSet => {
+                self.columns = None;
+                self.pointer = None;
+            }
+            Command::PageAddressSet => {
+                self.pages = None;
+                self.pointer = None;
+            }
+            Command::MemoryAccessControl | Command::SoftReset => *self = AddressWindow::default(),
+            _ => self.pointer = None,
+        }
+    }
+
+    /// Moves the write pointer past the pixel at (`x`, `y`).
+    fn advance(&mut self, x: u16, y: u16) {
+        self.pointer = match (self.columns, self.pages) {
+            (Some((start, end)), Some((_, last_page))) => {
+                if x < end {
+                    Some((x + 1, y))
+                } else if y < last_page {
+                    Some((start, y + 1))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+    }
+}
+
 mod commands;
 pub use commands::*;
 
+mod health;
+pub use health::*;
+
+mod graphics;
+#[cfg(any(feature = "graphics-0-7", feature = "graphics-0-8"))]
+pub use graphics::WithColor;
+
 impl<RW, T> Commands for ILI9486<RW, T>
 where
     RW: ReadWriteInterface<T> + PixelWriter<T>,
-    T: From<u8> + Default,
+    T: From<u8> + Copy + Default,
 {
     fn set_interface_pixel_format(
         &mut self,
         pixel_format: &PixelFormat,
     ) -> Result<(), DisplayError> {
-        match pixel_format {
-            PixelFormat::Rgb565 => self.write_command(0x3A, &[0b01010101.into()]),
-            PixelFormat::Rgb666 => self.write_command(0x3A, &[0b01100110.into()]),
-            _ => Err(DisplayError::InvalidFormatError),
-        }
+        self.send(&dcs::InterfacePixelFormat(*pixel_format))
     }
 
     fn column_address_set(&mut self, start: u16, end: u16) -> Result<(), DisplayError> {
-        self.write_command(
-            Command::ColumnAddressSet as u8,
-            &[
-                ((start >> 8) as u8).into(),
-                ((start & 0xff) as u8).into(),
-                ((end >> 8) as u8).into(),
-                ((end & 0xff) as u8).into(),
-            ],
-        )
+        if self.window.columns == Some((start, end)) {
+            return Ok(());
+        }
+        self.send(&dcs::ColumnAddressSet { start, end })?;
+        self.window.columns = Some((start, end));
+        Ok(())
     }
 
     fn page_address_set(&mut self, start: u16, end: u16) -> Result<(), DisplayError> {
-        self.write_command(
-            Command::PageAddressSet as u8,
-            &[
-                ((start >> 8) as u8).into(),
-                ((start & 0xff) as u8).into(),
-                ((end >> 8) as u8).into(),
-                ((end & 0xff) as u8).into(),
-            ],
-        )
+        if self.window.pages == Some((start, end)) {
+            return Ok(());
+        }
+        self.send(&dcs::PageAddressSet { start, end })?;
+        self.window.pages = Some((start, end));
+        Ok(())
     }
 
     fn clear_screen(&mut self) -> Result<(), DisplayError> {
-        self._draw_rect(0, 0, 320, 480, 0, 0, 0)
+        let (columns, pages) = self.dimensions();
+        self._draw_rect(0, 0, columns as u32, pages as u32, (0, 0, 0))
     }
 }
 
 impl<RW, T> ILI9486<RW, T>
 where
     RW: ReadWriteInterface<T> + PixelWriter<T>,
-    T: From<u8> + Default,
+    T: From<u8> + Copy + Default,
 {
     pub fn new<RST>(
         delay: &mut dyn DelayUs<u32>,
@@ -120,8 +209,13 @@ where
         delay.delay_us(120_000);
 
         let mut driver = ILI9486 {
-            rw_interface: rw_interface,
+            rw_interface,
             color_mode: PixelFormat::Rgb565,
+            madctl: 0,
+            power_mode: health::POWER_MODE_RESET,
+            window: AddressWindow::default(),
+            can_read: true,
+            dma_busy: false,
             _marker: PhantomData,
         };
 
@@ -131,14 +225,73 @@ where
         Ok(driver)
     }
 
+    /// Runs the power-on sequence: software reset and sleep out, followed by the pixel format and
+    /// memory access control settings cached by the driver, then turns the display on.
+    ///
+    /// This is also used by [health_check](ILI9486::health_check) to recover a controller that
+    /// has reset itself.
+    pub fn init(&mut self, delay: &mut dyn DelayUs<u32>) -> Result<(), DisplayError> {
+        self.send(&dcs::SoftReset)?;
+        delay.delay_us(120_000);
+        self.send(&dcs::SleepOut)?;
+        delay.delay_us(120_000);
+
+        let color_mode = self.color_mode;
+        self.set_interface_pixel_format(&color_mode)?;
+        let madctl = self.madctl;
+        self.set_memory_access_control(madctl)?;
+
+        self.send(&dcs::NormalDisplayMode)?;
+        self.send(&dcs::DisplayOn)
+    }
+
+    /// Sets MADCTL, the orientation and color order, and caches it for [init](ILI9486::init) and
+    /// [health_check](ILI9486::health_check).
+    pub fn set_memory_access_control(&mut self, madctl: u8) -> Result<(), DisplayError> {
+        self.send(&dcs::MemoryAccessControl(madctl))?;
+        self.madctl = madctl;
+        Ok(())
+    }
+
+    /// Draws a single pixel.
+    ///
+    /// The window is programmed from the pixel to the end of the screen, so a pixel at the
+    /// position the controller's write pointer moves to next is sent with `MemoryWriteContinue`
+    /// and no address commands. Unchanged `ColumnAddressSet` and `PageAddressSet` commands are
+    /// skipped.
+    ///
+    /// Formats that [pack](PixelWriter::packs_pixels) two pixels write the pixel twice, so the
+    /// window only covers the pixel.
     pub fn _draw_pixel(&mut self, x: u16, y: u16, r: u8, g: u8, b: u8) -> Result<(), DisplayError> {
-        self.column_address_set(x, x + 1)?;
-        self.page_address_set(y, y + 1)?;
+        if self.rw_interface.packs_pixels(&self.color_mode) {
+            self.column_address_set(x, x)?;
+            self.page_address_set(y, y)?;
+            self.send(&dcs::MemoryWrite)?;
+            return self
+                .rw_interface
+                .write_pixel_data(&self.color_mode, &(r, g, b), None);
+        }
 
+        if self.window.pointer == Some((x, y)) {
+            self.send(&dcs::MemoryWriteContinue)?;
+        } else {
+            let (columns, pages) = self.dimensions();
+            self.column_address_set(x, (columns - 1).max(x))?;
+            self.page_address_set(y, (pages - 1).max(y))?;
+            self.send(&dcs::MemoryWrite)?;
+        }
+
+        // the pointer is unknown if the write fails
+        self.window.pointer = None;
         self.rw_interface
-            .write(WriteMode::Command, &[0x2c.into()])?;
-        self.rw_interface
-            .write_pixel_data(&self.color_mode, &(r, g, b), None)
+            .write_pixel_data(&self.color_mode, &(r, g, b), None)?;
+        self.window.advance(x, y);
+        Ok(())
+    }
+
+    /// Number of columns and pages, swapped when rows and columns are exchanged in MADCTL.
+    fn dimensions(&self) -> (u16, u16) {
+        dcs::MemoryAccessControl(self.madctl).dimensions()
     }
 
     fn _draw_rect(
@@ -147,159 +300,431 @@ where
         y: u16,
         width: u32,
         height: u32,
-        r: u8,
-        g: u8,
-        b: u8,
+        color: RGBPixel,
     ) -> Result<(), DisplayError> {
         self.column_address_set(x, x + (width - 1) as u16)?;
         self.page_address_set(y, y + (height - 1) as u16)?;
 
         let n: usize = (width * height) as usize;
-        self.rw_interface
-            .write(WriteMode::Command, &[0x2c.into()])?;
+        self.send(&dcs::MemoryWrite)?;
 
         self.rw_interface
-            .write_repeated_pixel_data(&self.color_mode, &(r, g, b), n)
+            .write_repeated_pixel_data(&self.color_mode, &color, n)
     }
 
+    /// Programs the address window of an area and sends `command`, `MemoryWrite` or
+    /// `MemoryRead`.
+    fn start_area<C>(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        command: &C,
+    ) -> Result<(), DisplayError>
+    where
+        C: dcs::DcsCommand,
+    {
+        let (columns, pages) = dcs::address_window(x, y, width, height);
+        self.column_address_set(columns.start, columns.end)?;
+        self.page_address_set(pages.start, pages.end)?;
+        self.send(command)
+    }
+
+    /// Marks the interface as write-only, e.g. a
+    /// [ShiftRegisterInterface](spi::ShiftRegisterInterface), so
+    /// [set_color_mode](ILI9486::set_color_mode) uses the pixel format without reading it back and
+    /// [health_check](ILI9486::health_check) reports [Unchecked](Health::Unchecked).
+    ///
+    /// ```ignore
+    /// let lcd = ILI9486::new(&mut delay, PixelFormat::Rgb565, interface, rst)?.write_only();
+    /// ```
+    pub fn write_only(mut self) -> Self {
+        self.can_read = false;
+        self
+    }
+
+    /// Whether the interface can read from the controller, see
+    /// [write_only](ILI9486::write_only).
+    pub fn can_read(&self) -> bool {
+        self.can_read
+    }
+
+    /// The pixel format used for drawing.
+    pub fn color_mode(&self) -> PixelFormat {
+        self.color_mode
+    }
+
+    /// Returns `BusWriteError` while a [write_area_dma](ILI9486::write_area_dma) transfer is
+    /// running, the interface can't be written until it is done.
+    fn check_idle(&self) -> Result<(), DisplayError> {
+        if self.dma_busy {
+            return Err(DisplayError::BusWriteError);
+        }
+        Ok(())
+    }
+
+    /// The interface, e.g. to read the counters of a [Counting](stats::Counting) interface. The
+    /// address window is kept.
+    pub fn interface(&self) -> &RW {
+        &self.rw_interface
+    }
+
+    /// The interface, e.g. to send commands the driver doesn't know.
+    ///
+    /// The driver forgets the address window it has programmed, as commands sent through the
+    /// interface may change it. Nothing may be written while a
+    /// [write_area_dma](ILI9486::write_area_dma) transfer is running.
     pub fn writer(&mut self) -> &mut RW {
+        self.window = AddressWindow::default();
         &mut self.rw_interface
     }
 
+    /// The interface, see [writer](ILI9486::writer).
     pub fn reader(&mut self) -> &mut RW {
+        self.window = AddressWindow::default();
         &mut self.rw_interface
     }
 
-    /// Sends a write command to the device, streaming data from `data_provider` to the device.
+    /// Sends a read command to the device.
     ///
     /// # Arguments
     ///
     /// `command` - The command to send
     ///
-    /// `data_provider` - Function that provides data to be sent. `None` should be returned from the function when transmission is complete.
+    /// `output` - `output.len()` bytes will be read from the device, starting with the dummy
+    /// reads, see [dummy_reads](Command::dummy_reads).
     ///
-    pub fn write_command<C>(&mut self, command: C, data: &[T]) -> Result<(), DisplayError>
+    pub fn read_command<C>(&mut self, command: C, output: &mut [T]) -> Result<(), DisplayError>
     where
         C: Into<T>,
     {
+        self.check_idle()?;
+        self.window = AddressWindow::default();
         self.rw_interface
-            .write(WriteMode::Command, &mut [command.into()])?;
-        self.rw_interface.write(WriteMode::Data, data)
+            .write(WriteMode::Command, &[command.into()])?;
+        self.rw_interface.read(output)
     }
 
-    /// Sends a read command to the device.
+    /// Sends a write command to the device, streaming data from `data_provider` to the device.
     ///
     /// # Arguments
     ///
     /// `command` - The command to send
     ///
-    /// `output` - `output.len()` bytes will be read from the device.
+    /// `data_provider` - Function that provides data to be sent. `None` should be returned from the function when transmission is complete.
     ///
-    pub fn read_command<C>(&mut self, command: C, output: &mut [T]) -> Result<(), DisplayError>
+    /// MADCTL, the pixel format and the power mode are cached like with
+    /// [set_memory_access_control](ILI9486::set_memory_access_control), if the interface knows
+    /// the [value](PixelWriter::word_value) of its words.
+    ///
+    pub fn write_command<C>(&mut self, command: C, data: &[T]) -> Result<(), DisplayError>
     where
         C: Into<T>,
     {
-        self.rw_interface
-            .write(WriteMode::Command, &mut [command.into()])?;
-        self.rw_interface.read(output)
+        self.check_idle()?;
+        let command = command.into();
+        // the command is not known, it may change the address window
+        self.window = AddressWindow::default();
+        self.rw_interface.write(WriteMode::Command, &[command])?;
+        self.rw_interface.write(WriteMode::Data, data)?;
+        self.track_settings(command, data);
+        Ok(())
+    }
+
+    /// Caches MADCTL, the pixel format and the power mode when they are sent with
+    /// [write_command](ILI9486::write_command), so [init](ILI9486::init) and
+    /// [health_check](ILI9486::health_check) restore them.
+    fn track_settings(&mut self, command: T, data: &[T]) {
+        let command = match self.rw_interface.word_value(&command) {
+            Some(opcode) => match Command::try_from(opcode as u8) {
+                Ok(command) => command,
+                Err(_) => return,
+            },
+            None => return,
+        };
+        self.power_mode = health::expected_power_mode(self.power_mode, command);
+        let parameter = match data
+            .first()
+            .and_then(|word| self.rw_interface.word_value(word))
+        {
+            Some(parameter) => parameter as u8,
+            None => return,
+        };
+        match command {
+            Command::MemoryAccessControl => self.madctl = parameter,
+            Command::InterfacePixelFormat => {
+                if let Some(color_mode) = PixelFormat::from_dbi_bits(parameter) {
+                    self.color_mode = color_mode;
+                }
+            }
+            _ => {}
+        }
     }
 }
 
-impl<RW, RGBC, T> DrawTarget<RGBC> for ILI9486<RW, T>
+impl<RW, T> ILI9486<RW, T>
 where
-    RGBC: RgbColor,
     RW: ReadWriteInterface<T> + PixelWriter<T>,
-    T: From<u8> + Default,
+    T: From<u8> + Into<u32> + Copy + Default,
 {
-    type Error = Infallible;
+    /// Switches the pixel format, e.g. to `Rgb565` for fast animation and `Rgb666` for photos.
+    ///
+    /// The format is read back with `ReadDisplayPixelFormat` and only used for drawing once the
+    /// controller has confirmed it. Write-only interfaces, see [write_only](ILI9486::write_only),
+    /// can't confirm it and the format is used as sent.
+    ///
+    /// Returns `DataFormatNotImplemented` if the interface can't write pixels in `color_mode`,
+    /// see [PixelFormat](PixelFormat), and `InvalidFormatError` if the controller reports a
+    /// different format.
+    pub fn set_color_mode(&mut self, color_mode: PixelFormat) -> Result<(), DisplayError> {
+        if !self.rw_interface.supports_pixel_format(&color_mode) {
+            return Err(DisplayError::DataFormatNotImplemented);
+        }
+
+        self.set_interface_pixel_format(&color_mode)?;
+        // write-only interfaces use the format as sent
+        if self.can_read {
+            let response = self.query(&dcs::ReadDisplayPixelFormat);
+            if let Err(error) = health::verify_pixel_format(response, &color_mode) {
+                // pixels keep being encoded in the current color mode
+                let previous = self.color_mode;
+                self.set_interface_pixel_format(&previous)?;
+                return Err(error);
+            }
+        }
 
-    fn draw_rectangle(
+        self.color_mode = color_mode;
+        Ok(())
+    }
+}
+
+impl<RW, T> ILI9486<RW, T>
+where
+    RW: ReadWriteInterface<T> + PixelWriter<T> + PixelReader<T>,
+    T: From<u8> + Copy + Default,
+{
+    /// Reads the pixels of an area from the frame memory, row by row, into `pixels`.
+    ///
+    /// At most `width * height` pixels are read. The controller returns RGB666, which is
+    /// converted to the components of the [color mode](ILI9486::color_mode), so a pixel reads
+    /// back as it was drawn.
+    pub fn read_pixels(
         &mut self,
-        item: &Styled<Rectangle, PrimitiveStyle<RGBC>>,
-    ) -> Result<(), Self::Error> {
-        let color = item.style.fill_color.unwrap_or(RgbColor::BLACK);
-        self._draw_rect(
-            item.primitive.top_left.x as u16,
-            item.primitive.top_left.y as u16,
-            (item.primitive.bottom_right.x - item.primitive.top_left.x) as u32,
-            (item.primitive.bottom_right.y - item.primitive.top_left.y) as u32,
-            color.r(),
-            color.g(),
-            color.b(),
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        pixels: &mut [RGBPixel],
+    ) -> Result<(), DisplayError> {
+        let len = pixels.len().min(width as usize * height as usize);
+        let pixels = &mut pixels[..len];
+        if pixels.is_empty() {
+            return Ok(());
+        }
+
+        self.start_area(x, y, width, height, &dcs::MemoryRead)?;
+        self.rw_interface
+            .read_pixel_data(Command::MemoryRead.dummy_reads(), pixels)?;
+
+        let max = PixelFormat::Rgb666.max_components();
+        for pixel in pixels.iter_mut() {
+            *pixel = self.color_mode.convert(pixel, &max);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::emulator::{Emulator, NoDelay, NoPin};
+    use crate::io::shim::OutputOnlyIoPin;
+
+    fn driver(color_mode: PixelFormat) -> ILI9486<Emulator, u8> {
+        let mut lcd = ILI9486::new(
+            &mut NoDelay,
+            color_mode,
+            Emulator::new(),
+            OutputOnlyIoPin::new(NoPin),
         )
         .unwrap();
+        lcd.init(&mut NoDelay).unwrap();
+        lcd
+    }
 
-        match item.style.stroke_color {
-            Some(stroke_color) => {
-                self._draw_rect(
-                    item.primitive.top_left.x as u16,
-                    item.primitive.top_left.y as u16,
-                    (item.primitive.bottom_right.x - item.primitive.top_left.x) as u32,
-                    item.style.stroke_width,
-                    stroke_color.r(),
-                    stroke_color.g(),
-                    stroke_color.b(),
-                )
-                .unwrap();
-
-                self._draw_rect(
-                    item.primitive.top_left.x as u16,
-                    (item.primitive.top_left.y as u32 + item.style.stroke_width) as u16,
-                    item.style.stroke_width,
-                    (item.primitive.bottom_right.y
-                        - item.primitive.top_left.y
-                        - item.style.stroke_width as i32) as u32,
-                    stroke_color.r(),
-                    stroke_color.g(),
-                    stroke_color.b(),
-                )
-                .unwrap();
-
-                self._draw_rect(
-                    (item.primitive.bottom_right.x as u32 - item.style.stroke_width) as u16,
-                    (item.primitive.top_left.y as u32 + item.style.stroke_width) as u16,
-                    item.style.stroke_width,
-                    (item.primitive.bottom_right.y
-                        - item.primitive.top_left.y
-                        - item.style.stroke_width as i32) as u32,
-                    stroke_color.r(),
-                    stroke_color.g(),
-                    stroke_color.b(),
-                )
-                .unwrap();
-
-                self._draw_rect(
-                    (item.primitive.top_left.x as u32 + item.style.stroke_width) as u16,
-                    (item.primitive.bottom_right.y as u32 - item.style.stroke_width) as u16,
-                    (item.primitive.bottom_right.x - item.primitive.top_left.x) as u32
-                        - item.style.stroke_width * 2,
-                    item.style.stroke_width,
-                    stroke_color.r(),
-                    stroke_color.g(),
-                    stroke_color.b(),
-                )
-                .unwrap();
+    #[test]
+    fn draws_rects() {
+        let mut lcd = driver(PixelFormat::Rgb565);
+        lcd._draw_rect(10, 20, 3, 2, (31, 0, 0)).unwrap();
+
+        let emulator = lcd.writer();
+        assert_eq!(emulator.column_window(), (10, 12));
+        assert_eq!(emulator.page_window(), (20, 21));
+        for y in 19..23 {
+            for x in 9..14 {
+                let expected = if (10..13).contains(&x) && (20..22).contains(&y) {
+                    (0x3f, 0, 0)
+                } else {
+                    (0, 0, 0)
+                };
+                assert_eq!(emulator.pixel(x, y), expected, "({}, {})", x, y);
             }
-            None => {}
-        };
-        Ok(())
+        }
+
+        lcd.clear_screen().unwrap();
+        assert!(lcd.writer().gram().iter().all(|pixel| *pixel == (0, 0, 0)));
+    }
+
+    #[test]
+    fn clear_rotated_screen() {
+        let mut lcd = driver(PixelFormat::Rgb565);
+        lcd._draw_rect(0, 0, 320, 480, (31, 63, 31)).unwrap();
+        lcd.set_memory_access_control(0x28).unwrap();
+        lcd.clear_screen().unwrap();
+        assert_eq!(lcd.writer().column_window(), (0, 479));
+        assert_eq!(lcd.writer().page_window(), (0, 319));
+        assert!(lcd.writer().gram().iter().all(|pixel| *pixel == (0, 0, 0)));
     }
 
-    fn draw_pixel(&mut self, item: Pixel<RGBC>) -> Result<(), Self::Error> {
-        self._draw_pixel(
-            item.0.x as u16,
-            item.0.y as u16,
-            item.1.r(),
-            item.1.g(),
-            item.1.b(),
+    #[test]
+    fn draws_pixels() {
+        let mut lcd = driver(PixelFormat::Rgb666);
+        lcd._draw_pixel(5, 7, 0x3f, 0, 0).unwrap();
+        assert_eq!(lcd.writer().column_window(), (5, 319));
+        assert_eq!(lcd.writer().page_window(), (7, 479));
+
+        // the next pixel along the row continues the write
+        lcd._draw_pixel(6, 7, 0, 0x3f, 0).unwrap();
+        lcd._draw_pixel(319, 7, 0, 0, 0x3f).unwrap();
+        lcd._draw_pixel(0, 8, 1, 2, 3).unwrap();
+
+        let emulator = lcd.writer();
+        assert_eq!(emulator.pixel(5, 7), (0x3f, 0, 0));
+        assert_eq!(emulator.pixel(6, 7), (0, 0x3f, 0));
+        assert_eq!(emulator.pixel(7, 7), (0, 0, 0));
+        assert_eq!(emulator.pixel(319, 7), (0, 0, 0x3f));
+        assert_eq!(emulator.pixel(0, 8), (1, 2, 3));
+        assert_eq!(emulator.column_window(), (0, 319));
+        assert_eq!(emulator.page_window(), (8, 479));
+    }
+
+    #[test]
+    fn window_changed_through_the_interface() {
+        let mut lcd = driver(PixelFormat::Rgb565);
+        lcd._draw_pixel(5, 7, 31, 0, 0).unwrap();
+
+        // the pointer moves to (6, 7), then the window is changed behind the driver's back
+        lcd.writer()
+            .write(WriteMode::Command, &[Command::ColumnAddressSet as u8])
+            .unwrap();
+        lcd.writer()
+            .write(WriteMode::Data, &[0u8, 100, 0, 200])
+            .unwrap();
+        lcd._draw_pixel(6, 7, 0, 63, 0).unwrap();
+        assert_eq!(lcd.writer().column_window(), (6, 319));
+        assert_eq!(lcd.writer().pixel(6, 7), (0, 0x3f, 0));
+        assert_eq!(lcd.writer().pixel(100, 7), (0, 0, 0));
+
+        lcd.reader()
+            .write(WriteMode::Command, &[Command::PageAddressSet as u8])
+            .unwrap();
+        lcd.reader()
+            .write(WriteMode::Data, &[0u8, 100, 0, 200])
+            .unwrap();
+        lcd._draw_pixel(7, 7, 0, 0, 31).unwrap();
+        assert_eq!(lcd.writer().page_window(), (7, 479));
+        assert_eq!(lcd.writer().pixel(7, 7), (0, 0, 0x3f));
+        assert_eq!(lcd.writer().pixel(7, 100), (0, 0, 0));
+    }
+
+    #[test]
+    fn memory_access_control_and_pixel_format() {
+        let mut lcd = driver(PixelFormat::Rgb565);
+        assert_eq!(lcd.writer().pixel_format(), Some(PixelFormat::Rgb565));
+        assert!(lcd.writer().is_display_on());
+
+        // rows and columns exchanged
+        lcd.set_memory_access_control(0x28).unwrap();
+        assert_eq!(lcd.writer().madctl(), 0x28);
+        lcd._draw_pixel(479, 0, 31, 0, 0).unwrap();
+        assert_eq!(lcd.writer().column_window(), (479, 479));
+        assert_eq!(lcd.writer().pixel(0, 479), (0x3f, 0, 0));
+
+        lcd.set_color_mode(PixelFormat::Rgb666).unwrap();
+        assert_eq!(lcd.color_mode(), PixelFormat::Rgb666);
+        assert_eq!(lcd.writer().pixel_format(), Some(PixelFormat::Rgb666));
+
+        // both are restored after a reset
+        lcd.writer().reset();
+        lcd.init(&mut NoDelay).unwrap();
+        assert_eq!(lcd.writer().madctl(), 0x28);
+        assert_eq!(lcd.writer().pixel_format(), Some(PixelFormat::Rgb666));
+
+        // including when they are sent as raw commands
+        lcd.write_command(Command::MemoryAccessControl, &[0x48])
+            .unwrap();
+        lcd.write_command(Command::InterfacePixelFormat, &[0x55])
+            .unwrap();
+        assert_eq!(lcd.color_mode(), PixelFormat::Rgb565);
+        lcd.writer().reset();
+        lcd.init(&mut NoDelay).unwrap();
+        assert_eq!(lcd.writer().madctl(), 0x48);
+        assert_eq!(lcd.writer().pixel_format(), Some(PixelFormat::Rgb565));
+    }
+
+    /// An emulator whose reads all return 0.
+    struct ZeroReads(Emulator);
+
+    impl WriteInterface<u8> for ZeroReads {
+        fn write_stream<'a>(
+            &mut self,
+            mode: WriteMode,
+            func: &mut dyn FnMut() -> Option<&'a u8>,
+        ) -> Result<(), DisplayError> {
+            self.0.write_stream(mode, func)
+        }
+    }
+
+    impl ReadInterface<u8> for ZeroReads {
+        fn read_stream(&mut self, f: &mut dyn FnMut(u8) -> bool) -> Result<(), DisplayError> {
+            while f(0) {}
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn color_mode_restored_on_mismatch() {
+        let mut lcd = ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb565,
+            ZeroReads(Emulator::new()),
+            OutputOnlyIoPin::new(NoPin),
         )
         .unwrap();
-        Ok(())
+        assert!(matches!(
+            lcd.set_color_mode(PixelFormat::Rgb666),
+            Err(DisplayError::InvalidFormatError)
+        ));
+        assert_eq!(lcd.color_mode(), PixelFormat::Rgb565);
+        assert_eq!(lcd.writer().0.pixel_format(), Some(PixelFormat::Rgb565));
     }
-    fn size(&self) -> Size {
-        Size::new(320, 480)
+
+    #[test]
+    fn reads_pixels() {
+        for color_mode in [PixelFormat::Rgb565, PixelFormat::Rgb666].iter() {
+            let mut lcd = driver(*color_mode);
+            let drawn = [(31, 0, 1), (0, 63, 2), (3, 4, 5), (16, 32, 16)];
+            for (i, (r, g, b)) in drawn.iter().enumerate() {
+                lcd._draw_pixel(100 + (i as u16 % 2), 200 + (i as u16 / 2), *r, *g, *b)
+                    .unwrap();
+            }
+
+            let mut pixels = [(0, 0, 0); 5];
+            lcd.read_pixels(100, 200, 2, 2, &mut pixels).unwrap();
+            assert_eq!(pixels[..4], drawn);
+            // only the area is read
+            assert_eq!(pixels[4], (0, 0, 0));
+        }
     }
 }
-
-#[cfg(test)]
-mod tests {}
@@ -0,0 +1,397 @@
+use crate::color::PixelWriter;
+use crate::dcs;
+use crate::ILI9486;
+use display_interface::v2::*;
+use display_interface::DisplayError;
+use embedded_hal::blocking::delay::DelayUs;
+
+/// Each NV memory location can only be programmed this many times.
+pub const MAX_WRITES: u8 = 3;
+
+const POLL_INTERVAL_US: u32 = 1_000;
+const POLL_ATTEMPTS: u32 = 500;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The underlying bus returned an error
+    Display(DisplayError),
+    /// The target has already been programmed [MAX_WRITES](MAX_WRITES) times
+    NoWritesRemaining,
+    /// The controller was still busy after programming
+    Timeout,
+}
+
+impl From<DisplayError> for Error {
+    fn from(e: DisplayError) -> Error {
+        Error::Display(e)
+    }
+}
+
+/// A value stored in NV memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NvmTarget {
+    Id1,
+    Id2,
+    Id3,
+    Vcom,
+}
+
+impl NvmTarget {
+    /// `PGM_ADR` parameter of `NVMemoryWrite`
+    pub(crate) fn address(&self) -> u8 {
+        match self {
+            NvmTarget::Id1 => 0b000,
+            NvmTarget::Id2 => 0b001,
+            NvmTarget::Id3 => 0b010,
+            NvmTarget::Vcom => 0b100,
+        }
+    }
+}
+
+/// Result of `NVMemoryStatusRead`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NvmStatus {
+    /// Programming is in progress
+    pub busy: bool,
+    pub id1_count: u8,
+    pub id2_count: u8,
+    pub id3_count: u8,
+    pub vcom_count: u8,
+}
+
+impl NvmStatus {
+    pub(crate) fn from_registers(first: u8, second: u8) -> NvmStatus {
+        // the counters are thermometer coded, 0b000, 0b001, 0b011, 0b111
+        NvmStatus {
+            busy: second & 0b1000_0000 != 0,
+            id1_count: (first & 0b111).count_ones() as u8,
+            id2_count: ((first >> 4) & 0b111).count_ones() as u8,
+            id3_count: (second & 0b111).count_ones() as u8,
+            vcom_count: ((second >> 4) & 0b111).count_ones() as u8,
+        }
+    }
+
+    /// Number of times `target` has been programmed.
+    pub fn write_count(&self, target: NvmTarget) -> u8 {
+        match target {
+            NvmTarget::Id1 => self.id1_count,
+            NvmTarget::Id2 => self.id2_count,
+            NvmTarget::Id3 => self.id3_count,
+            NvmTarget::Vcom => self.vcom_count,
+        }
+    }
+
+    /// Number of times `target` can still be programmed.
+    pub fn remaining_writes(&self, target: NvmTarget) -> u8 {
+        MAX_WRITES.saturating_sub(self.write_count(target))
+    }
+}
+
+/// Outcome of [NvmProgrammer::write](NvmProgrammer::write).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NvmWrite {
+    pub target: NvmTarget,
+    pub value: u8,
+    /// Nothing was sent to the controller
+    pub dry_run: bool,
+    /// Writes left for `target` after this one
+    pub remaining_writes: u8,
+}
+
+/// Programs the ID and VCOM values into the controller's NV memory.
+///
+/// NV memory can only be written a few times and writes can not be undone, so a programmer
+/// starts out in dry-run mode: the status register is read and checked, but nothing is
+/// programmed until [arm](NvmProgrammer::arm) is called.
+pub struct NvmProgrammer<'a, RW, T>
+where
+    RW: ReadWriteInterface<T> + PixelWriter<T>,
+{
+    driver: &'a mut ILI9486<RW, T>,
+    armed: bool,
+}
+
+impl<'a, RW, T> NvmProgrammer<'a, RW, T>
+where
+    RW: ReadWriteInterface<T> + PixelWriter<T>,
+    T: From<u8> + Into<u32> + Copy + Default,
+{
+    /// Creates a programmer in dry-run mode.
+    pub fn new(driver: &'a mut ILI9486<RW, T>) -> NvmProgrammer<'a, RW, T> {
+        NvmProgrammer {
+            driver,
+            armed: false,
+        }
+    }
+
+    /// Leaves dry-run mode, subsequent writes program the NV memory.
+    ///
+    /// # Safety
+    ///
+    /// This does not cause undefined behavior, but every write permanently uses up one of the
+    /// [MAX_WRITES](MAX_WRITES) programming cycles of the panel. Wrong VCOM values can not be
+    /// corrected once all cycles are used.
+    pub unsafe fn arm(&mut self) {
+        self.armed = true;
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        !self.armed
+    }
+
+    /// Reads the NV memory status register.
+    pub fn status(&mut self) -> Result<NvmStatus, Error> {
+        Ok(self.driver.query(&dcs::NVMemoryStatusRead)?)
+    }
+
+    /// Programs `value` into `target`, waiting for the controller to finish.
+    ///
+    /// # Arguments
+    ///
+    /// `target` - The NV memory location
+    ///
+    /// `value` - The value to program
+    ///
+    /// `delay` - Delay used while polling the status register
+    ///
+    pub fn write(
+        &mut self,
+        target: NvmTarget,
+        value: u8,
+        delay: &mut dyn DelayUs<u32>,
+    ) -> Result<NvmWrite, Error> {
+        let remaining = self.status()?.remaining_writes(target);
+        if remaining == 0 {
+            return Err(Error::NoWritesRemaining);
+        }
+
+        if !self.armed {
+            return Ok(NvmWrite {
+                target,
+                value,
+                dry_run: true,
+                remaining_writes: remaining - 1,
+            });
+        }
+
+        self.driver.send(&dcs::NVMemoryWrite { target, value })?;
+        self.driver.send(&dcs::NVMemoryProtectionKey)?;
+
+        for _ in 0..POLL_ATTEMPTS {
+            delay.delay_us(POLL_INTERVAL_US);
+            let status = self.status()?;
+            if !status.busy {
+                return Ok(NvmWrite {
+                    target,
+                    value,
+                    dry_run: false,
+                    remaining_writes: status.remaining_writes(target),
+                });
+            }
+        }
+
+        Err(Error::Timeout)
+    }
+
+    /// Programs the three ID bytes, see `ReadDisplayId`.
+    pub fn write_id(
+        &mut self,
+        id: &[u8; 3],
+        delay: &mut dyn DelayUs<u32>,
+    ) -> Result<[NvmWrite; 3], Error> {
+        Ok([
+            self.write(NvmTarget::Id1, id[0], delay)?,
+            self.write(NvmTarget::Id2, id[1], delay)?,
+            self.write(NvmTarget::Id3, id[2], delay)?,
+        ])
+    }
+
+    /// Programs the VCOM offset.
+    pub fn write_vcom(
+        &mut self,
+        vcom: u8,
+        delay: &mut dyn DelayUs<u32>,
+    ) -> Result<NvmWrite, Error> {
+        self.write(NvmTarget::Vcom, vcom, delay)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::color::PixelFormat;
+    use crate::emulator::{NoDelay, NoPin};
+    use crate::io::shim::OutputOnlyIoPin;
+    use std::collections::VecDeque;
+    use std::vec::Vec;
+
+    /// The NV memory of a controller, busy for `busy_polls` status reads after programming.
+    #[derive(Default)]
+    struct Model {
+        counts: [u8; 4],
+        busy_polls: usize,
+        busy: usize,
+        command: u8,
+        parameters: Vec<u8>,
+        pending: Option<(u8, u8)>,
+        programmed: Vec<(u8, u8)>,
+        response: VecDeque<u8>,
+    }
+
+    impl Model {
+        fn status(&mut self) -> [u8; 2] {
+            let thermometer = |count: u8| (1u8 << count) - 1;
+            let busy = if self.busy > 0 {
+                self.busy -= 1;
+                0b1000_0000
+            } else {
+                0
+            };
+            [
+                thermometer(self.counts[0]) | (thermometer(self.counts[1]) << 4),
+                thermometer(self.counts[2]) | (thermometer(self.counts[3]) << 4) | busy,
+            ]
+        }
+
+        fn parameter(&mut self, byte: u8) {
+            self.parameters.push(byte);
+            match (self.command, self.parameters.as_slice()) {
+                (0xd0, &[address, value]) => self.pending = Some((address, value)),
+                (0xd1, &[0x55, 0xaa, 0x66]) => {
+                    if let Some((address, value)) = self.pending.take() {
+                        let index = if address == 0b100 {
+                            3
+                        } else {
+                            address as usize
+                        };
+                        self.counts[index] += 1;
+                        self.programmed.push((address, value));
+                        self.busy = self.busy_polls;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    impl WriteInterface<u8> for Model {
+        fn write_stream<'a>(
+            &mut self,
+            mode: WriteMode,
+            func: &mut dyn FnMut() -> Option<&'a u8>,
+        ) -> Result<(), DisplayError> {
+            while let Some(word) = func() {
+                match mode {
+                    WriteMode::Command => {
+                        self.command = *word;
+                        self.parameters.clear();
+                        self.response.clear();
+                        if *word == 0xd2 {
+                            let status = self.status();
+                            self.response.extend(&[0, status[0], status[1]]);
+                        }
+                    }
+                    _ => self.parameter(*word),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl ReadInterface<u8> for Model {
+        fn read_stream(&mut self, f: &mut dyn FnMut(u8) -> bool) -> Result<(), DisplayError> {
+            while f(self.response.pop_front().unwrap_or(0)) {}
+            Ok(())
+        }
+    }
+
+    fn driver(model: Model) -> ILI9486<Model, u8> {
+        ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb565,
+            model,
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn status_counters() {
+        let status = NvmStatus::from_registers(0b0011_0001, 0b1111_0000);
+        assert!(status.busy);
+        assert_eq!(status.write_count(NvmTarget::Id1), 1);
+        assert_eq!(status.write_count(NvmTarget::Id2), 2);
+        assert_eq!(status.write_count(NvmTarget::Id3), 0);
+        assert_eq!(status.remaining_writes(NvmTarget::Vcom), 0);
+    }
+
+    #[test]
+    fn dry_run() {
+        let mut lcd = driver(Model {
+            counts: [0, 0, 0, 1],
+            ..Model::default()
+        });
+        let mut programmer = NvmProgrammer::new(&mut lcd);
+        assert!(programmer.is_dry_run());
+
+        let write = programmer.write_vcom(0x1f, &mut NoDelay).unwrap();
+        assert!(write.dry_run);
+        assert_eq!(write.remaining_writes, 1);
+        assert_eq!(lcd.writer().programmed, []);
+    }
+
+    #[test]
+    fn program_and_poll() {
+        let mut lcd = driver(Model {
+            busy_polls: 3,
+            ..Model::default()
+        });
+        let mut programmer = NvmProgrammer::new(&mut lcd);
+        unsafe { programmer.arm() };
+
+        let writes = programmer
+            .write_id(&[0x54, 0x80, 0x66], &mut NoDelay)
+            .unwrap();
+        assert_eq!(writes[2].target, NvmTarget::Id3);
+        assert!(writes.iter().all(|write| !write.dry_run));
+        assert!(writes.iter().all(|write| write.remaining_writes == 2));
+        let write = programmer.write_vcom(0x1f, &mut NoDelay).unwrap();
+        assert_eq!(write.remaining_writes, 2);
+
+        assert_eq!(
+            lcd.writer().programmed,
+            [(0b000, 0x54), (0b001, 0x80), (0b010, 0x66), (0b100, 0x1f)]
+        );
+    }
+
+    #[test]
+    fn no_writes_remaining() {
+        let mut lcd = driver(Model {
+            counts: [0, 0, 0, MAX_WRITES],
+            ..Model::default()
+        });
+        let mut programmer = NvmProgrammer::new(&mut lcd);
+        unsafe { programmer.arm() };
+
+        match programmer.write_vcom(0x1f, &mut NoDelay) {
+            Err(Error::NoWritesRemaining) => {}
+            result => panic!("unexpected {:?}", result),
+        }
+        assert_eq!(lcd.writer().programmed, []);
+    }
+
+    #[test]
+    fn timeout() {
+        let mut lcd = driver(Model {
+            busy_polls: POLL_ATTEMPTS as usize + 1,
+            ..Model::default()
+        });
+        let mut programmer = NvmProgrammer::new(&mut lcd);
+        unsafe { programmer.arm() };
+
+        match programmer.write_vcom(0x1f, &mut NoDelay) {
+            Err(Error::Timeout) => {}
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,507 @@
+//! Recording and replaying of bus transactions.
+//!
+//! A [Recording](Recording) wraps the interface of the driver and logs every word written or
+//! read. The log can be [encoded](encode) into a compact byte stream, sent over e.g. a UART,
+//! [parsed](parse) on the host and fed into another interface with [Replay](Replay), e.g. the
+//! emulator:
+//!
+//! ```ignore
+//! // on the device
+//! let mut records = [Record::Command(0); 1024];
+//! let recording = Recording::new(interface, BusWidth::Bits8, Buffer::new(&mut records));
+//! let mut lcd = ILI9486::new(&mut delay, PixelFormat::Rgb565, recording, rst)?;
+//! ...
+//! recording::encode(BusWidth::Bits8, lcd.writer().buffer().as_slice(), &mut |b| uart_write(b));
+//!
+//! // on the host
+//! let mut records = Vec::new();
+//! let width = recording::parse(&bytes, &mut |record| records.push(record))?;
+//! let mut emulator = Emulator::new();
+//! Replay::new(&records).run::<u8, _>(&mut emulator)?;
+//! ```
+
+use crate::decode::BusWidth;
+use core::convert::TryFrom;
+use display_interface::v2::*;
+use display_interface::DisplayError;
+
+/// A word on the bus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Record {
+    /// A word written with D/CX low
+    Command(u32),
+    /// A word written with D/CX high
+    Data(u32),
+    /// A word read from the controller
+    Read(u32),
+}
+
+/// Storage for the records of a [Recording](Recording).
+pub trait TraceBuffer {
+    /// Adds a record, returns false if there is no room for it.
+    fn push(&mut self, record: Record) -> bool;
+
+    fn as_slice(&self) -> &[Record];
+
+    fn clear(&mut self);
+}
+
+/// A bounded buffer on top of a caller supplied slice, records are dropped once it is full.
+pub struct Buffer<'a> {
+    records: &'a mut [Record],
+    len: usize,
+}
+
+impl<'a> Buffer<'a> {
+    pub fn new(records: &'a mut [Record]) -> Buffer<'a> {
+        Buffer { records, len: 0 }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.records.len()
+    }
+}
+
+impl<'a> TraceBuffer for Buffer<'a> {
+    fn push(&mut self, record: Record) -> bool {
+        if self.len == self.records.len() {
+            return false;
+        }
+        self.records[self.len] = record;
+        self.len += 1;
+        true
+    }
+
+    fn as_slice(&self) -> &[Record] {
+        &self.records[..self.len]
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+#[cfg(feature = "std")]
+impl TraceBuffer for std::vec::Vec<Record> {
+    fn push(&mut self, record: Record) -> bool {
+        std::vec::Vec::push(self, record);
+        true
+    }
+
+    fn as_slice(&self) -> &[Record] {
+        self
+    }
+
+    fn clear(&mut self) {
+        std::vec::Vec::clear(self);
+    }
+}
+
+/// Wraps an interface and records every word written to and read from it.
+pub struct Recording<RW, B> {
+    inner: RW,
+    width: BusWidth,
+    buffer: B,
+    dropped: usize,
+}
+
+impl<RW, B> Recording<RW, B>
+where
+    B: TraceBuffer,
+{
+    /// Creates a new recording
+    ///
+    /// # Arguments
+    ///
+    /// `inner` - The interface that is recorded
+    ///
+    /// `width` - The width of the bus, stored with the [encoded](encode) trace
+    ///
+    /// `buffer` - Where records are kept
+    ///
+    pub fn new(inner: RW, width: BusWidth, buffer: B) -> Recording<RW, B> {
+        Recording {
+            inner,
+            width,
+            buffer,
+            dropped: 0,
+        }
+    }
+
+    pub fn width(&self) -> BusWidth {
+        self.width
+    }
+
+    pub fn buffer(&self) -> &B {
+        &self.buffer
+    }
+
+    /// Number of records that did not fit into the buffer.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Clears the buffer and the dropped count.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.dropped = 0;
+    }
+
+    pub fn inner(&mut self) -> &mut RW {
+        &mut self.inner
+    }
+
+    pub fn release(self) -> (RW, B) {
+        (self.inner, self.buffer)
+    }
+}
+
+fn record(buffer: &mut dyn TraceBuffer, dropped: &mut usize, record: Record) {
+    if !buffer.push(record) {
+        *dropped += 1;
+    }
+}
+
+impl<RW, B, T> WriteInterface<T> for Recording<RW, B>
+where
+    RW: WriteInterface<T>,
+    B: TraceBuffer,
+    T: Copy + Into<u32>,
+{
+    fn write_stream<'a>(
+        &mut self,
+        mode: WriteMode,
+        func: &mut dyn FnMut() -> Option<&'a T>,
+    ) -> Result<(), DisplayError> {
+        let command = matches!(mode, WriteMode::Command);
+        let buffer = &mut self.buffer;
+        let dropped = &mut self.dropped;
+
+        self.inner.write_stream(mode, &mut || {
+            let word = func();
+            if let Some(word) = word {
+                let word = (*word).into();
+                let entry = if command {
+                    Record::Command(word)
+                } else {
+                    Record::Data(word)
+                };
+                record(buffer, dropped, entry);
+            }
+            word
+        })
+    }
+}
+
+impl<RW, B, T> ReadInterface<T> for Recording<RW, B>
+where
+    RW: ReadInterface<T>,
+    B: TraceBuffer,
+    T: Copy + Into<u32>,
+{
+    fn read_stream(&mut self, f: &mut dyn FnMut(T) -> bool) -> Result<(), DisplayError> {
+        let buffer = &mut self.buffer;
+        let dropped = &mut self.dropped;
+
+        self.inner.read_stream(&mut |word| {
+            record(buffer, dropped, Record::Read(word.into()));
+            f(word)
+        })
+    }
+}
+
+const MAGIC: [u8; 2] = [b'T', b'R'];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The trace does not start with a valid header
+    InvalidHeader,
+    /// A record has an unknown tag
+    InvalidRecord { offset: usize },
+    /// The trace ends in the middle of a record
+    Truncated,
+}
+
+fn width_code(width: BusWidth) -> u8 {
+    match width {
+        BusWidth::Bits8 => 8,
+        BusWidth::Bits9 => 9,
+        BusWidth::Bits16 => 16,
+        BusWidth::Bits18 => 18,
+    }
+}
+
+/// Number of bytes used for a word on a bus of `width`.
+fn word_bytes(width: BusWidth) -> usize {
+    match width {
+        BusWidth::Bits8 => 1,
+        BusWidth::Bits9 | BusWidth::Bits16 => 2,
+        BusWidth::Bits18 => 3,
+    }
+}
+
+/// Encodes records as bytes, a header with the bus width followed by a tag byte and the
+/// little endian word for every record.
+pub fn encode(width: BusWidth, records: &[Record], out: &mut dyn FnMut(u8)) {
+    out(MAGIC[0]);
+    out(MAGIC[1]);
+    out(width_code(width));
+
+    for entry in records {
+        let (tag, word) = match *entry {
+            Record::Command(word) => (b'C', word),
+            Record::Data(word) => (b'D', word),
+            Record::Read(word) => (b'R', word),
+        };
+        out(tag);
+        for i in 0..word_bytes(width) {
+            out((word >> (i * 8)) as u8);
+        }
+    }
+}
+
+/// Parses a trace produced by [encode](encode), calling `f` with every record.
+pub fn parse(bytes: &[u8], f: &mut dyn FnMut(Record)) -> Result<BusWidth, Error> {
+    if bytes.len() < 3 || bytes[..2] != MAGIC {
+        return Err(Error::InvalidHeader);
+    }
+    let width = match bytes[2] {
+        8 => BusWidth::Bits8,
+        9 => BusWidth::Bits9,
+        16 => BusWidth::Bits16,
+        18 => BusWidth::Bits18,
+        _ => return Err(Error::InvalidHeader),
+    };
+
+    let size = 1 + word_bytes(width);
+    let mut offset = 3;
+    while offset < bytes.len() {
+        if bytes.len() < offset + size {
+            return Err(Error::Truncated);
+        }
+        let word = bytes[offset + 1..offset + size]
+            .iter()
+            .rev()
+            .fold(0u32, |word, byte| (word << 8) | *byte as u32);
+        f(match bytes[offset] {
+            b'C' => Record::Command(word),
+            b'D' => Record::Data(word),
+            b'R' => Record::Read(word),
+            _ => return Err(Error::InvalidRecord { offset }),
+        });
+        offset += size;
+    }
+
+    Ok(width)
+}
+
+/// Feeds recorded transactions into another interface.
+///
+/// Consecutive writes with the same D/CX level are sent in one stream, recorded reads are read
+/// back from the interface and compared.
+pub struct Replay<'a> {
+    records: &'a [Record],
+    read_mismatches: usize,
+}
+
+impl<'a> Replay<'a> {
+    pub fn new(records: &'a [Record]) -> Replay<'a> {
+        Replay {
+            records,
+            read_mismatches: 0,
+        }
+    }
+
+    /// Number of words read during [run](Replay::run) that differ from the recording.
+    pub fn read_mismatches(&self) -> usize {
+        self.read_mismatches
+    }
+
+    /// Replays all records into `target`.
+    ///
+    /// Returns `InvalidFormatError` if a recorded word does not fit into `T`.
+    pub fn run<T, RW>(&mut self, target: &mut RW) -> Result<(), DisplayError>
+    where
+        RW: ReadWriteInterface<T>,
+        T: TryFrom<u32> + Into<u32> + Copy + Default,
+    {
+        const CHUNK: usize = 64;
+        let records = self.records;
+        let mut index = 0;
+
+        while index < records.len() {
+            let end = records[index..]
+                .iter()
+                .position(|entry| {
+                    core::mem::discriminant(entry) != core::mem::discriminant(&records[index])
+                })
+                .map(|len| index + len)
+                .unwrap_or(records.len());
+            let run = &records[index..end];
+
+            match records[index] {
+                Record::Read(_) => {
+                    let mut expected = run.iter();
+                    let mut mismatches = 0;
+                    target.read_stream(&mut |word: T| match expected.next() {
+                        Some(Record::Read(recorded)) => {
+                            if word.into() != *recorded {
+                                mismatches += 1;
+                            }
+                            expected.len() > 0
+                        }
+                        _ => false,
+                    })?;
+                    self.read_mismatches += mismatches;
+                }
+                _ => {
+                    for chunk in run.chunks(CHUNK) {
+                        let mut words = [T::default(); CHUNK];
+                        for (word, entry) in words.iter_mut().zip(chunk) {
+                            let value = match *entry {
+                                Record::Command(value)
+                                | Record::Data(value)
+                                | Record::Read(value) => value,
+                            };
+                            *word =
+                                T::try_from(value).map_err(|_| DisplayError::InvalidFormatError)?;
+                        }
+                        let mode = match records[index] {
+                            Record::Command(_) => WriteMode::Command,
+                            _ => WriteMode::Data,
+                        };
+                        target.write(mode, &words[..chunk.len()])?;
+                    }
+                }
+            }
+
+            index = end;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::color::PixelFormat;
+    use crate::emulator::{Emulator, NoDelay, NoPin};
+    use crate::io::shim::OutputOnlyIoPin;
+    use crate::ILI9486;
+    use std::vec::Vec;
+
+    #[test]
+    fn record_encode_parse_replay() {
+        let recording = Recording::new(Emulator::new(), BusWidth::Bits8, Vec::new());
+        let mut lcd: ILI9486<_, u8> = ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb565,
+            recording,
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap();
+        lcd.init(&mut NoDelay).unwrap();
+        lcd._draw_pixel(10, 20, 31, 0, 0).unwrap();
+        lcd._draw_pixel(11, 20, 0, 63, 0).unwrap();
+        let mut pixels = [(0, 0, 0); 2];
+        lcd.read_pixels(10, 20, 2, 1, &mut pixels).unwrap();
+        assert_eq!(pixels, [(31, 0, 0), (0, 63, 0)]);
+
+        let records = lcd.writer().buffer().clone();
+        assert_eq!(records[0], Record::Command(0x3a));
+        assert_eq!(records[1], Record::Data(0x55));
+        assert!(records.contains(&Record::Read(0xfc)));
+
+        let mut bytes = Vec::new();
+        encode(BusWidth::Bits8, &records, &mut |b| bytes.push(b));
+        assert_eq!(&bytes[..3], b"TR\x08");
+        assert_eq!(bytes.len(), 3 + records.len() * 2);
+
+        let mut parsed = Vec::new();
+        assert_eq!(
+            parse(&bytes, &mut |record| parsed.push(record)),
+            Ok(BusWidth::Bits8)
+        );
+        assert_eq!(parsed, records);
+
+        let mut target = Emulator::new();
+        let mut replay = Replay::new(&parsed);
+        replay.run::<u8, _>(&mut target).unwrap();
+        assert_eq!(replay.read_mismatches(), 0);
+        assert_eq!(target.gram(), lcd.writer().inner().gram());
+        assert_eq!(target.pixel(11, 20), (0, 0x3f, 0));
+    }
+
+    #[test]
+    fn bounded_buffer() {
+        let mut records = [Record::Command(0); 3];
+        let mut recording =
+            Recording::new(Emulator::new(), BusWidth::Bits16, Buffer::new(&mut records));
+        recording.write(WriteMode::Command, &[0x2cu16]).unwrap();
+        recording.write(WriteMode::Data, &[1u16, 2, 3, 4]).unwrap();
+        assert_eq!(
+            recording.buffer().as_slice(),
+            [Record::Command(0x2c), Record::Data(1), Record::Data(2)]
+        );
+        assert_eq!(recording.dropped(), 2);
+        assert_eq!(recording.buffer().capacity(), 3);
+
+        recording.clear();
+        assert!(recording.buffer().as_slice().is_empty());
+        assert_eq!(recording.dropped(), 0);
+    }
+
+    #[test]
+    fn wide_words() {
+        let records = [
+            Record::Command(0x2c),
+            Record::Data(0x3ffff),
+            Record::Read(0x12345),
+        ];
+        let mut bytes = Vec::new();
+        encode(BusWidth::Bits18, &records, &mut |b| bytes.push(b));
+        assert_eq!(bytes.len(), 3 + 3 * 4);
+
+        let mut parsed = Vec::new();
+        assert_eq!(
+            parse(&bytes, &mut |record| parsed.push(record)),
+            Ok(BusWidth::Bits18)
+        );
+        assert_eq!(parsed, records);
+
+        // the words don't fit into an 8-bit bus
+        assert!(matches!(
+            Replay::new(&records).run::<u8, _>(&mut Emulator::new()),
+            Err(DisplayError::InvalidFormatError)
+        ));
+    }
+
+    #[test]
+    fn read_mismatches() {
+        let records = [
+            Record::Command(0x04),
+            Record::Read(0),
+            Record::Read(0x54),
+            Record::Read(0x81),
+            Record::Read(0x67),
+        ];
+        let mut replay = Replay::new(&records);
+        replay.run::<u8, _>(&mut Emulator::new()).unwrap();
+        assert_eq!(replay.read_mismatches(), 2);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let mut ignore = |_| {};
+        assert_eq!(parse(b"TR", &mut ignore), Err(Error::InvalidHeader));
+        assert_eq!(parse(b"XX\x08", &mut ignore), Err(Error::InvalidHeader));
+        assert_eq!(parse(b"TR\x07", &mut ignore), Err(Error::InvalidHeader));
+        assert_eq!(parse(b"TR\x10C\x2c", &mut ignore), Err(Error::Truncated));
+        assert_eq!(
+            parse(b"TR\x08C\x2cX\x00", &mut ignore),
+            Err(Error::InvalidRecord { offset: 5 })
+        );
+        assert_eq!(parse(b"TR\x09", &mut ignore), Ok(BusWidth::Bits9));
+    }
+}
@@ -0,0 +1,406 @@
+use crate::decode::{BusWidth, Decoder, Event};
+use std::io::{self, BufRead, Read};
+use std::string::String;
+use std::vec::Vec;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// A line of the CSV export could not be parsed
+    Parse {
+        line: usize,
+    },
+    /// A channel was not found in the CSV header
+    MissingChannel(&'static str),
+    /// The sample size of a binary export is not between 1 and 8 bytes
+    UnitSize(usize),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+/// Positions of the 8-bit parallel bus signals, as CSV columns or bits of a binary sample.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChannelMap {
+    pub data: [usize; 8],
+    pub dcx: usize,
+    pub wrx: usize,
+    pub rdx: usize,
+    pub cs: usize,
+}
+
+impl Default for ChannelMap {
+    /// DB0-DB7 on channels 0-7, followed by D/CX, WRX, RDX and CS.
+    fn default() -> ChannelMap {
+        ChannelMap {
+            data: [0, 1, 2, 3, 4, 5, 6, 7],
+            dcx: 8,
+            wrx: 9,
+            rdx: 10,
+            cs: 11,
+        }
+    }
+}
+
+const DATA_NAMES: [[&str; 2]; 8] = [
+    ["DB0", "D0"],
+    ["DB1", "D1"],
+    ["DB2", "D2"],
+    ["DB3", "D3"],
+    ["DB4", "D4"],
+    ["DB5", "D5"],
+    ["DB6", "D6"],
+    ["DB7", "D7"],
+];
+
+impl ChannelMap {
+    /// Finds the channels by name in a CSV header, e.g. `DB0,...,DB7,DCX,WRX,RDX,CS`.
+    pub fn from_csv_header(header: &str) -> Result<ChannelMap, Error> {
+        let columns: Vec<String> = header
+            .split(',')
+            .map(|column| column.trim().to_ascii_uppercase())
+            .collect();
+        let find = |names: &[&str], name: &'static str| -> Result<usize, Error> {
+            columns
+                .iter()
+                .position(|column| names.iter().any(|n| column == n))
+                .ok_or(Error::MissingChannel(name))
+        };
+
+        let mut data = [0; 8];
+        for (bit, names) in DATA_NAMES.iter().enumerate() {
+            data[bit] = find(names, names[0])?;
+        }
+
+        Ok(ChannelMap {
+            data,
+            dcx: find(&["DCX", "D/CX", "DC", "RS"], "DCX")?,
+            wrx: find(&["WRX", "WR"], "WRX")?,
+            rdx: find(&["RDX", "RD"], "RDX")?,
+            cs: find(&["CS", "CSX"], "CS")?,
+        })
+    }
+}
+
+/// The bus signals at one sample.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Sample {
+    data: u8,
+    dcx: bool,
+    wrx: bool,
+    rdx: bool,
+    cs: bool,
+}
+
+/// A word written to or read from the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transaction {
+    Write { dcx: bool, word: u8 },
+    Read { dcx: bool, word: u8 },
+}
+
+/// Reconstructs transactions from the WRX and RDX rising edges, where the controller latches
+/// the data lines.
+struct Reconstructor {
+    previous: Option<Sample>,
+    transactions: Vec<Transaction>,
+}
+
+impl Reconstructor {
+    fn new() -> Reconstructor {
+        Reconstructor {
+            previous: None,
+            transactions: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, sample: Sample) {
+        if let Some(previous) = self.previous {
+            // the last sample before the edge holds the latched values
+            if !previous.cs && !previous.wrx && sample.wrx {
+                self.transactions.push(Transaction::Write {
+                    dcx: previous.dcx,
+                    word: previous.data,
+                });
+            }
+            if !previous.cs && !previous.rdx && sample.rdx {
+                self.transactions.push(Transaction::Read {
+                    dcx: previous.dcx,
+                    word: previous.data,
+                });
+            }
+        }
+        self.previous = Some(sample);
+    }
+}
+
+fn sample_from_bits(bit: &dyn Fn(usize) -> bool, channels: &ChannelMap) -> Sample {
+    let mut data = 0;
+    for (i, channel) in channels.data.iter().enumerate() {
+        if bit(*channel) {
+            data |= 1 << i;
+        }
+    }
+    Sample {
+        data,
+        dcx: bit(channels.dcx),
+        wrx: bit(channels.wrx),
+        rdx: bit(channels.rdx),
+        cs: bit(channels.cs),
+    }
+}
+
+/// Reads a sigrok CSV export (`sigrok-cli -O csv`).
+///
+/// Lines starting with `;` are skipped. The channels are taken from `channels`, or looked up in
+/// the header line if `None`. Without a header the [default](ChannelMap::default) positions are used.
+pub fn read_csv<R>(reader: R, channels: Option<ChannelMap>) -> Result<Vec<Transaction>, Error>
+where
+    R: BufRead,
+{
+    let mut channels = channels;
+    let mut header_checked = false;
+    let mut reconstructor = Reconstructor::new();
+    let mut values: Vec<bool> = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        if !header_checked {
+            header_checked = true;
+            if line
+                .split(',')
+                .any(|column| column.trim().parse::<f64>().is_err())
+            {
+                if channels.is_none() {
+                    channels = Some(ChannelMap::from_csv_header(line)?);
+                }
+                continue;
+            }
+        }
+        let map = channels.unwrap_or_default();
+
+        values.clear();
+        for column in line.split(',') {
+            values.push(match column.trim() {
+                "1" => true,
+                "0" => false,
+                // other columns, e.g. the time, are not used
+                column if column.parse::<f64>().is_ok() => false,
+                _ => return Err(Error::Parse { line: index + 1 }),
+            });
+        }
+
+        let bit = |channel: usize| values.get(channel).cloned();
+        if [map.dcx, map.wrx, map.rdx, map.cs]
+            .iter()
+            .chain(map.data.iter())
+            .any(|channel| bit(*channel).is_none())
+        {
+            return Err(Error::Parse { line: index + 1 });
+        }
+        reconstructor.push(sample_from_bits(
+            &|channel| bit(channel).unwrap_or(false),
+            &map,
+        ));
+    }
+
+    Ok(reconstructor.transactions)
+}
+
+/// Reads a sigrok binary export (`sigrok-cli -O binary`), samples are `unit_size` bytes with
+/// channel 0 in the lowest bit.
+///
+/// Returns [UnitSize](Error::UnitSize) if `unit_size` is 0 or more than 8.
+pub fn read_binary<R>(
+    mut reader: R,
+    unit_size: usize,
+    channels: &ChannelMap,
+) -> Result<Vec<Transaction>, Error>
+where
+    R: Read,
+{
+    if unit_size == 0 || unit_size > 8 {
+        return Err(Error::UnitSize(unit_size));
+    }
+
+    let mut reconstructor = Reconstructor::new();
+    let mut unit = [0u8; 8];
+    let unit = &mut unit[..unit_size];
+
+    loop {
+        match reader.read_exact(unit) {
+            Ok(()) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        let bit = |channel: usize| {
+            unit.get(channel / 8)
+                .map(|byte| byte & (1 << (channel % 8)) != 0)
+                .unwrap_or(false)
+        };
+        reconstructor.push(sample_from_bits(&bit, channels));
+    }
+
+    Ok(reconstructor.transactions)
+}
+
+/// Decodes reconstructed transactions into command events.
+pub fn decode(transactions: &[Transaction]) -> Vec<Event> {
+    let mut decoder = Decoder::new(BusWidth::Bits8);
+    let mut events = Vec::new();
+
+    for transaction in transactions {
+        match *transaction {
+            Transaction::Write { dcx, word } => {
+                if let Some(event) = decoder.push(dcx, word as u32) {
+                    events.push(event);
+                }
+            }
+            Transaction::Read { word, .. } => decoder.push_read(word as u32),
+        }
+    }
+    if let Some(event) = decoder.finish() {
+        events.push(event);
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::Event;
+    use crate::Command;
+
+    /// Samples of a write strobe, the data is set up while WRX is low and latched on the rising
+    /// edge.
+    fn write(samples: &mut Vec<u16>, dcx: bool, word: u8) {
+        let dcx = if dcx { 1 << 8 } else { 0 };
+        // RDX high, CS low
+        let idle = 1 << 10;
+        samples.push(idle | 1 << 9 | dcx);
+        samples.push(idle | dcx | word as u16);
+        samples.push(idle | 1 << 9 | dcx | word as u16);
+    }
+
+    fn capture() -> Vec<u16> {
+        let mut samples = Vec::new();
+        write(&mut samples, false, 0x2a);
+        for word in [0x00, 0x10, 0x01, 0x3f].iter() {
+            write(&mut samples, true, *word);
+        }
+        write(&mut samples, false, 0x29);
+        samples
+    }
+
+    fn expected() -> Vec<Transaction> {
+        let mut transactions = std::vec![Transaction::Write {
+            dcx: false,
+            word: 0x2a
+        }];
+        for word in [0x00, 0x10, 0x01, 0x3f].iter() {
+            transactions.push(Transaction::Write {
+                dcx: true,
+                word: *word,
+            });
+        }
+        transactions.push(Transaction::Write {
+            dcx: false,
+            word: 0x29,
+        });
+        transactions
+    }
+
+    #[test]
+    fn binary_export() {
+        let bytes: Vec<u8> = capture()
+            .iter()
+            .flat_map(|sample| std::vec![*sample as u8, (*sample >> 8) as u8])
+            .collect();
+        let transactions = read_binary(&bytes[..], 2, &ChannelMap::default()).unwrap();
+        assert_eq!(transactions, expected());
+
+        let events = decode(&transactions);
+        assert_eq!(events.len(), 2);
+        match events[0] {
+            Event::Command {
+                command,
+                parameters,
+                ..
+            } => {
+                assert_eq!(command, Command::ColumnAddressSet);
+                assert_eq!(parameters.as_slice(), [0x00, 0x10, 0x01, 0x3f]);
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+
+        // a wider sample, the upper bytes are not used
+        let bytes: Vec<u8> = capture()
+            .iter()
+            .flat_map(|sample| std::vec![*sample as u8, (*sample >> 8) as u8, 0xff, 0xff])
+            .collect();
+        assert_eq!(
+            read_binary(&bytes[..], 4, &ChannelMap::default()).unwrap(),
+            expected()
+        );
+    }
+
+    #[test]
+    fn binary_unit_size() {
+        for unit_size in [0, 9, 16].iter() {
+            match read_binary(&[0u8; 32][..], *unit_size, &ChannelMap::default()) {
+                Err(Error::UnitSize(size)) => assert_eq!(size, *unit_size),
+                result => panic!("unexpected {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn csv_export() {
+        let mut csv = String::from("; sigrok capture\nTime,D0,D1,D2,D3,D4,D5,D6,D7,DCX,WR,RD,CS\n");
+        for (i, sample) in capture().iter().enumerate() {
+            csv.push_str(&std::format!("{}", i as f64 * 0.5));
+            for bit in 0..12 {
+                csv.push_str(if sample & (1 << bit) != 0 { ",1" } else { ",0" });
+            }
+            csv.push('\n');
+        }
+        assert_eq!(read_csv(csv.as_bytes(), None).unwrap(), expected());
+
+        // without a header the default channels are used
+        let headerless: String = csv
+            .lines()
+            .skip(2)
+            .map(|line| &line[line.find(',').unwrap() + 1..])
+            .flat_map(|line| line.chars().chain(Some('\n')))
+            .collect();
+        assert_eq!(read_csv(headerless.as_bytes(), None).unwrap(), expected());
+    }
+
+    #[test]
+    fn csv_errors() {
+        match read_csv("D0,D1,D2,D3,D4,D5,D6,D7,WRX,RDX,CS\n".as_bytes(), None) {
+            Err(Error::MissingChannel(name)) => assert_eq!(name, "DCX"),
+            result => panic!("unexpected {:?}", result),
+        }
+        match read_csv("0,0,0,0,0,0,0,0,0,1,1,0\n0,1\n".as_bytes(), None) {
+            Err(Error::Parse { line }) => assert_eq!(line, 2),
+            result => panic!("unexpected {:?}", result),
+        }
+        match read_csv(
+            "0,0,0,0,0,0,0,0,0,1,1,0\n0,0,0,0,0,0,0,0,0,1,1,x\n".as_bytes(),
+            None,
+        ) {
+            Err(Error::Parse { line }) => assert_eq!(line, 2),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+}
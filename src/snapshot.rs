@@ -0,0 +1,460 @@
+//! Golden image comparison for scenes rendered on the [Emulator](crate::emulator::Emulator).
+//!
+//! ```ignore
+//! let image = snapshot::render(PixelFormat::Rgb565, |lcd| {
+//!     Rectangle::new(Point::new(16, 16), Point::new(200, 240))
+//!         .into_styled(PrimitiveStyle::with_fill(Rgb565::RED))
+//!         .draw(lcd)
+//!         .unwrap();
+//!     Ok(())
+//! })?;
+//! snapshot::assert_snapshot("tests/snapshots/rectangle.ppm", &image);
+//! ```
+//!
+//! Golden images are stored as binary PPM. When the `UPDATE_SNAPSHOTS` environment variable is
+//! set, the golden image is written instead of compared, otherwise a missing golden image is an
+//! error. On a mismatch `<name>.actual.ppm` and `<name>.diff.ppm` are written next to it, the diff shows
+//! matching pixels dimmed and differing pixels in red.
+
+use crate::color::PixelFormat;
+use crate::emulator::{Emulator, NoDelay, NoPin, HEIGHT, WIDTH};
+use crate::io::shim::OutputOnlyIoPin;
+use crate::ILI9486;
+use display_interface::DisplayError;
+use std::env;
+use std::format;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::vec::Vec;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The file is not a binary PPM with 8-bit samples
+    InvalidImage,
+    /// The golden image does not exist, set `UPDATE_SNAPSHOTS` to create it
+    MissingGolden(PathBuf),
+    /// The rendered image differs from the golden image
+    Mismatch {
+        golden: PathBuf,
+        differing: usize,
+        first: (usize, usize),
+    },
+    /// The golden image has a different size than the rendered image
+    SizeMismatch {
+        golden: PathBuf,
+        expected: (usize, usize),
+        actual: (usize, usize),
+    },
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+/// An RGB888 image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<(u8, u8, u8)>,
+}
+
+fn expand_6bit(value: u8) -> u8 {
+    (value << 2) | (value >> 4)
+}
+
+impl Image {
+    /// Converts the frame memory of `emulator` to RGB888.
+    pub fn from_emulator(emulator: &Emulator) -> Image {
+        Image {
+            width: WIDTH,
+            height: HEIGHT,
+            pixels: emulator
+                .gram()
+                .iter()
+                .map(|p| (expand_6bit(p.0), expand_6bit(p.1), expand_6bit(p.2)))
+                .collect(),
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> (u8, u8, u8) {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Encodes the image as a binary (P6) PPM.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut data = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        data.reserve(self.pixels.len() * 3);
+        for (r, g, b) in &self.pixels {
+            data.extend_from_slice(&[*r, *g, *b]);
+        }
+        data
+    }
+
+    /// Decodes a binary (P6) PPM with a maximum value of 255.
+    pub fn from_ppm(data: &[u8]) -> Result<Image, Error> {
+        let mut position = 0;
+        let mut fields: Vec<String> = Vec::new();
+
+        // magic, width, height and maximum value, separated by whitespace and comments
+        while fields.len() < 4 {
+            while position < data.len() && data[position].is_ascii_whitespace() {
+                position += 1;
+            }
+            if position < data.len() && data[position] == b'#' {
+                while position < data.len() && data[position] != b'\n' {
+                    position += 1;
+                }
+                continue;
+            }
+            let start = position;
+            while position < data.len() && !data[position].is_ascii_whitespace() {
+                position += 1;
+            }
+            if start == position {
+                return Err(Error::InvalidImage);
+            }
+            fields.push(String::from_utf8_lossy(&data[start..position]).into_owned());
+        }
+        // a single whitespace character separates the header from the samples
+        position += 1;
+
+        let width: usize = fields[1].parse().map_err(|_| Error::InvalidImage)?;
+        let height: usize = fields[2].parse().map_err(|_| Error::InvalidImage)?;
+        let len = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(3))
+            .ok_or(Error::InvalidImage)?;
+        if fields[0] != "P6" || fields[3] != "255" || data.len().saturating_sub(position) < len {
+            return Err(Error::InvalidImage);
+        }
+
+        Ok(Image {
+            width,
+            height,
+            pixels: data[position..position + len]
+                .chunks(3)
+                .map(|p| (p[0], p[1], p[2]))
+                .collect(),
+        })
+    }
+}
+
+/// Differences between two images of the same size.
+#[derive(Clone, Debug)]
+pub struct Diff {
+    /// Number of differing pixels
+    pub differing: usize,
+    /// The first differing pixel, row by row
+    pub first: (usize, usize),
+    /// Matching pixels dimmed, differing pixels in red
+    pub image: Image,
+}
+
+/// Compares two images of the same size, returns `None` if they are equal.
+pub fn diff(expected: &Image, actual: &Image) -> Option<Diff> {
+    let mut differing = 0;
+    let mut first = None;
+    let pixels = expected
+        .pixels
+        .iter()
+        .zip(&actual.pixels)
+        .enumerate()
+        .map(|(i, (e, a))| {
+            if e == a {
+                let gray = ((e.0 as u16 + e.1 as u16 + e.2 as u16) / 12) as u8;
+                (gray, gray, gray)
+            } else {
+                differing += 1;
+                first = first.or(Some((i % expected.width, i / expected.width)));
+                (255, 0, 0)
+            }
+        })
+        .collect();
+
+    first.map(|first| Diff {
+        differing,
+        first,
+        image: Image {
+            width: expected.width,
+            height: expected.height,
+            pixels,
+        },
+    })
+}
+
+/// Runs `draw` on a driver connected to a fresh [Emulator](Emulator) and returns the frame memory.
+pub fn render<F>(pixel_format: PixelFormat, draw: F) -> Result<Image, DisplayError>
+where
+    F: FnOnce(&mut ILI9486<Emulator, u8>) -> Result<(), DisplayError>,
+{
+    let mut lcd: ILI9486<Emulator, u8> = ILI9486::new(
+        &mut NoDelay,
+        pixel_format,
+        Emulator::new(),
+        OutputOnlyIoPin::new(NoPin),
+    )?;
+    draw(&mut lcd)?;
+    Ok(Image::from_emulator(lcd.writer()))
+}
+
+fn with_suffix(golden: &Path, suffix: &str) -> PathBuf {
+    let mut name = golden.file_stem().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    golden.with_file_name(name)
+}
+
+/// Compares `actual` with the golden image at `golden`.
+///
+/// The golden image is written if `UPDATE_SNAPSHOTS` is set, otherwise it must exist.
+pub fn check_snapshot<P>(golden: P, actual: &Image) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    let golden = golden.as_ref();
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        if let Some(parent) = golden.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(golden, actual.to_ppm())?;
+        return Ok(());
+    }
+
+    if !golden.exists() {
+        return Err(Error::MissingGolden(golden.to_path_buf()));
+    }
+
+    let expected = Image::from_ppm(&fs::read(golden)?)?;
+    if (expected.width, expected.height) != (actual.width, actual.height) {
+        return Err(Error::SizeMismatch {
+            golden: golden.to_path_buf(),
+            expected: (expected.width, expected.height),
+            actual: (actual.width, actual.height),
+        });
+    }
+
+    match diff(&expected, actual) {
+        None => Ok(()),
+        Some(diff) => {
+            fs::write(with_suffix(golden, ".actual.ppm"), actual.to_ppm())?;
+            fs::write(with_suffix(golden, ".diff.ppm"), diff.image.to_ppm())?;
+            Err(Error::Mismatch {
+                golden: golden.to_path_buf(),
+                differing: diff.differing,
+                first: diff.first,
+            })
+        }
+    }
+}
+
+/// Like [check_snapshot](check_snapshot), but panics with a readable message on failure.
+pub fn assert_snapshot<P>(golden: P, actual: &Image)
+where
+    P: AsRef<Path>,
+{
+    match check_snapshot(golden, actual) {
+        Ok(()) => {}
+        Err(Error::Mismatch {
+            golden,
+            differing,
+            first,
+        }) => panic!(
+            "{} pixels differ from {}, first at ({}, {}): expected {:?}, got {:?}. See {} and {}, \
+             set UPDATE_SNAPSHOTS=1 to accept the new image",
+            differing,
+            golden.display(),
+            first.0,
+            first.1,
+            Image::from_ppm(&fs::read(&golden).unwrap_or_default())
+                .map(|image| image.pixel(first.0, first.1))
+                .ok(),
+            actual.pixel(first.0, first.1),
+            with_suffix(&golden, ".actual.ppm").display(),
+            with_suffix(&golden, ".diff.ppm").display(),
+        ),
+        Err(e) => panic!("snapshot comparison failed: {:?}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "graphics")]
+    use crate::Commands;
+    #[cfg(feature = "graphics")]
+    use embedded_graphics::pixelcolor::Rgb888;
+    #[cfg(feature = "graphics")]
+    use embedded_graphics::prelude::*;
+    #[cfg(feature = "graphics")]
+    use embedded_graphics::primitives::Rectangle;
+    #[cfg(feature = "graphics")]
+    use embedded_graphics::style::{PrimitiveStyle, PrimitiveStyleBuilder};
+
+    fn golden(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test/snapshots")
+            .join(name)
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("ili9486-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(feature = "graphics")]
+    fn rect(
+        lcd: &mut ILI9486<Emulator, u8>,
+        top_left: (i32, i32),
+        bottom_right: (i32, i32),
+        style: PrimitiveStyle<Rgb888>,
+    ) {
+        Rectangle::new(
+            Point::new(top_left.0, top_left.1),
+            Point::new(bottom_right.0, bottom_right.1),
+        )
+        .into_styled(style)
+        .draw(lcd)
+        .unwrap();
+    }
+
+    #[cfg(feature = "graphics")]
+    fn rects(lcd: &mut ILI9486<Emulator, u8>) -> Result<(), DisplayError> {
+        let outlined = PrimitiveStyleBuilder::new()
+            .stroke_color(Rgb888::WHITE)
+            .stroke_width(2)
+            .fill_color(Rgb888::RED)
+            .build();
+        rect(lcd, (16, 16), (116, 76), outlined);
+        rect(
+            lcd,
+            (60, 50),
+            (160, 110),
+            PrimitiveStyle::with_fill(Rgb888::GREEN),
+        );
+        rect(
+            lcd,
+            (300, 460),
+            (320, 480),
+            PrimitiveStyle::with_stroke(Rgb888::BLUE, 3),
+        );
+        rect(
+            lcd,
+            (0, 200),
+            (320, 201),
+            PrimitiveStyle::with_fill(Rgb888::new(255, 128, 0)),
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "graphics")]
+    fn golden_images() {
+        assert_snapshot(
+            golden("rects.ppm"),
+            &render(PixelFormat::Rgb565, rects).unwrap(),
+        );
+
+        let image = render(PixelFormat::Rgb666, |lcd| {
+            lcd.set_memory_access_control(0x28)?;
+            for i in 0..64 {
+                let x = i as u8 * 4;
+                Pixel(Point::new(i * 2, 10), Rgb888::new(x, 0, 255 - x))
+                    .draw(lcd)
+                    .unwrap();
+                Pixel(Point::new(479 - i, 300), Rgb888::new(0, x, 0))
+                    .draw(lcd)
+                    .unwrap();
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_snapshot(golden("pixels_rotated.ppm"), &image);
+    }
+
+    #[test]
+    fn missing_golden() {
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            return;
+        }
+        let path = scratch_dir("missing").join("missing.ppm");
+        let image = render(PixelFormat::Rgb565, |lcd| lcd.clear_screen()).unwrap();
+        match check_snapshot(&path, &image) {
+            Err(Error::MissingGolden(golden)) => assert_eq!(golden, path),
+            result => panic!("unexpected {:?}", result),
+        }
+        assert!(!path.exists());
+    }
+
+    #[test]
+    #[cfg(feature = "graphics")]
+    fn mismatch() {
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            return;
+        }
+        let dir = scratch_dir("mismatch");
+        let path = dir.join("rects.ppm");
+        fs::copy(golden("rects.ppm"), &path).unwrap();
+
+        let image = render(PixelFormat::Rgb565, |lcd| {
+            rects(lcd)?;
+            rect(
+                lcd,
+                (20, 30),
+                (22, 33),
+                PrimitiveStyle::with_fill(Rgb888::BLACK),
+            );
+            Ok(())
+        })
+        .unwrap();
+        match check_snapshot(&path, &image) {
+            Err(Error::Mismatch {
+                differing, first, ..
+            }) => {
+                assert_eq!(differing, 6);
+                assert_eq!(first, (20, 30));
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+
+        let diff = Image::from_ppm(&fs::read(dir.join("rects.diff.ppm")).unwrap()).unwrap();
+        assert_eq!(diff.pixel(20, 30), (255, 0, 0));
+        assert_eq!(diff.pixel(22, 30), (21, 21, 21));
+        let actual = Image::from_ppm(&fs::read(dir.join("rects.actual.ppm")).unwrap()).unwrap();
+        assert_eq!(actual, image);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn ppm() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            pixels: std::vec![(1, 2, 3), (4, 5, 6)],
+        };
+        assert_eq!(Image::from_ppm(&image.to_ppm()).unwrap(), image);
+
+        let commented = b"P6\n# comment\n2 1\n255\n\x01\x02\x03\x04\x05\x06";
+        assert_eq!(Image::from_ppm(commented).unwrap(), image);
+
+        for invalid in [
+            &b"P6\n2 1\n255\n\x01\x02\x03\x04\x05"[..],
+            b"P3\n2 1\n255\n\x01\x02\x03\x04\x05\x06",
+            b"P6\n2 1\n65535\n\x01\x02\x03\x04\x05\x06",
+            b"P6\n18446744073709551615 2\n255\n\x01\x02\x03",
+            b"P6\n2",
+        ]
+        .iter()
+        {
+            match Image::from_ppm(invalid) {
+                Err(Error::InvalidImage) => {}
+                result => panic!("unexpected {:?}", result),
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use display_interface::DisplayError;
+use embedded_hal::blocking::spi::Write;
+
+pub use self::shift_register::*;
+pub use self::spi3::*;
+pub use self::spi4::*;
+
+mod shift_register;
+mod spi3;
+mod spi4;
+
+/// Number of bytes collected before they are written to the SPI bus.
+const CHUNK: usize = 64;
+
+/// A word that is sent over SPI as big-endian bytes.
+trait SpiWord: Copy {
+    const BYTES: usize;
+
+    fn to_bytes(self, bytes: &mut [u8]);
+}
+
+impl SpiWord for u8 {
+    const BYTES: usize = 1;
+
+    fn to_bytes(self, bytes: &mut [u8]) {
+        bytes[0] = self;
+    }
+}
+
+impl SpiWord for u16 {
+    const BYTES: usize = 2;
+
+    fn to_bytes(self, bytes: &mut [u8]) {
+        bytes[0] = (self >> 8) as u8;
+        bytes[1] = self as u8;
+    }
+}
+
+/// Writes the words of `func` in chunks of up to `CHUNK` bytes.
+fn write_chunks<'a, W, SPI>(
+    spi: &mut SPI,
+    func: &mut dyn FnMut() -> Option<&'a W>,
+) -> Result<(), DisplayError>
+where
+    W: SpiWord + 'a,
+    SPI: Write<u8>,
+{
+    let mut buffer = [0u8; CHUNK];
+    loop {
+        let mut len = 0;
+        while len + W::BYTES <= CHUNK {
+            match func() {
+                Some(word) => {
+                    word.to_bytes(&mut buffer[len..len + W::BYTES]);
+                    len += W::BYTES;
+                }
+                None => break,
+            }
+        }
+        if len > 0 {
+            spi.write(&buffer[..len])
+                .map_err(|_e| DisplayError::BusWriteError)?;
+        }
+        if len + W::BYTES <= CHUNK {
+            return Ok(());
+        }
+    }
+}
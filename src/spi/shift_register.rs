@@ -0,0 +1,236 @@
+use super::write_chunks;
+use display_interface::v2::*;
+use display_interface::DisplayError;
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::OutputPin;
+
+/// The SPI interface of boards with a 16-bit shift register in front of a 16-bit parallel bus,
+/// e.g. the Waveshare and other 3.5" Raspberry Pi HATs.
+///
+/// Every word is written as 16 bits, MSB first, so commands and parameters are padded with a
+/// zero byte and RGB565 pixels are a single big-endian word. Use the driver with `u16` words:
+///
+/// ```ignore
+/// let interface = ShiftRegisterInterface::new(spi, dc, cs)?;
+/// let mut lcd: ILI9486<_, u16> =
+///     ILI9486::new(&mut delay, PixelFormat::Rgb565, interface, OutputOnlyIoPin::new(rst))?
+///         .write_only();
+/// ```
+///
+/// The controller's outputs are not connected, so every read returns `DataFormatNotImplemented`.
+/// Mark the driver [write_only](crate::ILI9486::write_only) so
+/// [set_color_mode](crate::ILI9486::set_color_mode) uses the format without reading it back.
+pub struct ShiftRegisterInterface<SPI, DC, CS>
+where
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    spi: SPI,
+    dc: DC,
+    cs: CS,
+}
+
+impl<SPI, DC, CS> ShiftRegisterInterface<SPI, DC, CS>
+where
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    /// Creates the interface and deselects the controller.
+    pub fn new(
+        spi: SPI,
+        dc: DC,
+        cs: CS,
+    ) -> Result<ShiftRegisterInterface<SPI, DC, CS>, DisplayError> {
+        let mut interface = ShiftRegisterInterface { spi, dc, cs };
+        interface
+            .dc
+            .set_high()
+            .map_err(|_e| DisplayError::DCError)?;
+        interface
+            .cs
+            .set_high()
+            .map_err(|_e| DisplayError::CSError)?;
+        Ok(interface)
+    }
+
+    /// Returns the SPI bus and the pins.
+    pub fn release(self) -> (SPI, DC, CS) {
+        (self.spi, self.dc, self.cs)
+    }
+}
+
+impl<SPI, DC, CS> WriteInterface<u16> for ShiftRegisterInterface<SPI, DC, CS>
+where
+    SPI: Write<u8>,
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    fn write_stream<'a>(
+        &mut self,
+        mode: WriteMode,
+        func: &mut dyn FnMut() -> Option<&'a u16>,
+    ) -> Result<(), DisplayError> {
+        match mode {
+            WriteMode::Command => self.dc.set_low(),
+            _ => self.dc.set_high(),
+        }
+        .map_err(|_e| DisplayError::DCError)?;
+        self.cs.set_low().map_err(|_e| DisplayError::CSError)?;
+        let result = write_chunks(&mut self.spi, func);
+
+        // deselect even if the write failed
+        result.and(self.cs.set_high().map_err(|_e| DisplayError::CSError))
+    }
+}
+
+impl<SPI, DC, CS> ReadInterface<u16> for ShiftRegisterInterface<SPI, DC, CS>
+where
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    fn read_stream(&mut self, _f: &mut dyn FnMut(u16) -> bool) -> Result<(), DisplayError> {
+        Err(DisplayError::DataFormatNotImplemented)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::color::{PixelFormat, PixelWriter};
+    use crate::emulator::{NoDelay, NoPin};
+    use crate::io::shim::OutputOnlyIoPin;
+    use crate::ILI9486;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Cs(bool),
+        Dc(bool),
+        Write(u8),
+    }
+
+    type Log = Rc<RefCell<Vec<Event>>>;
+
+    struct Spi(Log);
+
+    impl Write<u8> for Spi {
+        type Error = ();
+
+        fn write(&mut self, words: &[u8]) -> Result<(), ()> {
+            let mut log = self.0.borrow_mut();
+            log.extend(words.iter().map(|word| Event::Write(*word)));
+            Ok(())
+        }
+    }
+
+    struct Pin(Log, fn(bool) -> Event);
+
+    impl OutputPin for Pin {
+        type Error = ();
+
+        fn set_low(&mut self) -> Result<(), ()> {
+            self.0.borrow_mut().push((self.1)(false));
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), ()> {
+            self.0.borrow_mut().push((self.1)(true));
+            Ok(())
+        }
+    }
+
+    fn interface() -> (ShiftRegisterInterface<Spi, Pin, Pin>, Log) {
+        let log = Log::default();
+        let interface = ShiftRegisterInterface::new(
+            Spi(log.clone()),
+            Pin(log.clone(), Event::Dc),
+            Pin(log.clone(), Event::Cs),
+        )
+        .unwrap();
+        log.borrow_mut().clear();
+        (interface, log)
+    }
+
+    /// The bytes of the last command and its parameters.
+    fn parameters(log: &Log) -> Vec<u8> {
+        let log = log.borrow();
+        let start = log
+            .iter()
+            .rposition(|event| *event == Event::Dc(false))
+            .unwrap();
+        log[start..]
+            .iter()
+            .filter_map(|event| match event {
+                Event::Write(byte) => Some(*byte),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pads_commands_and_parameters() {
+        let (mut spi, log) = interface();
+        spi.write(WriteMode::Command, &[0x36u16]).unwrap();
+        spi.write(WriteMode::Data, &[0x48u16]).unwrap();
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                Event::Dc(false),
+                Event::Cs(false),
+                Event::Write(0x00),
+                Event::Write(0x36),
+                Event::Cs(true),
+                Event::Dc(true),
+                Event::Cs(false),
+                Event::Write(0x00),
+                Event::Write(0x48),
+                Event::Cs(true)
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_rgb565_big_endian() {
+        let (mut spi, log) = interface();
+        spi.write_pixel_data(&PixelFormat::Rgb565, &(31, 7, 1), Some(&(0, 63, 0)))
+            .unwrap();
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                Event::Dc(true),
+                Event::Cs(false),
+                Event::Write(0xf8),
+                Event::Write(0xe1),
+                Event::Write(0x07),
+                Event::Write(0xe0),
+                Event::Cs(true)
+            ]
+        );
+    }
+
+    #[test]
+    fn sets_the_color_mode_without_reading_it_back() {
+        let (spi, log) = interface();
+        let mut lcd: ILI9486<_, u16> = ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb565,
+            spi,
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap();
+        // the readback fails on a write-only interface
+        assert!(matches!(
+            lcd.set_color_mode(PixelFormat::Rgb666),
+            Err(DisplayError::DataFormatNotImplemented)
+        ));
+        assert_eq!(lcd.color_mode(), PixelFormat::Rgb565);
+
+        let mut lcd = lcd.write_only();
+        lcd.set_color_mode(PixelFormat::Rgb666).unwrap();
+        assert_eq!(lcd.color_mode(), PixelFormat::Rgb666);
+        assert_eq!(parameters(&log), [0x00, 0x3a, 0x00, 0x66]);
+    }
+}
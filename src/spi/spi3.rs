@@ -0,0 +1,514 @@
+use crate::gpio::{pause, DelayNs};
+use crate::{Command, IoPin};
+use core::convert::TryFrom;
+use display_interface::v2::*;
+use display_interface::DisplayError;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// Minimum SCL cycles of the serial interface in nanoseconds, zero skips the delay.
+///
+/// Half of each cycle is spent with SCL low and half with SCL high.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SerialTiming {
+    /// SCL cycle while writing
+    pub write_cycle: u32,
+    /// SCL cycle while reading
+    pub read_cycle: u32,
+    /// Time from CSX low to the first rising edge of SCL
+    pub cs_setup: u32,
+}
+
+impl SerialTiming {
+    /// The minimums of the ILI9486 datasheet for the serial interface.
+    pub const ILI9486: SerialTiming = SerialTiming {
+        write_cycle: 66,
+        read_cycle: 150,
+        cs_setup: 15,
+    };
+}
+
+/// A bit-banged 3-wire serial interface, SCL, SDA and CSX, for boards without a D/CX line.
+///
+/// Every word is sent as 9 bits, MSB first, starting with the D/CX flag, 0 for commands and 1
+/// for data. SDA is set while SCL is low and sampled by the controller on the rising edge.
+///
+/// The controller only answers a read command in the same transaction, so CSX stays low after a
+/// command and is raised after the parameters or the response. SDA is an input only while a
+/// response is read. Register reads have no dummy word on the serial interface, the interface
+/// reports a zero first so the responses line up with the parallel bus, see
+/// [dummy_reads](Command::dummy_reads). `ReadDisplayId` and `ReadDisplayStatus` start with a
+/// dummy clock, which is skipped. Frame memory reads start with a dummy byte.
+///
+/// ```ignore
+/// let interface = ThreeWireInterface::new(scl, sda, cs)?
+///     .with_timing(SerialTiming::ILI9486, delay);
+/// ```
+pub struct ThreeWireInterface<SCL, SDA, CS, D = ()>
+where
+    SCL: OutputPin,
+    SDA: IoPin,
+    CS: OutputPin,
+{
+    scl: SCL,
+    sda: SDA,
+    cs: CS,
+    timing: SerialTiming,
+    delay: D,
+    /// Whether CSX was left low after a command
+    selected: bool,
+    /// The last command sent, it decides the dummy cycles of a read
+    command: Option<Command>,
+}
+
+impl<SCL, SDA, CS> ThreeWireInterface<SCL, SDA, CS>
+where
+    SCL: OutputPin,
+    SDA: IoPin,
+    CS: OutputPin,
+{
+    /// Creates the interface with SCL low and the controller deselected.
+    pub fn new(
+        scl: SCL,
+        sda: SDA,
+        cs: CS,
+    ) -> Result<ThreeWireInterface<SCL, SDA, CS>, DisplayError> {
+        let mut interface = ThreeWireInterface {
+            scl,
+            sda,
+            cs,
+            timing: SerialTiming::default(),
+            delay: (),
+            selected: false,
+            command: None,
+        };
+        interface
+            .cs
+            .set_high()
+            .map_err(|_e| DisplayError::CSError)?;
+        wrap_output_err!(interface.scl.set_low())?;
+        wrap_output_err!(interface.sda.into_output().set_low())?;
+        Ok(interface)
+    }
+}
+
+impl<SCL, SDA, CS, D> ThreeWireInterface<SCL, SDA, CS, D>
+where
+    SCL: OutputPin,
+    SDA: IoPin,
+    CS: OutputPin,
+    D: DelayNs,
+{
+    /// Uses `delay` to hold SCL for at least the cycles in `timing`.
+    pub fn with_timing<E>(
+        self,
+        timing: SerialTiming,
+        delay: E,
+    ) -> ThreeWireInterface<SCL, SDA, CS, E>
+    where
+        E: DelayNs,
+    {
+        ThreeWireInterface {
+            scl: self.scl,
+            sda: self.sda,
+            cs: self.cs,
+            timing,
+            delay,
+            selected: self.selected,
+            command: self.command,
+        }
+    }
+
+    pub fn timing(&self) -> SerialTiming {
+        self.timing
+    }
+
+    /// Returns the pins.
+    pub fn release(self) -> (SCL, SDA, CS) {
+        (self.scl, self.sda, self.cs)
+    }
+
+    fn select(&mut self) -> Result<(), DisplayError> {
+        if !self.selected {
+            self.cs.set_low().map_err(|_e| DisplayError::CSError)?;
+            self.selected = true;
+            pause(&mut self.delay, self.timing.cs_setup);
+        }
+        Ok(())
+    }
+
+    /// Raises CSX and drives SDA again.
+    fn deselect(&mut self) -> Result<(), DisplayError> {
+        self.selected = false;
+        let cs = self.cs.set_high().map_err(|_e| DisplayError::CSError);
+        cs.and(wrap_output_err!(self.sda.into_output().set_low()))
+    }
+}
+
+/// Sends every word as 9 bits with the D/CX flag first, returns the last word.
+fn write_words<'a, SCL, SDA>(
+    scl: &mut SCL,
+    sda: &mut SDA,
+    delay: &mut dyn DelayNs,
+    cycle: u32,
+    data: bool,
+    func: &mut dyn FnMut() -> Option<&'a u8>,
+) -> Result<Option<u8>, DisplayError>
+where
+    SCL: OutputPin,
+    SDA: OutputPin,
+{
+    let mut last = None;
+    while let Some(word) = func() {
+        let bits = (u16::from(data) << 8) | u16::from(*word);
+        for bit in (0..9).rev() {
+            write_bit!(sda, bits & (1 << bit) != 0);
+            pause(delay, cycle / 2);
+            wrap_output_err!(scl.set_high())?;
+            pause(delay, cycle - cycle / 2);
+            wrap_output_err!(scl.set_low())?;
+        }
+        last = Some(*word);
+    }
+    Ok(last)
+}
+
+/// Clocks in `count` bits, MSB first, the controller sets SDA on the falling edge of SCL.
+fn read_bits<SCL, SDA>(
+    scl: &mut SCL,
+    sda: &mut SDA,
+    delay: &mut dyn DelayNs,
+    cycle: u32,
+    count: usize,
+) -> Result<u8, DisplayError>
+where
+    SCL: OutputPin,
+    SDA: InputPin,
+{
+    let mut value = 0;
+    for _ in 0..count {
+        pause(delay, cycle / 2);
+        wrap_output_err!(scl.set_high())?;
+        let high = wrap_input_err!(sda.is_high())?;
+        value = (value << 1) | u8::from(high);
+        pause(delay, cycle - cycle / 2);
+        wrap_output_err!(scl.set_low())?;
+    }
+    Ok(value)
+}
+
+/// Reads the response to `command` a byte at a time until `f` returns `false`.
+fn read_words<SCL, SDA>(
+    scl: &mut SCL,
+    sda: &mut SDA,
+    delay: &mut dyn DelayNs,
+    cycle: u32,
+    command: Option<Command>,
+    f: &mut dyn FnMut(u8) -> bool,
+) -> Result<(), DisplayError>
+where
+    SCL: OutputPin,
+    SDA: InputPin,
+{
+    match command {
+        Some(Command::MemoryRead) | Some(Command::MemoryReadContinue) => {}
+        Some(Command::ReadDisplayId) | Some(Command::ReadDisplayStatus) => {
+            // 24 and 32-bit responses start with a dummy clock
+            read_bits(scl, sda, delay, cycle, 1)?;
+            if !f(0) {
+                return Ok(());
+            }
+        }
+        _ => {
+            if !f(0) {
+                return Ok(());
+            }
+        }
+    }
+
+    loop {
+        if !f(read_bits(scl, sda, delay, cycle, 8)?) {
+            return Ok(());
+        }
+    }
+}
+
+impl<SCL, SDA, CS, D> WriteInterface<u8> for ThreeWireInterface<SCL, SDA, CS, D>
+where
+    SCL: OutputPin,
+    SDA: IoPin,
+    CS: OutputPin,
+    D: DelayNs,
+{
+    fn write_stream<'a>(
+        &mut self,
+        mode: WriteMode,
+        func: &mut dyn FnMut() -> Option<&'a u8>,
+    ) -> Result<(), DisplayError> {
+        let command = matches!(mode, WriteMode::Command);
+        self.select()?;
+        let cycle = self.timing.write_cycle;
+        let sda = self.sda.into_output();
+        let result = write_words(&mut self.scl, sda, &mut self.delay, cycle, !command, func);
+
+        match result {
+            // the parameters or the response follow in the same transaction
+            Ok(last) if command => {
+                if let Some(last) = last {
+                    self.command = Command::try_from(last).ok();
+                }
+                Ok(())
+            }
+            // deselect even if the write failed
+            result => result.map(|_last| ()).and(self.deselect()),
+        }
+    }
+}
+
+impl<SCL, SDA, CS, D> ReadInterface<u8> for ThreeWireInterface<SCL, SDA, CS, D>
+where
+    SCL: OutputPin,
+    SDA: IoPin,
+    CS: OutputPin,
+    D: DelayNs,
+{
+    fn read_stream(&mut self, f: &mut dyn FnMut(u8) -> bool) -> Result<(), DisplayError> {
+        self.select()?;
+        let cycle = self.timing.read_cycle;
+        let command = self.command;
+        let sda = self.sda.into_input();
+        let result = read_words(&mut self.scl, sda, &mut self.delay, cycle, command, f);
+
+        // deselect even if the read failed
+        result.and(self.deselect())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Cs(bool),
+        /// SDA switched to an input or back to an output
+        Input(bool),
+        /// 9 bits clocked out, the D/CX flag first
+        Word(u16),
+        /// A rising edge of SCL while SDA is an input
+        Clock,
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Line {
+        Scl,
+        Sda,
+        Cs,
+    }
+
+    /// Decodes the serial bus, the controller answers reads with `responses`, then zeros.
+    #[derive(Default)]
+    struct Serial {
+        log: Vec<Event>,
+        scl: bool,
+        sda: bool,
+        input: bool,
+        /// Bits clocked out since the last complete word
+        bits: Vec<bool>,
+        responses: VecDeque<bool>,
+    }
+
+    type State = Rc<RefCell<Serial>>;
+
+    struct Pin(State, Line);
+
+    impl Pin {
+        fn set(&mut self, high: bool) -> Result<(), ()> {
+            let mut serial = self.0.borrow_mut();
+            match self.1 {
+                Line::Cs => serial.log.push(Event::Cs(high)),
+                Line::Sda => {
+                    assert!(!serial.input, "SDA driven while it is an input");
+                    assert!(!serial.scl, "SDA changed while SCL is high");
+                    serial.sda = high;
+                }
+                Line::Scl if high && !serial.scl => {
+                    if serial.input {
+                        serial.sda = serial.responses.pop_front().unwrap_or(false);
+                        serial.log.push(Event::Clock);
+                    } else {
+                        let sda = serial.sda;
+                        serial.bits.push(sda);
+                        if serial.bits.len() == 9 {
+                            let word = serial
+                                .bits
+                                .drain(..)
+                                .fold(0, |word, bit| (word << 1) | u16::from(bit));
+                            serial.log.push(Event::Word(word));
+                        }
+                    }
+                }
+                Line::Scl => {}
+            }
+            if self.1 == Line::Scl {
+                serial.scl = high;
+            }
+            Ok(())
+        }
+
+        fn direction(&mut self, input: bool) -> &mut Self {
+            let mut serial = self.0.borrow_mut();
+            if serial.input != input {
+                serial.input = input;
+                serial.log.push(Event::Input(input));
+            }
+            drop(serial);
+            self
+        }
+    }
+
+    impl OutputPin for Pin {
+        type Error = ();
+
+        fn set_low(&mut self) -> Result<(), ()> {
+            self.set(false)
+        }
+
+        fn set_high(&mut self) -> Result<(), ()> {
+            self.set(true)
+        }
+    }
+
+    impl InputPin for Pin {
+        type Error = ();
+
+        fn is_high(&self) -> Result<bool, ()> {
+            Ok(self.0.borrow().sda)
+        }
+
+        fn is_low(&self) -> Result<bool, ()> {
+            self.is_high().map(|high| !high)
+        }
+    }
+
+    impl IoPin for Pin {
+        type InputPinError = ();
+        type OutputPinError = ();
+        type Input = Self;
+        type Output = Self;
+
+        fn into_input(&mut self) -> &mut Self {
+            self.direction(true)
+        }
+
+        fn into_output(&mut self) -> &mut Self {
+            self.direction(false)
+        }
+    }
+
+    fn interface() -> (ThreeWireInterface<Pin, Pin, Pin>, State) {
+        let state = State::default();
+        let interface = ThreeWireInterface::new(
+            Pin(state.clone(), Line::Scl),
+            Pin(state.clone(), Line::Sda),
+            Pin(state.clone(), Line::Cs),
+        )
+        .unwrap();
+        state.borrow_mut().log.clear();
+        (interface, state)
+    }
+
+    /// Queues `bytes` as the response bits, MSB first.
+    fn respond(state: &State, dummy_clock: bool, bytes: &[u8]) {
+        let mut serial = state.borrow_mut();
+        if dummy_clock {
+            serial.responses.push_back(false);
+        }
+        for byte in bytes {
+            serial
+                .responses
+                .extend((0..8).rev().map(|bit| byte & (1 << bit) != 0));
+        }
+    }
+
+    #[test]
+    fn frames_words_with_the_dc_flag() {
+        let (mut spi, state) = interface();
+        spi.write(WriteMode::Command, &[0x2au8]).unwrap();
+        spi.write(WriteMode::Data, &[0x00u8, 0x01, 0x3f]).unwrap();
+        spi.write(WriteMode::Command, &[0x29u8]).unwrap();
+        spi.write(WriteMode::Data, &[]).unwrap();
+
+        let serial = state.borrow();
+        assert_eq!(
+            serial.log,
+            vec![
+                Event::Cs(false),
+                Event::Word(0x02a),
+                Event::Word(0x100),
+                Event::Word(0x101),
+                Event::Word(0x13f),
+                Event::Cs(true),
+                Event::Cs(false),
+                Event::Word(0x029),
+                Event::Cs(true)
+            ]
+        );
+        assert!(serial.bits.is_empty() && !serial.scl);
+    }
+
+    #[test]
+    fn reads_in_the_command_transaction() {
+        let (mut spi, state) = interface();
+        respond(&state, false, &[0x9c]);
+        spi.write(WriteMode::Command, &[0x0au8]).unwrap();
+        let mut response = [0xffu8; 2];
+        spi.read(&mut response).unwrap();
+
+        // a zero in place of the dummy word, no dummy clock
+        assert_eq!(response, [0, 0x9c]);
+        let mut expected = vec![Event::Cs(false), Event::Word(0x0a), Event::Input(true)];
+        expected.extend((0..8).map(|_| Event::Clock));
+        expected.extend(vec![Event::Cs(true), Event::Input(false)]);
+        assert_eq!(state.borrow().log, expected);
+    }
+
+    #[test]
+    fn shifts_out_the_dummy_clock() {
+        for (command, bytes) in [(0x04u8, &[0x12u8, 0x34, 0x56][..]), (0x09, &[1, 2, 3, 4])].iter()
+        {
+            let (mut spi, state) = interface();
+            respond(&state, true, bytes);
+            spi.write(WriteMode::Command, &[*command]).unwrap();
+            let mut response = vec![0xffu8; bytes.len() + 1];
+            spi.read(&mut response).unwrap();
+
+            assert_eq!(response[0], 0);
+            assert_eq!(&response[1..], *bytes);
+            let log = &state.borrow().log;
+            let clocks = log.iter().filter(|event| **event == Event::Clock).count();
+            assert_eq!(clocks, 1 + 8 * bytes.len());
+        }
+    }
+
+    #[test]
+    fn reads_the_dummy_byte_of_memory_reads() {
+        let (mut spi, state) = interface();
+        respond(&state, false, &[0xaa, 0xfc, 0x00, 0x80]);
+        spi.write(WriteMode::Command, &[0x2eu8]).unwrap();
+        let mut response = [0u8; 4];
+        spi.read(&mut response).unwrap();
+
+        assert_eq!(response, [0xaa, 0xfc, 0x00, 0x80]);
+        let log = &state.borrow().log;
+        assert_eq!(
+            log.iter().filter(|event| **event == Event::Clock).count(),
+            32
+        );
+        assert_eq!(log[log.len() - 2..], [Event::Cs(true), Event::Input(false)]);
+    }
+}
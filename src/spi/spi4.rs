@@ -0,0 +1,339 @@
+use super::write_chunks;
+use crate::Command;
+use core::convert::TryFrom;
+use display_interface::v2::*;
+use display_interface::DisplayError;
+use embedded_hal::blocking::spi::{Transfer, Write};
+use embedded_hal::digital::v2::OutputPin;
+
+/// A 4-wire serial interface, SCL, SDA, D/CX and CSX, on top of an `embedded-hal` SPI bus.
+///
+/// D/CX is set before CSX falls and CSX is raised after every stream, also when the transfer
+/// fails. Words are collected into a 64 byte buffer that is written with a single SPI write, so
+/// fills with `write_repeated_pixel_data` go out in chunks instead of one transaction per byte.
+///
+/// The controller only answers a read command in the same transaction, so CSX stays low after a
+/// read command until the response is read. Reads clock out a zero for every byte. Register reads
+/// have no dummy word on the serial interface, the interface reports a zero first so the
+/// responses line up with the parallel bus, see [dummy_reads](Command::dummy_reads).
+/// `ReadDisplayId` and `ReadDisplayStatus` start with a dummy clock, so their bytes are read one
+/// bit late and shifted back. Frame memory reads start with a dummy byte.
+///
+/// ```ignore
+/// let spi = Spi::spi2(dp.SPI2, pins, spi_mode, 8.mhz(), clocks, &mut rcc.apb1);
+/// let interface = SpiInterface::new(spi, dc, cs)?;
+/// ```
+pub struct SpiInterface<SPI, DC, CS>
+where
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    spi: SPI,
+    dc: DC,
+    cs: CS,
+    /// Whether CSX was left low after a read command
+    selected: bool,
+    /// The read command whose response follows
+    command: Option<Command>,
+}
+
+impl<SPI, DC, CS> SpiInterface<SPI, DC, CS>
+where
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    /// Creates the interface and deselects the controller.
+    pub fn new(spi: SPI, dc: DC, cs: CS) -> Result<SpiInterface<SPI, DC, CS>, DisplayError> {
+        let mut interface = SpiInterface {
+            spi,
+            dc,
+            cs,
+            selected: false,
+            command: None,
+        };
+        interface
+            .dc
+            .set_high()
+            .map_err(|_e| DisplayError::DCError)?;
+        interface
+            .cs
+            .set_high()
+            .map_err(|_e| DisplayError::CSError)?;
+        Ok(interface)
+    }
+
+    /// Returns the SPI bus and the pins.
+    pub fn release(self) -> (SPI, DC, CS) {
+        (self.spi, self.dc, self.cs)
+    }
+
+    /// Sets D/CX and selects the controller, a pending read command is dropped.
+    fn select(&mut self, data: bool) -> Result<(), DisplayError> {
+        if self.selected {
+            self.deselect()?;
+        }
+        if data {
+            self.dc.set_high()
+        } else {
+            self.dc.set_low()
+        }
+        .map_err(|_e| DisplayError::DCError)?;
+        self.cs.set_low().map_err(|_e| DisplayError::CSError)?;
+        self.selected = true;
+        Ok(())
+    }
+
+    fn deselect(&mut self) -> Result<(), DisplayError> {
+        self.selected = false;
+        self.command = None;
+        self.cs.set_high().map_err(|_e| DisplayError::CSError)
+    }
+}
+
+/// Clocks in a byte.
+fn read_byte<SPI>(spi: &mut SPI) -> Result<u8, DisplayError>
+where
+    SPI: Transfer<u8>,
+{
+    let mut word = [0u8];
+    let read = spi
+        .transfer(&mut word)
+        .map_err(|_e| DisplayError::BusReadError)?;
+    Ok(read[0])
+}
+
+/// Reads the response to `command` a byte at a time until `f` returns `false`.
+fn read_bytes<SPI>(
+    spi: &mut SPI,
+    command: Option<Command>,
+    f: &mut dyn FnMut(u8) -> bool,
+) -> Result<(), DisplayError>
+where
+    SPI: Transfer<u8>,
+{
+    match command {
+        Some(Command::MemoryRead) | Some(Command::MemoryReadContinue) => {}
+        Some(Command::ReadDisplayId) | Some(Command::ReadDisplayStatus) => {
+            if !f(0) {
+                return Ok(());
+            }
+            // 24 and 32-bit responses start with a dummy clock, every byte ends in the next one
+            let mut byte = read_byte(spi)?;
+            loop {
+                let next = read_byte(spi)?;
+                if !f((byte << 1) | (next >> 7)) {
+                    return Ok(());
+                }
+                byte = next;
+            }
+        }
+        _ => {
+            if !f(0) {
+                return Ok(());
+            }
+        }
+    }
+
+    loop {
+        if !f(read_byte(spi)?) {
+            return Ok(());
+        }
+    }
+}
+
+impl<SPI, DC, CS> WriteInterface<u8> for SpiInterface<SPI, DC, CS>
+where
+    SPI: Write<u8>,
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    fn write_stream<'a>(
+        &mut self,
+        mode: WriteMode,
+        func: &mut dyn FnMut() -> Option<&'a u8>,
+    ) -> Result<(), DisplayError> {
+        let data = !matches!(mode, WriteMode::Command);
+        self.select(data)?;
+        let mut last = None;
+        let result = write_chunks(&mut self.spi, &mut || {
+            let word = func();
+            if let Some(word) = word {
+                last = Some(*word);
+            }
+            word
+        });
+
+        let command = last
+            .and_then(|word| Command::try_from(word).ok())
+            .filter(Command::is_read);
+        match result {
+            // the response follows in the same transaction
+            Ok(()) if !data && command.is_some() => {
+                self.command = command;
+                Ok(())
+            }
+            // deselect even if the write failed
+            result => result.and(self.deselect()),
+        }
+    }
+}
+
+impl<SPI, DC, CS> ReadInterface<u8> for SpiInterface<SPI, DC, CS>
+where
+    SPI: Transfer<u8>,
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    fn read_stream(&mut self, f: &mut dyn FnMut(u8) -> bool) -> Result<(), DisplayError> {
+        if !self.selected {
+            self.select(true)?;
+        }
+        let command = self.command;
+        let result = read_bytes(&mut self.spi, command, f);
+
+        // deselect even if the read failed
+        result.and(self.deselect())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Cs(bool),
+        Dc(bool),
+        Write(u8),
+        Read(u8),
+    }
+
+    type Log = Rc<RefCell<Vec<Event>>>;
+
+    /// Logs the written bytes and answers reads with `responses`, then zeros.
+    struct Spi {
+        log: Log,
+        responses: VecDeque<u8>,
+    }
+
+    impl Write<u8> for Spi {
+        type Error = ();
+
+        fn write(&mut self, words: &[u8]) -> Result<(), ()> {
+            let mut log = self.log.borrow_mut();
+            log.extend(words.iter().map(|word| Event::Write(*word)));
+            Ok(())
+        }
+    }
+
+    impl Transfer<u8> for Spi {
+        type Error = ();
+
+        fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], ()> {
+            for word in words.iter_mut() {
+                *word = self.responses.pop_front().unwrap_or(0);
+                self.log.borrow_mut().push(Event::Read(*word));
+            }
+            Ok(words)
+        }
+    }
+
+    struct Pin(Log, fn(bool) -> Event);
+
+    impl OutputPin for Pin {
+        type Error = ();
+
+        fn set_low(&mut self) -> Result<(), ()> {
+            self.0.borrow_mut().push((self.1)(false));
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), ()> {
+            self.0.borrow_mut().push((self.1)(true));
+            Ok(())
+        }
+    }
+
+    fn interface(responses: &[u8]) -> (SpiInterface<Spi, Pin, Pin>, Log) {
+        let log = Log::default();
+        let spi = Spi {
+            log: log.clone(),
+            responses: responses.iter().cloned().collect(),
+        };
+        let interface = SpiInterface::new(
+            spi,
+            Pin(log.clone(), Event::Dc),
+            Pin(log.clone(), Event::Cs),
+        )
+        .unwrap();
+        log.borrow_mut().clear();
+        (interface, log)
+    }
+
+    #[test]
+    fn reads_in_the_command_transaction() {
+        let (mut spi, log) = interface(&[0x55]);
+        spi.write(WriteMode::Command, &[0x0cu8]).unwrap();
+        let mut response = [0xffu8; 2];
+        spi.read(&mut response).unwrap();
+
+        // a zero in place of the dummy word
+        assert_eq!(response, [0, 0x55]);
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                Event::Dc(false),
+                Event::Cs(false),
+                Event::Write(0x0c),
+                Event::Read(0x55),
+                Event::Cs(true)
+            ]
+        );
+    }
+
+    #[test]
+    fn shifts_out_the_dummy_clock() {
+        // 0x12, 0x34, 0x56 one bit late
+        let (mut spi, _log) = interface(&[0x09, 0x1a, 0x2b, 0x00]);
+        spi.write(WriteMode::Command, &[0x04u8]).unwrap();
+        let mut response = [0xffu8; 4];
+        spi.read(&mut response).unwrap();
+        assert_eq!(response, [0, 0x12, 0x34, 0x56]);
+    }
+
+    #[test]
+    fn deselects_after_other_commands() {
+        let (mut spi, log) = interface(&[]);
+        spi.write(WriteMode::Command, &[0x2cu8]).unwrap();
+        spi.write(WriteMode::Data, &[0x01u8]).unwrap();
+        // a read command that is not followed by a read
+        spi.write(WriteMode::Command, &[0x0au8]).unwrap();
+        spi.write(WriteMode::Command, &[0x29u8]).unwrap();
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                Event::Dc(false),
+                Event::Cs(false),
+                Event::Write(0x2c),
+                Event::Cs(true),
+                Event::Dc(true),
+                Event::Cs(false),
+                Event::Write(0x01),
+                Event::Cs(true),
+                Event::Dc(false),
+                Event::Cs(false),
+                Event::Write(0x0a),
+                Event::Cs(true),
+                Event::Dc(false),
+                Event::Cs(false),
+                Event::Write(0x29),
+                Event::Cs(true)
+            ]
+        );
+    }
+}
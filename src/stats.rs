@@ -0,0 +1,382 @@
+//! Bus statistics.
+//!
+//! [Counting](Counting) wraps the interface of the driver and counts what is sent over the bus,
+//! e.g. to find redundant address window writes in a drawing call:
+//!
+//! ```ignore
+//! let mut lcd = ILI9486::new(&mut delay, PixelFormat::Rgb565, Counting::new(interface), rst)?;
+//! let before = lcd.interface().stats();
+//! circle.draw(&mut lcd)?;
+//! let stats = lcd.interface().stats() - before;
+//! ```
+
+use crate::Command;
+use core::fmt;
+use core::ops::{Add, AddAssign, Sub};
+use display_interface::v2::*;
+use display_interface::DisplayError;
+
+/// Counters for the traffic on the bus.
+///
+/// The counters wrap on overflow, the difference of two snapshots taken with
+/// [stats](Counting::stats) is still correct across the wrap.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Command words written
+    pub commands: u32,
+    /// Parameter words written, excluding pixel data
+    pub parameter_words: u32,
+    /// `MemoryWrite` and `MemoryWriteContinue` commands
+    pub memory_writes: u32,
+    /// Pixel data words written after a memory write
+    pub memory_words: u32,
+    /// Words read, including dummy reads
+    pub read_words: u32,
+    /// `ColumnAddressSet` and `PageAddressSet` commands that changed the window
+    pub window_changes: u32,
+    /// `ColumnAddressSet` and `PageAddressSet` commands that set the window that was already set
+    pub redundant_window_writes: u32,
+}
+
+impl Stats {
+    /// All words written and read.
+    pub fn total_words(&self) -> u32 {
+        self.commands
+            .wrapping_add(self.parameter_words)
+            .wrapping_add(self.memory_words)
+            .wrapping_add(self.read_words)
+    }
+}
+
+impl Add for Stats {
+    type Output = Stats;
+
+    fn add(self, other: Stats) -> Stats {
+        Stats {
+            commands: self.commands.wrapping_add(other.commands),
+            parameter_words: self.parameter_words.wrapping_add(other.parameter_words),
+            memory_writes: self.memory_writes.wrapping_add(other.memory_writes),
+            memory_words: self.memory_words.wrapping_add(other.memory_words),
+            read_words: self.read_words.wrapping_add(other.read_words),
+            window_changes: self.window_changes.wrapping_add(other.window_changes),
+            redundant_window_writes: self
+                .redundant_window_writes
+                .wrapping_add(other.redundant_window_writes),
+        }
+    }
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, other: Stats) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Stats {
+    type Output = Stats;
+
+    /// The difference between two snapshots of the counters.
+    fn sub(self, other: Stats) -> Stats {
+        Stats {
+            commands: self.commands.wrapping_sub(other.commands),
+            parameter_words: self.parameter_words.wrapping_sub(other.parameter_words),
+            memory_writes: self.memory_writes.wrapping_sub(other.memory_writes),
+            memory_words: self.memory_words.wrapping_sub(other.memory_words),
+            read_words: self.read_words.wrapping_sub(other.read_words),
+            window_changes: self.window_changes.wrapping_sub(other.window_changes),
+            redundant_window_writes: self
+                .redundant_window_writes
+                .wrapping_sub(other.redundant_window_writes),
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} commands, {} parameter words, {} memory writes ({} words), {} read words, \
+             {} window changes, {} redundant window writes",
+            self.commands,
+            self.parameter_words,
+            self.memory_writes,
+            self.memory_words,
+            self.read_words,
+            self.window_changes,
+            self.redundant_window_writes
+        )
+    }
+}
+
+/// An address window command whose parameters are being collected.
+struct WindowWrite {
+    command: Command,
+    parameters: [u8; 4],
+    len: usize,
+}
+
+/// Tracks the command in progress and the last address window.
+#[derive(Default)]
+struct Counter {
+    stats: Stats,
+    command: Option<u8>,
+    window: Option<WindowWrite>,
+    column: Option<[u8; 4]>,
+    page: Option<[u8; 4]>,
+}
+
+impl Counter {
+    fn command_word(&mut self, word: u32) {
+        self.finish_window();
+        self.stats.commands = self.stats.commands.wrapping_add(1);
+        self.command = Some(word as u8);
+
+        if word == Command::MemoryWrite as u32 || word == Command::MemoryWriteContinue as u32 {
+            self.stats.memory_writes = self.stats.memory_writes.wrapping_add(1);
+        }
+        if word == Command::ColumnAddressSet as u32 {
+            self.start_window(Command::ColumnAddressSet);
+        } else if word == Command::PageAddressSet as u32 {
+            self.start_window(Command::PageAddressSet);
+        }
+    }
+
+    fn data_word(&mut self, word: u32) {
+        let memory = self.command == Some(Command::MemoryWrite as u8)
+            || self.command == Some(Command::MemoryWriteContinue as u8);
+        if memory {
+            self.stats.memory_words = self.stats.memory_words.wrapping_add(1);
+            return;
+        }
+
+        self.stats.parameter_words = self.stats.parameter_words.wrapping_add(1);
+        let complete = match self.window {
+            Some(ref mut window) if window.len < 4 => {
+                // parameters are in the low 8 bits on every bus width
+                window.parameters[window.len] = word as u8;
+                window.len += 1;
+                window.len == 4
+            }
+            _ => false,
+        };
+        if complete {
+            self.finish_window();
+        }
+    }
+
+    fn start_window(&mut self, command: Command) {
+        self.window = Some(WindowWrite {
+            command,
+            parameters: [0; 4],
+            len: 0,
+        });
+    }
+
+    fn finish_window(&mut self) {
+        let window = match self.window.take() {
+            Some(window) if window.len == 4 => window,
+            // incomplete, the controller ignores it
+            _ => return,
+        };
+        let last = match window.command {
+            Command::ColumnAddressSet => &mut self.column,
+            _ => &mut self.page,
+        };
+        if *last == Some(window.parameters) {
+            self.stats.redundant_window_writes = self.stats.redundant_window_writes.wrapping_add(1);
+        } else {
+            self.stats.window_changes = self.stats.window_changes.wrapping_add(1);
+            *last = Some(window.parameters);
+        }
+    }
+}
+
+/// Wraps an interface and counts the words written to and read from it.
+pub struct Counting<RW> {
+    inner: RW,
+    counter: Counter,
+}
+
+impl<RW> Counting<RW> {
+    pub fn new(inner: RW) -> Counting<RW> {
+        Counting {
+            inner,
+            counter: Counter::default(),
+        }
+    }
+
+    /// The counters since creation or the last [take](Counting::take) or [reset](Counting::reset).
+    pub fn stats(&self) -> Stats {
+        self.counter.stats
+    }
+
+    /// Returns the counters and resets them, e.g. after every drawing call.
+    pub fn take(&mut self) -> Stats {
+        let stats = self.counter.stats;
+        self.reset();
+        stats
+    }
+
+    /// Resets the counters, the last address window is kept.
+    pub fn reset(&mut self) {
+        self.counter.stats = Stats::default();
+    }
+
+    pub fn inner(&mut self) -> &mut RW {
+        &mut self.inner
+    }
+
+    pub fn release(self) -> RW {
+        self.inner
+    }
+}
+
+impl<RW, T> WriteInterface<T> for Counting<RW>
+where
+    RW: WriteInterface<T>,
+    T: Copy + Into<u32>,
+{
+    fn write_stream<'a>(
+        &mut self,
+        mode: WriteMode,
+        func: &mut dyn FnMut() -> Option<&'a T>,
+    ) -> Result<(), DisplayError> {
+        let command = matches!(mode, WriteMode::Command);
+        let counter = &mut self.counter;
+
+        self.inner.write_stream(mode, &mut || {
+            let word = func();
+            if let Some(word) = word {
+                if command {
+                    counter.command_word((*word).into());
+                } else {
+                    counter.data_word((*word).into());
+                }
+            }
+            word
+        })
+    }
+}
+
+impl<RW, T> ReadInterface<T> for Counting<RW>
+where
+    RW: ReadInterface<T>,
+{
+    fn read_stream(&mut self, f: &mut dyn FnMut(T) -> bool) -> Result<(), DisplayError> {
+        let stats = &mut self.counter.stats;
+        self.inner.read_stream(&mut |word| {
+            stats.read_words = stats.read_words.wrapping_add(1);
+            f(word)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic_wraps() {
+        let max = Stats {
+            commands: u32::MAX,
+            read_words: u32::MAX,
+            ..Stats::default()
+        };
+        let one = Stats {
+            commands: 1,
+            read_words: 2,
+            ..Stats::default()
+        };
+
+        let sum = max + one;
+        assert_eq!(sum.commands, 0);
+        assert_eq!(sum.read_words, 1);
+        assert_eq!(sum - max, one);
+
+        let mut total = max;
+        total += one;
+        assert_eq!(total, sum);
+        assert_eq!(max.total_words(), u32::MAX - 1);
+    }
+
+    #[test]
+    fn counts_window_writes() {
+        let mut counter = Counter::default();
+        let window = |counter: &mut Counter, command: Command, parameters: [u8; 4]| {
+            counter.command_word(command as u32);
+            for parameter in parameters.iter() {
+                counter.data_word(*parameter as u32);
+            }
+        };
+
+        window(&mut counter, Command::ColumnAddressSet, [0, 0, 0x01, 0x3f]);
+        window(&mut counter, Command::PageAddressSet, [0, 0, 0x01, 0xdf]);
+        window(&mut counter, Command::ColumnAddressSet, [0, 0, 0x01, 0x3f]);
+        window(&mut counter, Command::PageAddressSet, [0, 1, 0x01, 0xdf]);
+        // incomplete, it is not counted
+        counter.command_word(Command::ColumnAddressSet as u32);
+        counter.data_word(0);
+
+        counter.command_word(Command::MemoryWrite as u32);
+        counter.data_word(0xff);
+        counter.data_word(0xff);
+
+        assert_eq!(
+            counter.stats,
+            Stats {
+                commands: 6,
+                parameter_words: 17,
+                memory_writes: 1,
+                memory_words: 2,
+                read_words: 0,
+                window_changes: 3,
+                redundant_window_writes: 1,
+            }
+        );
+        assert_eq!(counter.stats.total_words(), 25);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn counts_driver_traffic() {
+        use crate::color::PixelFormat;
+        use crate::emulator::{Emulator, NoDelay, NoPin};
+        use crate::io::shim::OutputOnlyIoPin;
+        use crate::ILI9486;
+        use std::string::ToString;
+
+        let mut lcd: ILI9486<_, u8> = ILI9486::new(
+            &mut NoDelay,
+            PixelFormat::Rgb565,
+            Counting::new(Emulator::new()),
+            OutputOnlyIoPin::new(NoPin),
+        )
+        .unwrap();
+        let before = lcd.interface().stats();
+
+        lcd._draw_pixel(10, 10, 31, 0, 0).unwrap();
+        lcd._draw_pixel(11, 10, 31, 0, 0).unwrap();
+        lcd._draw_pixel(10, 11, 31, 0, 0).unwrap();
+        let stats = lcd.interface().stats() - before;
+        assert_eq!(stats.memory_writes, 3);
+        assert_eq!(stats.memory_words, 6);
+        assert_eq!(stats.window_changes, 3);
+        assert_eq!(stats.redundant_window_writes, 0);
+        assert_eq!(
+            stats.to_string(),
+            "6 commands, 12 parameter words, 3 memory writes (6 words), 0 read words, \
+             3 window changes, 0 redundant window writes"
+        );
+
+        // unchanged windows are skipped
+        lcd._draw_pixel(10, 11, 0, 0, 31).unwrap();
+        let stats = lcd.interface().stats() - before - stats;
+        assert_eq!(stats.commands, 1);
+        assert_eq!(stats.redundant_window_writes, 0);
+
+        let mut pixel = [(0, 0, 0)];
+        lcd.read_pixels(10, 11, 1, 1, &mut pixel).unwrap();
+        assert_eq!(lcd.writer().take().read_words, 4);
+        assert_eq!(lcd.writer().inner().pixel(10, 11), (0, 0, 0x3f));
+    }
+}